@@ -0,0 +1,12 @@
+Feature: Concurrent leasing of the same task
+
+  Scenario: Only one of many concurrent workers is granted the lease of a task
+    Given a started workflow
+    When 32 workers lease the same task at the same time
+    Then exactly one worker must be granted the lease
+    And the other workers must be told the remaining lease timeout
+
+  Scenario: Concurrent workers are given the same idempotency key of a task
+    Given a started workflow
+    When 32 workers generate the idempotency key of the same task at the same time
+    Then every worker must be given the same idempotency key
@@ -0,0 +1,84 @@
+import { Given, When, Then, BeforeAll } from '@cucumber/cucumber';
+import { expect } from 'chai';
+import { faker } from '@faker-js/faker';
+import { GrpcAdapter } from '@idempotent-transformer/grpc-adapter';
+import { LeaseCheckpointOutput } from '@idempotent-transformer/core';
+
+// Feature: Concurrent leasing of the same task
+
+//   Scenario: Only one of many concurrent workers is granted the lease of a task
+//     Given a started workflow
+//     When 32 workers lease the same task at the same time
+//     Then exactly one worker must be granted the lease
+//     And the other workers must be told the remaining lease timeout
+
+//   Scenario: Concurrent workers are given the same idempotency key of a task
+//     Given a started workflow
+//     When 32 workers generate the idempotency key of the same task at the same time
+//     Then every worker must be given the same idempotency key
+
+let rpcAdapter: GrpcAdapter;
+let workflowId: string;
+let fencingToken: number;
+let leases: LeaseCheckpointOutput[];
+let idempotencyKeys: string[];
+
+const isGranted = (lease: LeaseCheckpointOutput) =>
+  !lease.remainingLeaseTimeout && !(lease.value && lease.value.length > 0);
+
+BeforeAll(async () => {
+  rpcAdapter = new GrpcAdapter({
+    host: 'localhost',
+    port: 51000,
+  });
+});
+
+Given('a started workflow', async function () {
+  workflowId = faker.string.uuid();
+  const workflow = await rpcAdapter.startWorkflow({
+    workflowId,
+    name: 'concurrent-leasing-test',
+  });
+  fencingToken = workflow.fencingToken;
+});
+
+When('{int} workers lease the same task at the same time', async function (workers: number) {
+  leases = await Promise.all(
+    Array.from({ length: workers }, () =>
+      rpcAdapter.leaseCheckpoint({
+        workflowId,
+        fencingToken,
+        position: 0,
+        leaseTimeout: 30000,
+        idempotencyKey: 'task',
+      })
+    )
+  );
+});
+
+Then('exactly one worker must be granted the lease', async function () {
+  expect(leases.filter(isGranted)).to.have.lengthOf(1);
+});
+
+Then('the other workers must be told the remaining lease timeout', async function () {
+  for (const lease of leases.filter((lease) => !isGranted(lease))) {
+    expect(lease.remainingLeaseTimeout).to.be.greaterThan(0);
+  }
+});
+
+When(
+  '{int} workers generate the idempotency key of the same task at the same time',
+  async function (workers: number) {
+    idempotencyKeys = await Promise.all(
+      Array.from({ length: workers }, () =>
+        rpcAdapter
+          .generateIdempotencyKey({ workflowId, fencingToken, position: 0 })
+          .then((output) => output.idempotencyKey)
+      )
+    );
+  }
+);
+
+Then('every worker must be given the same idempotency key', async function () {
+  expect(new Set(idempotencyKeys).size).to.equal(1);
+});
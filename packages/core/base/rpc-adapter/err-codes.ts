@@ -2,4 +2,5 @@ export enum ErrCodes {
   FencingTokenExpired = 'fencing_token_expired',
   FencingTokenNotFound = 'fencing_token_not_found',
   NonDeterministicCheckpointFound = 'non_deterministic_checkpoint_found',
+  StaleLeaseRelease = 'stale_lease_release',
 }
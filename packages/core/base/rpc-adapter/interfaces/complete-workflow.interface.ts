@@ -1,7 +1,10 @@
+import { TSerialized } from '../../types/serialized.type';
+
 export interface CompleteWorkflowInput {
   workflowId: string;
   fencingToken: number;
   expireAfter: number;
+  result?: TSerialized;
 }
 
 export interface CompleteWorkflowOutput {}
@@ -1,6 +1,7 @@
 export interface ReleaseLeaseCheckpointInput {
   workflowId: string;
   position: number;
+  fencingToken?: number;
 }
 
 export interface ReleaseLeaseCheckpointOutput {}
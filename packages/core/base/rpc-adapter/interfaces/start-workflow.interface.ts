@@ -1,8 +1,14 @@
+import { TSerialized } from '../../types/serialized.type';
+
 export interface StartWorkflowInput {
   workflowId: string;
   name?: string;
+  input?: TSerialized;
+  executionTimeout?: number;
 }
 
 export interface StartWorkflowOutput {
   fencingToken: number;
+  completed?: boolean;
+  result?: Buffer;
 }
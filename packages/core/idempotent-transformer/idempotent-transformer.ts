@@ -30,12 +30,13 @@ export class IdempotentTransformer {
 
   async startWorkflow(
     workflowId: string,
-    { completedRetentionTime, name }: IdempotentTransformerOptions
+    { completedRetentionTime, name, executionTimeout }: IdempotentTransformerOptions
   ): Promise<IdempotentRunnerResult> {
     // 1 day
     const { fencingToken } = await this.rpcAdapter.startWorkflow({
       workflowId,
       name,
+      executionTimeout: executionTimeout ?? undefined,
     });
     this.logger?.debug(
       `Leased workflow ${workflowId} with fencing token ${fencingToken}  context bound checkpoints`
@@ -114,6 +115,7 @@ export class IdempotentTransformer {
           await this.rpcAdapter.releaseLeaseCheckpoint({
             workflowId,
             position,
+            fencingToken,
           });
           throw err;
         }
@@ -27,4 +27,10 @@ export interface IdempotentTransformerOptions {
    * The context name to use for the workflow. This is used to differentiate nested workflows.
    */
   name?: string;
+
+  /**
+   * The number of milliseconds the workflow may run from its first start before it times out.
+   * @default null
+   */
+  executionTimeout?: number | null;
 }
@@ -89,6 +89,12 @@ export class GrpcAdapter implements IdempotentRpcAdapter {
     if (input.name) {
       request.setContextName(input.name);
     }
+    if (input.input !== undefined) {
+      request.setInput(input.input as string);
+    }
+    if (input.executionTimeout !== undefined) {
+      request.setExecutionTimeout(input.executionTimeout);
+    }
 
     return new Promise((resolve, reject) => {
       this.client.workflow_start(request, (err, response) => {
@@ -98,6 +104,8 @@ export class GrpcAdapter implements IdempotentRpcAdapter {
         }
         resolve({
           fencingToken: response.getFencingToken(),
+          completed: response.getCompleted(),
+          result: response.hasResult() ? (response.getResult() as Buffer) : undefined,
         });
       });
     });
@@ -149,6 +157,9 @@ export class GrpcAdapter implements IdempotentRpcAdapter {
     request.setWorkflowId(input.workflowId);
     request.setFencingToken(input.fencingToken);
     request.setExpireAfter(input.expireAfter);
+    if (input.result !== undefined) {
+      request.setResult(input.result as string);
+    }
     return new Promise((resolve, reject) => {
       this.client.complete_workflow(request, (err, response) => {
         if (err) {
@@ -185,6 +196,9 @@ export class GrpcAdapter implements IdempotentRpcAdapter {
     const request = new ReleaseCheckpointRequest();
     request.setWorkflowId(input.workflowId);
     request.setPosition(input.position);
+    if (input.fencingToken !== undefined) {
+      request.setFencingToken(input.fencingToken);
+    }
     return new Promise((resolve, reject) => {
       this.client.release_checkpoint(request, (err, response) => {
         if (err) {
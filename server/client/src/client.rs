@@ -0,0 +1,311 @@
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use tonic::codegen::InterceptedService;
+use tonic::metadata::MetadataValue;
+use tonic::service::Interceptor;
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Request, Status};
+
+use crate::codec::{Codec, MessagePackCodec};
+use crate::compensation::Compensator;
+use crate::error::ClientError;
+use crate::proto::workflow_service_impl_client::WorkflowServiceImplClient;
+use crate::proto::{
+    CompensateWorkflowRequest, DueTimer, PollDueTimersRequest, SignalWorkflowRequest,
+    WorkflowStartRequest, WorkflowStatusRequest, WorkflowStatusResponse,
+};
+use crate::workflow::Workflow;
+
+/// 1 day, the retention of completed workflows used by the TypeScript client as well.
+pub const DEFAULT_COMPLETED_RETENTION_TIME: i64 = 1000 * 60 * 60 * 24;
+
+#[derive(Debug, Clone)]
+pub struct WorkflowOptions {
+    pub name: Option<String>,
+    /// Milliseconds a completed or failed workflow is kept before the server deletes it.
+    /// `None` uses the default retention of the namespace.
+    pub completed_retention_time: Option<i64>,
+    /// Milliseconds the workflow may run from its first start. A workflow still running then
+    /// times out and its workers get `abort` on their next checkpoint.
+    pub execution_timeout: Option<i64>,
+}
+
+impl Default for WorkflowOptions {
+    fn default() -> Self {
+        Self {
+            name: None,
+            completed_retention_time: Some(DEFAULT_COMPLETED_RETENTION_TIME),
+            execution_timeout: None,
+        }
+    }
+}
+
+/// The position of the parent a child workflow is started at.
+pub(crate) struct ParentPosition {
+    pub(crate) workflow_id: String,
+    pub(crate) branch: Vec<i64>,
+    pub(crate) position: i64,
+    /// Fencing token of the worker running the parent.
+    pub(crate) fencing_token: i64,
+}
+
+/// Credentials sent with every call, required when the server sets `API_KEYS` or `JWT_SECRET`.
+#[derive(Debug, Clone, Default)]
+pub enum Credentials {
+    #[default]
+    None,
+    /// Sent as `x-api-key` metadata.
+    ApiKey(String),
+    /// A JWT sent as `authorization: Bearer <token>`.
+    BearerToken(String),
+}
+
+impl Interceptor for Credentials {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let (key, value) = match self {
+            Credentials::None => return Ok(request),
+            Credentials::ApiKey(api_key) => ("x-api-key", api_key.clone()),
+            Credentials::BearerToken(token) => ("authorization", format!("Bearer {token}")),
+        };
+        let value = MetadataValue::try_from(value)
+            .map_err(|_| Status::invalid_argument("credentials are not valid metadata"))?;
+        request.metadata_mut().insert(key, value);
+        Ok(request)
+    }
+}
+
+/// Client of the idempotency server. It is cheap to clone since the channel is shared.
+/// For TLS pass a [`Channel`] built from an [`Endpoint`] with a `tls_config` to [`WorkflowClient::new`].
+pub struct WorkflowClient<C: Codec = MessagePackCodec> {
+    channel: Channel,
+    credentials: Credentials,
+    namespace: String,
+    worker_id: Option<String>,
+    codec: Arc<C>,
+}
+
+impl<C: Codec> Clone for WorkflowClient<C> {
+    fn clone(&self) -> Self {
+        Self {
+            channel: self.channel.clone(),
+            credentials: self.credentials.clone(),
+            namespace: self.namespace.clone(),
+            worker_id: self.worker_id.clone(),
+            codec: self.codec.clone(),
+        }
+    }
+}
+
+impl WorkflowClient<MessagePackCodec> {
+    pub async fn connect(endpoint: impl Into<String>) -> Result<Self, ClientError> {
+        let channel = Endpoint::from_shared(endpoint.into())?.connect().await?;
+        Ok(Self::new(channel))
+    }
+
+    pub fn new(channel: Channel) -> Self {
+        Self::with_codec(channel, MessagePackCodec)
+    }
+}
+
+impl<C: Codec> WorkflowClient<C> {
+    pub fn with_codec(channel: Channel, codec: C) -> Self {
+        Self {
+            channel,
+            credentials: Credentials::None,
+            namespace: String::new(),
+            worker_id: None,
+            codec: Arc::new(codec),
+        }
+    }
+
+    pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    ///
+    /// Namespace of every workflow started through this client, the server's default namespace if not set.
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
+
+    ///
+    /// Identity recorded with every lease this client takes, e.g. the pod name, so the server can
+    /// report which worker holds a step.
+    pub fn with_worker_id(mut self, worker_id: impl Into<String>) -> Self {
+        self.worker_id = Some(worker_id.into());
+        self
+    }
+
+    ///
+    /// The generated gRPC client for the calls that have no helper here, sending the credentials as well.
+    pub fn raw(&self) -> WorkflowServiceImplClient<InterceptedService<Channel, Credentials>> {
+        WorkflowServiceImplClient::with_interceptor(self.channel.clone(), self.credentials.clone())
+    }
+
+    ///
+    /// Starts or resumes the workflow and returns a handle holding a fresh fencing token.
+    /// Every other handle of the same workflow gets `abort` on its next checkpoint.
+    /// If the workflow was completed before, the handle holds its result instead, see [`Workflow::is_completed`].
+    pub async fn start_workflow(
+        &self,
+        workflow_id: impl Into<String>,
+        options: WorkflowOptions,
+    ) -> Result<Workflow<C>, ClientError> {
+        self.start(workflow_id.into(), None, None, options).await
+    }
+
+    ///
+    /// Like [`WorkflowClient::start_workflow`], storing `input` with the workflow. A restart keeps
+    /// the input of the first start.
+    pub async fn start_workflow_with_input<I: Serialize>(
+        &self,
+        workflow_id: impl Into<String>,
+        input: &I,
+        options: WorkflowOptions,
+    ) -> Result<Workflow<C>, ClientError> {
+        let input = self.codec.encode(input).map_err(ClientError::Encode)?;
+        self.start(workflow_id.into(), Some(input), None, options)
+            .await
+    }
+
+    pub(crate) async fn start(
+        &self,
+        workflow_id: String,
+        input: Option<Vec<u8>>,
+        parent: Option<ParentPosition>,
+        options: WorkflowOptions,
+    ) -> Result<Workflow<C>, ClientError> {
+        let (parent_workflow_id, parent_branch, parent_position, parent_fencing_token) =
+            match parent {
+                Some(parent) => (
+                    Some(parent.workflow_id),
+                    parent.branch,
+                    Some(parent.position),
+                    Some(parent.fencing_token),
+                ),
+                None => (None, Vec::new(), None, None),
+            };
+        let response = self
+            .raw()
+            .workflow_start(WorkflowStartRequest {
+                namespace: self.namespace.clone(),
+                workflow_id: workflow_id.clone(),
+                context_name: options.name.clone(),
+                input,
+                execution_timeout: options.execution_timeout,
+                parent_workflow_id,
+                parent_branch,
+                parent_position,
+                parent_fencing_token,
+            })
+            .await?
+            .into_inner();
+        Ok(Workflow::new(
+            self.clone(),
+            workflow_id,
+            response.fencing_token,
+            response.completed.then_some(response.result),
+            options,
+        ))
+    }
+
+    pub async fn workflow_status(
+        &self,
+        workflow_id: impl Into<String>,
+    ) -> Result<WorkflowStatusResponse, ClientError> {
+        let response = self
+            .raw()
+            .workflow_status(WorkflowStatusRequest {
+                namespace: self.namespace.clone(),
+                workflow_id: workflow_id.into(),
+                include_payloads: false,
+            })
+            .await?;
+        Ok(response.into_inner())
+    }
+
+    ///
+    /// Claims up to `limit` due timers of the namespace's running workflows, waiting up to
+    /// `wait_timeout` milliseconds for one if none is due. Start the workflow of each timer to
+    /// resume it; a timer the workflow didn't pass is returned again after the claim timeout.
+    pub async fn poll_due_timers(
+        &self,
+        limit: i64,
+        wait_timeout: Option<i64>,
+    ) -> Result<Vec<DueTimer>, ClientError> {
+        let response = self
+            .raw()
+            .poll_due_timers(PollDueTimersRequest {
+                namespace: self.namespace.clone(),
+                limit,
+                claim_timeout: None,
+                wait_timeout,
+            })
+            .await?;
+        Ok(response.into_inner().timers)
+    }
+
+    ///
+    /// Sends a signal to the workflow, which its next [`Workflow::await_signal`] of `name` receives.
+    /// Signals sent again with the same `signal_id`, e.g. by a retried webhook, are delivered once.
+    /// Returns the id of the signal.
+    pub async fn signal_workflow<T: Serialize>(
+        &self,
+        workflow_id: impl Into<String>,
+        name: impl Into<String>,
+        payload: &T,
+        signal_id: Option<String>,
+    ) -> Result<String, ClientError> {
+        let payload = self.codec.encode(payload).map_err(ClientError::Encode)?;
+        let response = self
+            .raw()
+            .signal_workflow(SignalWorkflowRequest {
+                namespace: self.namespace.clone(),
+                workflow_id: workflow_id.into(),
+                name: name.into(),
+                payload,
+                signal_id,
+            })
+            .await?;
+        Ok(response.into_inner().signal_id)
+    }
+
+    ///
+    /// Moves the workflow to compensating, so workers still running it get `abort`, and returns a
+    /// [`Compensator`] running the compensations its steps registered, last step first. Calling it
+    /// again hands the compensations over to the new compensator.
+    pub async fn compensate_workflow(
+        &self,
+        workflow_id: impl Into<String>,
+    ) -> Result<Compensator<C>, ClientError> {
+        let workflow_id = workflow_id.into();
+        let response = self
+            .raw()
+            .compensate_workflow(CompensateWorkflowRequest {
+                namespace: self.namespace.clone(),
+                workflow_id: workflow_id.clone(),
+            })
+            .await?;
+        Ok(Compensator::new(
+            self.clone(),
+            workflow_id,
+            response.into_inner().fencing_token,
+        ))
+    }
+
+    pub(crate) fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub(crate) fn worker_id(&self) -> Option<String> {
+        self.worker_id.clone()
+    }
+
+    pub(crate) fn codec(&self) -> &C {
+        &self.codec
+    }
+}
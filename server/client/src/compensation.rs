@@ -0,0 +1,151 @@
+use std::future::Future;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use tokio::time::sleep;
+use tracing::debug;
+
+use crate::client::WorkflowClient;
+use crate::codec::Codec;
+use crate::error::{BoxError, ClientError};
+use crate::proto::lease_compensation_response::Response;
+use crate::proto::{CompleteCompensationRequest, LeaseCompensationRequest};
+use crate::workflow::DEFAULT_LEASE_TIMEOUT;
+
+const MIN_LEASE_BACKOFF: Duration = Duration::from_millis(10);
+
+/// A compensation registered by [`crate::Workflow::register_compensation`], leased by this worker.
+#[derive(Debug, Clone)]
+pub struct PendingCompensation {
+    /// Path of the branch of the step, empty for the top level of the workflow.
+    pub branch: Vec<i64>,
+    /// Position of the step the compensation undoes.
+    pub position: i64,
+    pub name: String,
+    pub payload: Vec<u8>,
+}
+
+/// Runs the compensations of a workflow moved to compensating by [`WorkflowClient::compensate_workflow`],
+/// from the last registered step to the first.
+pub struct Compensator<C: Codec> {
+    client: WorkflowClient<C>,
+    workflow_id: String,
+    fencing_token: i64,
+}
+
+impl<C: Codec> Compensator<C> {
+    pub(crate) fn new(client: WorkflowClient<C>, workflow_id: String, fencing_token: i64) -> Self {
+        Self {
+            client,
+            workflow_id,
+            fencing_token,
+        }
+    }
+
+    pub fn workflow_id(&self) -> &str {
+        &self.workflow_id
+    }
+
+    pub fn fencing_token(&self) -> i64 {
+        self.fencing_token
+    }
+
+    ///
+    /// Decodes the payload the compensation was registered with.
+    pub fn payload<T: DeserializeOwned>(
+        &self,
+        compensation: &PendingCompensation,
+    ) -> Result<T, ClientError> {
+        self.client
+            .codec()
+            .decode(&compensation.payload)
+            .map_err(ClientError::Decode)
+    }
+
+    ///
+    /// Runs `handler` for each pending compensation until the workflow is compensated. A handler
+    /// error stops the run, and the compensation is handed out again once its lease expired.
+    pub async fn run<F, Fut, E>(&self, mut handler: F) -> Result<(), ClientError>
+    where
+        F: FnMut(PendingCompensation) -> Fut,
+        Fut: Future<Output = Result<(), E>>,
+        E: Into<BoxError>,
+    {
+        while let Some(compensation) = self.next(DEFAULT_LEASE_TIMEOUT).await? {
+            let (branch, position) = (compensation.branch.clone(), compensation.position);
+            handler(compensation)
+                .await
+                .map_err(|e| ClientError::Task(e.into()))?;
+            if self.complete(&branch, position).await? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Leases the pending compensation of the last step for `lease_timeout` milliseconds,
+    /// waiting while another worker holds it. Returns `None` once the workflow is compensated.
+    pub async fn next(
+        &self,
+        lease_timeout: i64,
+    ) -> Result<Option<PendingCompensation>, ClientError> {
+        loop {
+            let response = self
+                .client
+                .raw()
+                .lease_compensation(LeaseCompensationRequest {
+                    workflow_id: self.workflow_id.clone(),
+                    namespace: self.client.namespace().to_string(),
+                    fencing_token: self.fencing_token,
+                    lease_timeout,
+                    worker_id: self.client.worker_id(),
+                })
+                .await?
+                .into_inner();
+            match response.response {
+                Some(Response::Compensation(compensation)) => {
+                    return Ok(Some(PendingCompensation {
+                        branch: compensation.branch,
+                        position: compensation.position,
+                        name: compensation.name,
+                        payload: compensation.payload,
+                    }));
+                }
+                Some(Response::RemainingLeaseTimeout(remaining)) => {
+                    debug!(
+                        "Compensation of workflow {} is leased by {} for another {}ms",
+                        self.workflow_id,
+                        response
+                            .lease_worker_id
+                            .as_deref()
+                            .unwrap_or("another worker"),
+                        remaining
+                    );
+                    let backoff = Duration::from_millis((remaining / 10).max(0) as u64);
+                    sleep(backoff.max(MIN_LEASE_BACKOFF)).await;
+                }
+                Some(Response::Done(_)) | None => return Ok(None),
+            }
+        }
+    }
+
+    ///
+    /// Records that the compensation at `position` of `branch` ran. Returns whether it was the last
+    /// one, in which case the workflow is compensated.
+    pub async fn complete(&self, branch: &[i64], position: i64) -> Result<bool, ClientError> {
+        let response = self
+            .client
+            .raw()
+            .complete_compensation(CompleteCompensationRequest {
+                workflow_id: self.workflow_id.clone(),
+                namespace: self.client.namespace().to_string(),
+                fencing_token: self.fencing_token,
+                position,
+                branch: branch.to_vec(),
+                worker_id: self.client.worker_id(),
+            })
+            .await?;
+        Ok(response.into_inner().done)
+    }
+}
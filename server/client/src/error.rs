@@ -0,0 +1,82 @@
+use std::fmt;
+
+use tonic::{Code, Status};
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Errors returned by the client and the [`crate::Workflow`] handle.
+#[derive(Debug)]
+pub enum ClientError {
+    Transport(tonic::transport::Error),
+    /// The server rejected the request, [`ClientError::reason`] holds the server side reason
+    /// such as `fencing_token_expired` or `non_deterministic_checkpoint_found`.
+    Rpc(Box<Status>),
+    Encode(BoxError),
+    Decode(BoxError),
+    /// The task of a step returned an error. Its lease was released, so the step can be retried.
+    Task(BoxError),
+    /// The checkpoint was written, but another worker started the workflow with a newer fencing token.
+    Aborted,
+}
+
+impl ClientError {
+    ///
+    /// The reason sent by the server, `None` for errors raised on the client side.
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            ClientError::Rpc(status) => Some(status.message()),
+            _ => None,
+        }
+    }
+
+    ///
+    /// Whether the request can be retried as is, e.g. during a leader change.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Transport(_) => true,
+            ClientError::Rpc(status) => matches!(
+                status.code(),
+                Code::Unavailable | Code::Internal | Code::Unknown | Code::DeadlineExceeded
+            ),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Transport(e) => write!(f, "transport error: {e}"),
+            ClientError::Rpc(status) => write!(f, "{:?}: {}", status.code(), status.message()),
+            ClientError::Encode(e) => write!(f, "failed to encode value: {e}"),
+            ClientError::Decode(e) => write!(f, "failed to decode value: {e}"),
+            ClientError::Task(e) => write!(f, "task failed: {e}"),
+            ClientError::Aborted => write!(f, "workflow aborted by another worker"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Transport(e) => Some(e),
+            ClientError::Rpc(status) => Some(status.as_ref()),
+            ClientError::Encode(e) | ClientError::Decode(e) | ClientError::Task(e) => {
+                Some(e.as_ref())
+            }
+            ClientError::Aborted => None,
+        }
+    }
+}
+
+impl From<tonic::transport::Error> for ClientError {
+    fn from(e: tonic::transport::Error) -> Self {
+        ClientError::Transport(e)
+    }
+}
+
+impl From<Status> for ClientError {
+    fn from(status: Status) -> Self {
+        ClientError::Rpc(Box::new(status))
+    }
+}
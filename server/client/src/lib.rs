@@ -0,0 +1,36 @@
+//! Rust client of the idempotency server.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), idempotency_client::ClientError> {
+//! use idempotency_client::{WorkflowClient, WorkflowOptions};
+//!
+//! let client = WorkflowClient::connect("http://127.0.0.1:51000").await?;
+//! let mut workflow = client
+//!     .start_workflow("order-42", WorkflowOptions::default())
+//!     .await?;
+//! let charge_id: String = workflow
+//!     .step("charge", || async { Ok::<_, std::io::Error>("ch_1".to_string()) })
+//!     .await?;
+//! workflow.complete().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod client;
+mod codec;
+mod compensation;
+mod error;
+mod workflow;
+
+pub mod proto {
+    tonic::include_proto!("workflow_service");
+}
+
+pub use client::{Credentials, DEFAULT_COMPLETED_RETENTION_TIME, WorkflowClient, WorkflowOptions};
+pub use codec::{Codec, MessagePackCodec};
+pub use compensation::{Compensator, PendingCompensation};
+pub use error::{BoxError, ClientError};
+pub use workflow::{
+    Branch, ChildOutcome, DEFAULT_LEASE_TIMEOUT, HeartbeatSender, LeaseRenewer, StepOptions,
+    Workflow,
+};
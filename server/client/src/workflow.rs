@@ -0,0 +1,809 @@
+use std::future::Future;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tokio::time::{Instant, sleep};
+use tracing::debug;
+
+use crate::client::{ParentPosition, WorkflowClient, WorkflowOptions};
+use crate::codec::Codec;
+use crate::error::{BoxError, ClientError};
+use crate::proto::lease_checkpoint_response::Response;
+use crate::proto::{
+    AwaitChildWorkflowRequest, AwaitSignalRequest, CheckPointRequest, CompleteWorkflowRequest,
+    FailWorkflowRequest, GenerateIdempotencyKeyRequest, HeartbeatWorkflowRequest,
+    LeaseCheckpointRequest, LeaseCheckpointResponse, RegisterCompensationRequest,
+    ReleaseCheckpointRequest, RenewLeaseRequest, ScheduleTimerRequest,
+};
+
+/// 30 seconds, the lease timeout used by the TypeScript client as well.
+pub const DEFAULT_LEASE_TIMEOUT: i64 = 1000 * 30;
+
+// terminal statuses of a workflow, see `WorkflowStatusResponse`
+const CHILD_COMPLETED: i64 = 1;
+const CHILD_FAILED: i64 = 2;
+const CHILD_CANCELLED: i64 = 3;
+const CHILD_TIMED_OUT: i64 = 5;
+
+const CHECKPOINT_RETRY_DELAY: Duration = Duration::from_millis(100);
+const MIN_LEASE_BACKOFF: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone)]
+pub struct StepOptions {
+    /// Milliseconds the step may run before another worker can lease its position.
+    pub lease_timeout: i64,
+    /// When set, the server holds the lease request up to this many milliseconds while another
+    /// worker owns the position, instead of the client polling with a backoff.
+    pub wait_timeout: Option<i64>,
+}
+
+impl Default for StepOptions {
+    fn default() -> Self {
+        Self {
+            lease_timeout: DEFAULT_LEASE_TIMEOUT,
+            wait_timeout: None,
+        }
+    }
+}
+
+/// How a child workflow finished.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChildOutcome<T> {
+    /// The child was completed, holding its result if it was completed with one.
+    Completed(Option<T>),
+    Failed,
+    Cancelled,
+    TimedOut,
+    /// The compensations of the child ran, see [`WorkflowClient::compensate_workflow`].
+    Compensated,
+}
+
+/// Sends heartbeats of a workflow on behalf of the worker running it.
+pub struct HeartbeatSender<C: Codec> {
+    client: WorkflowClient<C>,
+    workflow_id: String,
+    fencing_token: i64,
+}
+
+impl<C: Codec> Clone for HeartbeatSender<C> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            workflow_id: self.workflow_id.clone(),
+            fencing_token: self.fencing_token,
+        }
+    }
+}
+
+impl<C: Codec> HeartbeatSender<C> {
+    ///
+    /// Records a heartbeat, so a supervisor listing workflows by `last_heartbeat_before` doesn't
+    /// take the workflow for abandoned. Fails with `fencing_token_expired` once another worker
+    /// took the workflow over, and with `workflow_paused` or `workflow_terminated` once it stopped.
+    pub async fn send(&self) -> Result<(), ClientError> {
+        self.client
+            .raw()
+            .heartbeat_workflow(HeartbeatWorkflowRequest {
+                namespace: self.client.namespace().to_string(),
+                workflow_id: self.workflow_id.clone(),
+                fencing_token: self.fencing_token,
+            })
+            .await?;
+        Ok(())
+    }
+}
+
+/// Renews the lease of a step on behalf of the worker running it.
+pub struct LeaseRenewer<C: Codec> {
+    client: WorkflowClient<C>,
+    workflow_id: String,
+    fencing_token: i64,
+    branch: Vec<i64>,
+    position: i64,
+}
+
+impl<C: Codec> Clone for LeaseRenewer<C> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            workflow_id: self.workflow_id.clone(),
+            fencing_token: self.fencing_token,
+            branch: self.branch.clone(),
+            position: self.position,
+        }
+    }
+}
+
+impl<C: Codec> LeaseRenewer<C> {
+    ///
+    /// Extends the lease of the step by `lease_timeout` milliseconds from now and returns the unix
+    /// timestamp in milliseconds it expires at. Fails with `lease_expired` once another worker may
+    /// have taken the step over, and with `leased_checkpoint_not_found` once the step was checkpointed.
+    pub async fn renew(&self, lease_timeout: i64) -> Result<i64, ClientError> {
+        let response = self
+            .client
+            .raw()
+            .renew_lease(RenewLeaseRequest {
+                namespace: self.client.namespace().to_string(),
+                workflow_id: self.workflow_id.clone(),
+                fencing_token: self.fencing_token,
+                position: self.position,
+                lease_timeout,
+                branch: self.branch.clone(),
+            })
+            .await?;
+        Ok(response.into_inner().lease_expire_at)
+    }
+}
+
+/// A started workflow. Steps are numbered by the order they run in,
+/// so a replay must run the same steps in the same order.
+pub struct Workflow<C: Codec> {
+    client: WorkflowClient<C>,
+    workflow_id: String,
+    fencing_token: i64,
+    position: i64,
+    /// Set when the workflow was completed before it was started, holding its result.
+    completed_result: Option<Option<Vec<u8>>>,
+    parent_position: Option<i64>,
+    /// Path of the branch the steps run in, empty for the top level of the workflow.
+    branch: Vec<i64>,
+    options: WorkflowOptions,
+}
+
+impl<C: Codec> Workflow<C> {
+    pub(crate) fn new(
+        client: WorkflowClient<C>,
+        workflow_id: String,
+        fencing_token: i64,
+        completed_result: Option<Option<Vec<u8>>>,
+        options: WorkflowOptions,
+    ) -> Self {
+        Self {
+            client,
+            workflow_id,
+            fencing_token,
+            position: 0,
+            completed_result,
+            parent_position: None,
+            branch: Vec::new(),
+            options,
+        }
+    }
+
+    pub fn workflow_id(&self) -> &str {
+        &self.workflow_id
+    }
+
+    pub fn fencing_token(&self) -> i64 {
+        self.fencing_token
+    }
+
+    ///
+    /// Position of the next step.
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+
+    ///
+    /// Whether the workflow was completed before it was started. Its steps can't run again,
+    /// [`Workflow::result`] returns the result it was completed with.
+    pub fn is_completed(&self) -> bool {
+        self.completed_result.is_some()
+    }
+
+    ///
+    /// The result of a workflow that was completed before it was started, `None` if it is still
+    /// running or was completed without a result.
+    pub fn result<T: DeserializeOwned>(&self) -> Result<Option<T>, ClientError> {
+        let Some(Some(result)) = &self.completed_result else {
+            return Ok(None);
+        };
+        let result = self
+            .client
+            .codec()
+            .decode(result)
+            .map_err(ClientError::Decode)?;
+        Ok(Some(result))
+    }
+
+    pub async fn step<T, F, Fut, E>(&mut self, name: &str, task: F) -> Result<T, ClientError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Into<BoxError>,
+    {
+        self.step_with_options(name, StepOptions::default(), task)
+            .await
+    }
+
+    ///
+    /// Runs `task` once across all workers of the workflow: leases the position, executes the task,
+    /// checkpoints its result and returns it. If the position was already checkpointed, the recorded
+    /// result is returned without running the task. `name` is the idempotency key and the task name
+    /// of the position, so a replay with a different step fails with `non_deterministic_checkpoint_found`.
+    pub async fn step_with_options<T, F, Fut, E>(
+        &mut self,
+        name: &str,
+        options: StepOptions,
+        task: F,
+    ) -> Result<T, ClientError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Into<BoxError>,
+    {
+        if let Some(value) = self.lease(name, &options).await? {
+            let value = self
+                .client
+                .codec()
+                .decode(&value)
+                .map_err(ClientError::Decode)?;
+            self.position += 1;
+            return Ok(value);
+        }
+        let lease_expiry = Instant::now() + Duration::from_millis(options.lease_timeout as u64);
+
+        debug!(
+            "Executing workflow {} position {} task {}",
+            self.workflow_id, self.position, name
+        );
+        let value = match task().await {
+            Ok(value) => value,
+            Err(e) => {
+                // let another attempt take the position right away
+                self.release().await?;
+                return Err(ClientError::Task(e.into()));
+            }
+        };
+        let encoded = self
+            .client
+            .codec()
+            .encode(&value)
+            .map_err(ClientError::Encode)?;
+
+        let abort = self.checkpoint(name, encoded, lease_expiry).await?;
+        // the result is persisted, but a newer worker owns the workflow now
+        if abort {
+            return Err(ClientError::Aborted);
+        }
+        self.position += 1;
+        Ok(value)
+    }
+
+    ///
+    /// Forks `count` branches at the current position, e.g. to run steps in parallel or one branch
+    /// per loop iteration, and moves the workflow to the next position. Each branch numbers its
+    /// steps on its own, so branches running concurrently still replay deterministically.
+    pub fn branches(&mut self, count: usize) -> Vec<Branch<C>> {
+        let branches = (0..count as i64)
+            .map(|index| Branch {
+                workflow: self.fork(index),
+            })
+            .collect();
+        self.position += 1;
+        branches
+    }
+
+    fn fork(&self, index: i64) -> Workflow<C> {
+        let mut branch = self.branch.clone();
+        branch.extend([self.position, index]);
+        Workflow {
+            client: self.client.clone(),
+            workflow_id: self.workflow_id.clone(),
+            fencing_token: self.fencing_token,
+            position: 0,
+            completed_result: None,
+            parent_position: None,
+            branch,
+            options: self.options.clone(),
+        }
+    }
+
+    ///
+    /// Records a timer at the current position firing `duration` from the first time the workflow
+    /// got here, and returns whether it fired. Once it fired the workflow moves past the timer.
+    /// Otherwise the worker should stop running the workflow and start it again once
+    /// [`WorkflowClient::poll_due_timers`] returns the timer.
+    pub async fn sleep(&mut self, name: &str, duration: Duration) -> Result<bool, ClientError> {
+        let response = self
+            .client
+            .raw()
+            .schedule_timer(ScheduleTimerRequest {
+                workflow_id: self.workflow_id.clone(),
+                namespace: self.client.namespace().to_string(),
+                fencing_token: self.fencing_token,
+                position: self.position,
+                branch: self.branch.clone(),
+                name: name.to_string(),
+                delay: duration.as_millis().try_into().unwrap_or(i64::MAX),
+            })
+            .await?
+            .into_inner();
+        if response.fired {
+            self.position += 1;
+        }
+        Ok(response.fired)
+    }
+
+    ///
+    /// Receives the oldest signal named `name` sent to the workflow at the current position, waiting
+    /// up to `wait_timeout` for one if none is pending. A replay receives the same signal again.
+    /// Returns `None` if no signal arrived, in which case the position is not advanced.
+    pub async fn await_signal<T: DeserializeOwned>(
+        &mut self,
+        name: &str,
+        wait_timeout: Option<Duration>,
+    ) -> Result<Option<T>, ClientError> {
+        let response = self
+            .client
+            .raw()
+            .await_signal(AwaitSignalRequest {
+                workflow_id: self.workflow_id.clone(),
+                namespace: self.client.namespace().to_string(),
+                fencing_token: self.fencing_token,
+                position: self.position,
+                branch: self.branch.clone(),
+                name: name.to_string(),
+                wait_timeout: wait_timeout
+                    .map(|wait_timeout| wait_timeout.as_millis().try_into().unwrap_or(i64::MAX)),
+            })
+            .await?
+            .into_inner();
+        let Some(payload) = response.payload else {
+            return Ok(None);
+        };
+        let value = self
+            .client
+            .codec()
+            .decode(&payload)
+            .map_err(ClientError::Decode)?;
+        self.position += 1;
+        Ok(Some(value))
+    }
+
+    ///
+    /// Registers how to undo the last step, e.g. the id of the payment to refund, which
+    /// [`WorkflowClient::compensate_workflow`] hands out if the workflow is unwound. A replay keeps
+    /// the payload registered by the first run.
+    pub async fn register_compensation<P: Serialize>(
+        &self,
+        name: &str,
+        payload: &P,
+    ) -> Result<(), ClientError> {
+        let payload = self
+            .client
+            .codec()
+            .encode(payload)
+            .map_err(ClientError::Encode)?;
+        self.client
+            .raw()
+            .register_compensation(RegisterCompensationRequest {
+                workflow_id: self.workflow_id.clone(),
+                namespace: self.client.namespace().to_string(),
+                fencing_token: self.fencing_token,
+                position: self.position - 1,
+                branch: self.branch.clone(),
+                name: name.to_string(),
+                payload,
+            })
+            .await?;
+        Ok(())
+    }
+
+    ///
+    /// Starts or resumes a child workflow at the current position, like [`WorkflowClient::start_workflow`].
+    /// The outcome of the child is recorded at this position once it finishes, so the position is
+    /// passed to [`Workflow::await_child`]. Several children can be started before awaiting them.
+    pub async fn start_child(
+        &mut self,
+        child_workflow_id: impl Into<String>,
+        options: WorkflowOptions,
+    ) -> Result<Workflow<C>, ClientError> {
+        let parent = ParentPosition {
+            workflow_id: self.workflow_id.clone(),
+            branch: self.branch.clone(),
+            position: self.position,
+            fencing_token: self.fencing_token,
+        };
+        let mut child = self
+            .client
+            .start(child_workflow_id.into(), None, Some(parent), options)
+            .await?;
+        child.parent_position = Some(self.position);
+        self.position += 1;
+        Ok(child)
+    }
+
+    ///
+    /// Position of the parent the outcome of this child workflow is recorded at, set by [`Workflow::start_child`].
+    pub fn parent_position(&self) -> Option<i64> {
+        self.parent_position
+    }
+
+    ///
+    /// Returns the outcome of the child workflow started at `position`, waiting up to `wait_timeout`
+    /// for it to finish. Returns `None` while the child is running. A replay returns the recorded
+    /// outcome again.
+    pub async fn await_child<T: DeserializeOwned>(
+        &self,
+        child_workflow_id: &str,
+        position: i64,
+        wait_timeout: Option<Duration>,
+    ) -> Result<Option<ChildOutcome<T>>, ClientError> {
+        let response = self
+            .client
+            .raw()
+            .await_child_workflow(AwaitChildWorkflowRequest {
+                workflow_id: self.workflow_id.clone(),
+                namespace: self.client.namespace().to_string(),
+                fencing_token: self.fencing_token,
+                position,
+                branch: self.branch.clone(),
+                child_workflow_id: child_workflow_id.to_string(),
+                wait_timeout: wait_timeout
+                    .map(|wait_timeout| wait_timeout.as_millis().try_into().unwrap_or(i64::MAX)),
+            })
+            .await?
+            .into_inner();
+        let Some(outcome) = response.outcome else {
+            return Ok(None);
+        };
+        let outcome = match outcome.status {
+            CHILD_COMPLETED => {
+                let result = outcome
+                    .result
+                    .map(|result| self.client.codec().decode(&result))
+                    .transpose()
+                    .map_err(ClientError::Decode)?;
+                ChildOutcome::Completed(result)
+            }
+            CHILD_FAILED => ChildOutcome::Failed,
+            CHILD_CANCELLED => ChildOutcome::Cancelled,
+            CHILD_TIMED_OUT => ChildOutcome::TimedOut,
+            _ => ChildOutcome::Compensated,
+        };
+        Ok(Some(outcome))
+    }
+
+    ///
+    /// Returns the recorded value of the position, or `None` once this worker holds the lease.
+    async fn lease(
+        &self,
+        name: &str,
+        options: &StepOptions,
+    ) -> Result<Option<Vec<u8>>, ClientError> {
+        let mut retried_fencing_token = false;
+        loop {
+            let result = self
+                .client
+                .raw()
+                .lease_checkpoint(LeaseCheckpointRequest {
+                    workflow_id: self.workflow_id.clone(),
+                    namespace: self.client.namespace().to_string(),
+                    fencing_token: self.fencing_token,
+                    lease_timeout: options.lease_timeout,
+                    position: self.position,
+                    branch: self.branch.clone(),
+                    idempotency_key: name.to_string(),
+                    wait_timeout: options.wait_timeout,
+                    task_name: Some(name.to_string()),
+                    worker_id: self.client.worker_id(),
+                })
+                .await;
+            match result {
+                Ok(response) => match response.into_inner() {
+                    LeaseCheckpointResponse {
+                        response: Some(Response::Value(value)),
+                        ..
+                    } => return Ok(Some(value)),
+                    LeaseCheckpointResponse {
+                        response: Some(Response::RemainingLeaseTimeout(remaining)),
+                        lease_worker_id,
+                        ..
+                    } => {
+                        debug!(
+                            "Workflow {} position {} is leased by {} for another {}ms",
+                            self.workflow_id,
+                            self.position,
+                            lease_worker_id.as_deref().unwrap_or("another worker"),
+                            remaining
+                        );
+                        let backoff = Duration::from_millis((remaining / 10).max(0) as u64);
+                        sleep(backoff.max(MIN_LEASE_BACKOFF)).await;
+                    }
+                    LeaseCheckpointResponse { response: None, .. } => return Ok(None),
+                },
+                // the fencing token might not have been replicated yet, so try one more time
+                Err(status)
+                    if status.message() == "fencing_token_not_found"
+                        && self.position == 0
+                        && !retried_fencing_token =>
+                {
+                    retried_fencing_token = true;
+                    sleep(Duration::from_millis(100)).await;
+                }
+                Err(status) => return Err(status.into()),
+            }
+        }
+    }
+
+    ///
+    /// Writes the checkpoint, retrying transient errors until the lease expires. Returns `abort`.
+    async fn checkpoint(
+        &self,
+        name: &str,
+        value: Vec<u8>,
+        lease_expiry: Instant,
+    ) -> Result<bool, ClientError> {
+        loop {
+            let result = self
+                .client
+                .raw()
+                .checkpoint(CheckPointRequest {
+                    workflow_id: self.workflow_id.clone(),
+                    namespace: self.client.namespace().to_string(),
+                    value: value.clone(),
+                    fencing_token: self.fencing_token,
+                    position: self.position,
+                    branch: self.branch.clone(),
+                    idempotency_key: name.to_string(),
+                    task_name: Some(name.to_string()),
+                    worker_id: self.client.worker_id(),
+                })
+                .await;
+            match result {
+                Ok(response) => return Ok(response.into_inner().abort),
+                Err(status) => {
+                    let error = ClientError::from(status);
+                    if !error.is_retryable()
+                        || Instant::now() + CHECKPOINT_RETRY_DELAY > lease_expiry
+                    {
+                        return Err(error);
+                    }
+                    debug!(
+                        "Error checkpointing workflow {} position {}: {}",
+                        self.workflow_id, self.position, error
+                    );
+                    sleep(CHECKPOINT_RETRY_DELAY).await;
+                }
+            }
+        }
+    }
+
+    async fn release(&self) -> Result<(), ClientError> {
+        let result = self
+            .client
+            .raw()
+            .release_checkpoint(ReleaseCheckpointRequest {
+                workflow_id: self.workflow_id.clone(),
+                namespace: self.client.namespace().to_string(),
+                position: self.position,
+                branch: self.branch.clone(),
+                fencing_token: Some(self.fencing_token),
+                worker_id: self.client.worker_id(),
+            })
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            // the lease expired and another worker took the position, which is left to that worker
+            Err(status) if status.message() == "stale_lease_release" => {
+                debug!(
+                    "Workflow {} position {} is leased by another worker",
+                    self.workflow_id, self.position
+                );
+                Ok(())
+            }
+            Err(status) => Err(status.into()),
+        }
+    }
+
+    ///
+    /// Tells the server this worker is still running the workflow, see [`HeartbeatSender::send`].
+    pub async fn heartbeat(&self) -> Result<(), ClientError> {
+        self.heartbeat_sender().send().await
+    }
+
+    ///
+    /// A handle sending heartbeats of this workflow, to be moved into a task that keeps sending
+    /// them while a long step runs.
+    pub fn heartbeat_sender(&self) -> HeartbeatSender<C> {
+        HeartbeatSender {
+            client: self.client.clone(),
+            workflow_id: self.workflow_id.clone(),
+            fencing_token: self.fencing_token,
+        }
+    }
+
+    ///
+    /// A handle renewing the lease of the step at the current position, to be moved into a task that
+    /// keeps renewing it while the step runs longer than its lease timeout.
+    pub fn lease_renewer(&self) -> LeaseRenewer<C> {
+        LeaseRenewer {
+            client: self.client.clone(),
+            workflow_id: self.workflow_id.clone(),
+            fencing_token: self.fencing_token,
+            branch: self.branch.clone(),
+            position: self.position,
+        }
+    }
+
+    ///
+    /// A key derived from the workflow and the current position, stable across replays.
+    pub async fn generate_idempotency_key(&self) -> Result<String, ClientError> {
+        let response = self
+            .client
+            .raw()
+            .generate_idempotency_key(GenerateIdempotencyKeyRequest {
+                workflow_id: self.workflow_id.clone(),
+                namespace: self.client.namespace().to_string(),
+                fencing_token: self.fencing_token,
+                position: self.position,
+                branch: self.branch.clone(),
+                task_name: None,
+            })
+            .await?;
+        Ok(response.into_inner().idempotency_key)
+    }
+
+    pub async fn complete(self) -> Result<(), ClientError> {
+        self.finish(None).await
+    }
+
+    ///
+    /// Completes the workflow and stores `result`, which every later start of the workflow returns.
+    pub async fn complete_with_result<T: Serialize>(self, result: &T) -> Result<(), ClientError> {
+        let result = self
+            .client
+            .codec()
+            .encode(result)
+            .map_err(ClientError::Encode)?;
+        self.finish(Some(result)).await
+    }
+
+    async fn finish(self, result: Option<Vec<u8>>) -> Result<(), ClientError> {
+        self.client
+            .raw()
+            .complete_workflow(CompleteWorkflowRequest {
+                namespace: self.client.namespace().to_string(),
+                workflow_id: self.workflow_id,
+                fencing_token: self.fencing_token,
+                expire_after: self.options.completed_retention_time,
+                result,
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn fail(self) -> Result<(), ClientError> {
+        self.client
+            .raw()
+            .fail_workflow(FailWorkflowRequest {
+                namespace: self.client.namespace().to_string(),
+                workflow_id: self.workflow_id,
+                fencing_token: self.fencing_token,
+                expire_after: self.options.completed_retention_time,
+            })
+            .await?;
+        Ok(())
+    }
+}
+
+/// A branch forked by [`Workflow::branches`]. Its steps are checkpointed at the positions of the
+/// branch, e.g. `3.1.0` and `3.1.1` for the first two steps of the second branch forked at position 3.
+pub struct Branch<C: Codec> {
+    workflow: Workflow<C>,
+}
+
+impl<C: Codec> Branch<C> {
+    ///
+    /// Path of the branch, e.g. `[3, 1]` for the second branch forked at position 3.
+    pub fn path(&self) -> &[i64] {
+        &self.workflow.branch
+    }
+
+    ///
+    /// Position of the next step within the branch.
+    pub fn position(&self) -> i64 {
+        self.workflow.position
+    }
+
+    pub async fn step<T, F, Fut, E>(&mut self, name: &str, task: F) -> Result<T, ClientError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Into<BoxError>,
+    {
+        self.workflow.step(name, task).await
+    }
+
+    ///
+    /// Runs a step of the branch, see [`Workflow::step_with_options`].
+    pub async fn step_with_options<T, F, Fut, E>(
+        &mut self,
+        name: &str,
+        options: StepOptions,
+        task: F,
+    ) -> Result<T, ClientError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Into<BoxError>,
+    {
+        self.workflow.step_with_options(name, options, task).await
+    }
+
+    ///
+    /// Forks branches within the branch, see [`Workflow::branches`].
+    pub fn branches(&mut self, count: usize) -> Vec<Branch<C>> {
+        self.workflow.branches(count)
+    }
+
+    ///
+    /// Records a timer at the current position of the branch, see [`Workflow::sleep`].
+    pub async fn sleep(&mut self, name: &str, duration: Duration) -> Result<bool, ClientError> {
+        self.workflow.sleep(name, duration).await
+    }
+
+    ///
+    /// Receives a signal at the current position of the branch, see [`Workflow::await_signal`].
+    pub async fn await_signal<T: DeserializeOwned>(
+        &mut self,
+        name: &str,
+        wait_timeout: Option<Duration>,
+    ) -> Result<Option<T>, ClientError> {
+        self.workflow.await_signal(name, wait_timeout).await
+    }
+
+    ///
+    /// Registers how to undo the last step of the branch, see [`Workflow::register_compensation`].
+    pub async fn register_compensation<P: Serialize>(
+        &self,
+        name: &str,
+        payload: &P,
+    ) -> Result<(), ClientError> {
+        self.workflow.register_compensation(name, payload).await
+    }
+
+    ///
+    /// Starts a child workflow at the current position of the branch, see [`Workflow::start_child`].
+    pub async fn start_child(
+        &mut self,
+        child_workflow_id: impl Into<String>,
+        options: WorkflowOptions,
+    ) -> Result<Workflow<C>, ClientError> {
+        self.workflow.start_child(child_workflow_id, options).await
+    }
+
+    ///
+    /// Returns the outcome of the child workflow started at `position` of the branch,
+    /// see [`Workflow::await_child`].
+    pub async fn await_child<T: DeserializeOwned>(
+        &self,
+        child_workflow_id: &str,
+        position: i64,
+        wait_timeout: Option<Duration>,
+    ) -> Result<Option<ChildOutcome<T>>, ClientError> {
+        self.workflow
+            .await_child(child_workflow_id, position, wait_timeout)
+            .await
+    }
+
+    ///
+    /// A handle renewing the lease of the step at the current position of the branch,
+    /// see [`Workflow::lease_renewer`].
+    pub fn lease_renewer(&self) -> LeaseRenewer<C> {
+        self.workflow.lease_renewer()
+    }
+
+    ///
+    /// A key derived from the workflow and the current position of the branch, stable across replays.
+    pub async fn generate_idempotency_key(&self) -> Result<String, ClientError> {
+        self.workflow.generate_idempotency_key().await
+    }
+}
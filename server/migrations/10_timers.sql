@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS Timers (
+    namespace VARCHAR(255) NOT NULL,
+    workflow_id VARCHAR(255) NOT NULL,
+    position INTEGER NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    fire_at TIMESTAMP NOT NULL,
+    -- set once a replay of the workflow passed the timer, so it is no longer polled
+    fired_at TIMESTAMP,
+    -- a poller resuming the workflow holds the timer until then
+    claimed_until TIMESTAMP,
+    created_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (namespace, workflow_id, position)
+);
+
+CREATE INDEX IF NOT EXISTS idx_timers_namespace_fire_at ON Timers (namespace, fire_at);
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS Signals (
+    namespace VARCHAR(255) NOT NULL,
+    workflow_id VARCHAR(255) NOT NULL,
+    signal_id VARCHAR(255) NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    payload BLOB NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    -- position of the await_signal the signal was delivered to, NULL while it is pending
+    delivered_position INTEGER,
+    PRIMARY KEY (namespace, workflow_id, signal_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_signals_pending ON Signals (namespace, workflow_id, name, created_at);
+CREATE INDEX IF NOT EXISTS idx_signals_delivered_position ON Signals (namespace, workflow_id, delivered_position);
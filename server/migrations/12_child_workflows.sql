@@ -0,0 +1,5 @@
+ALTER TABLE Workflows ADD COLUMN parent_workflow_id VARCHAR(255);
+ALTER TABLE Workflows ADD COLUMN parent_position INTEGER;
+
+-- one child per position of the parent, workflows without a parent are NULL and never conflict
+CREATE UNIQUE INDEX IF NOT EXISTS idx_workflows_parent ON Workflows (namespace, parent_workflow_id, parent_position);
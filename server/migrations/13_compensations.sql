@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS Compensations (
+    namespace VARCHAR(255) NOT NULL,
+    workflow_id VARCHAR(255) NOT NULL,
+    -- position of the step the compensation undoes
+    position INTEGER NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    payload BLOB NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    -- the worker running the compensation, like a lease of a position
+    lease_expire_at TIMESTAMP,
+    lease_fencing_token INTEGER,
+    lease_worker_id VARCHAR(255),
+    compensated_at TIMESTAMP,
+    PRIMARY KEY (namespace, workflow_id, position)
+);
@@ -0,0 +1,21 @@
+-- Steps of parallel branches are checkpointed at the positions of their branch, e.g. position 2 of
+-- branch '3.1'. The top level of a workflow is the empty branch, which existing rows belong to.
+ALTER TABLE Checkpoints ADD COLUMN branch VARCHAR(255) NOT NULL DEFAULT '';
+DROP INDEX IF EXISTS idx_checkpoints_namespace_workflow_id_position;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_checkpoints_namespace_workflow_id_branch_position ON Checkpoints (namespace, workflow_id, branch, position);
+CREATE TABLE Leases_New (
+    namespace VARCHAR(255) NOT NULL,
+    workflow_id VARCHAR(255) NOT NULL,
+    branch VARCHAR(255) NOT NULL DEFAULT '',
+    position INTEGER NOT NULL,
+    lease_id VARCHAR(36) NOT NULL,
+    fencing_token INTEGER NOT NULL,
+    worker_id VARCHAR(255),
+    lease_timeout INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (namespace, workflow_id, branch, position)
+);
+INSERT INTO Leases_New (namespace, workflow_id, position, lease_id, fencing_token, worker_id, lease_timeout, created_at)
+    SELECT namespace, workflow_id, position, lease_id, fencing_token, worker_id, lease_timeout, created_at FROM Leases;
+DROP TABLE Leases;
+ALTER TABLE Leases_New RENAME TO Leases;
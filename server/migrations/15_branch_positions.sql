@@ -0,0 +1,50 @@
+-- Timers, signals, child workflows and compensations are recorded at positions of branches like
+-- checkpoints. Existing rows belong to the top level of their workflow, the empty branch.
+CREATE TABLE Timers_New (
+    namespace VARCHAR(255) NOT NULL,
+    workflow_id VARCHAR(255) NOT NULL,
+    branch VARCHAR(255) NOT NULL DEFAULT '',
+    position INTEGER NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    fire_at TIMESTAMP NOT NULL,
+    fired_at TIMESTAMP,
+    claimed_until TIMESTAMP,
+    created_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (namespace, workflow_id, branch, position)
+);
+INSERT INTO Timers_New (namespace, workflow_id, position, name, fire_at, fired_at, claimed_until, created_at)
+    SELECT namespace, workflow_id, position, name, fire_at, fired_at, claimed_until, created_at FROM Timers;
+DROP TABLE Timers;
+ALTER TABLE Timers_New RENAME TO Timers;
+CREATE INDEX IF NOT EXISTS idx_timers_namespace_fire_at ON Timers (namespace, fire_at);
+
+-- set together with delivered_position
+ALTER TABLE Signals ADD COLUMN delivered_branch VARCHAR(255);
+UPDATE Signals SET delivered_branch = '' WHERE delivered_position IS NOT NULL;
+DROP INDEX IF EXISTS idx_signals_delivered_position;
+CREATE INDEX IF NOT EXISTS idx_signals_delivered_position ON Signals (namespace, workflow_id, delivered_branch, delivered_position);
+
+-- set together with parent_position
+ALTER TABLE Workflows ADD COLUMN parent_branch VARCHAR(255);
+UPDATE Workflows SET parent_branch = '' WHERE parent_workflow_id IS NOT NULL;
+DROP INDEX IF EXISTS idx_workflows_parent;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_workflows_parent ON Workflows (namespace, parent_workflow_id, parent_branch, parent_position);
+
+CREATE TABLE Compensations_New (
+    namespace VARCHAR(255) NOT NULL,
+    workflow_id VARCHAR(255) NOT NULL,
+    branch VARCHAR(255) NOT NULL DEFAULT '',
+    position INTEGER NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    payload BLOB NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    lease_expire_at TIMESTAMP,
+    lease_fencing_token INTEGER,
+    lease_worker_id VARCHAR(255),
+    compensated_at TIMESTAMP,
+    PRIMARY KEY (namespace, workflow_id, branch, position)
+);
+INSERT INTO Compensations_New (namespace, workflow_id, position, name, payload, created_at, lease_expire_at, lease_fencing_token, lease_worker_id, compensated_at)
+    SELECT namespace, workflow_id, position, name, payload, created_at, lease_expire_at, lease_fencing_token, lease_worker_id, compensated_at FROM Compensations;
+DROP TABLE Compensations;
+ALTER TABLE Compensations_New RENAME TO Compensations;
@@ -0,0 +1,3 @@
+CREATE INDEX IF NOT EXISTS idx_workflows_created_at_id ON Workflows (created_at, id);
+
+CREATE INDEX IF NOT EXISTS idx_workflows_status_created_at ON Workflows (status, created_at);
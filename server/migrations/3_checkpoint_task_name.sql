@@ -0,0 +1 @@
+ALTER TABLE Checkpoints ADD COLUMN task_name VARCHAR(255);
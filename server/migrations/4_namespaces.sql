@@ -0,0 +1,55 @@
+CREATE TABLE IF NOT EXISTS Namespaces (
+    name VARCHAR(255) NOT NULL PRIMARY KEY,
+    default_retention INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
+
+-- existing workflows keep working in the default namespace, which keeps the old retention of 0.
+-- Time functions are not deterministic across Raft nodes, so it dates back to the oldest workflow.
+INSERT INTO Namespaces (name, default_retention, created_at)
+    SELECT 'default', 0, COALESCE(MIN(created_at), 0) FROM Workflows;
+
+CREATE TABLE Workflows_New (
+    namespace VARCHAR(255) NOT NULL,
+    id VARCHAR(255) NOT NULL,
+    status INTEGER NOT NULL,
+    name VARCHAR(255),
+    expire_at TIMESTAMP,
+    completed_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (namespace, id)
+);
+INSERT INTO Workflows_New (namespace, id, status, name, expire_at, completed_at, created_at)
+    SELECT 'default', id, status, name, expire_at, completed_at, created_at FROM Workflows;
+DROP TABLE Workflows;
+ALTER TABLE Workflows_New RENAME TO Workflows;
+
+CREATE INDEX IF NOT EXISTS idx_workflows_namespace_created_at_id ON Workflows (namespace, created_at, id);
+CREATE INDEX IF NOT EXISTS idx_workflows_status_created_at ON Workflows (status, created_at);
+
+CREATE TABLE WorkflowFencingTokens_New (
+    namespace VARCHAR(255) NOT NULL,
+    workflow_id VARCHAR(255) NOT NULL,
+    fencing_token INTEGER NOT NULL,
+    PRIMARY KEY (namespace, workflow_id)
+);
+INSERT INTO WorkflowFencingTokens_New (namespace, workflow_id, fencing_token)
+    SELECT 'default', workflow_id, fencing_token FROM WorkflowFencingTokens;
+DROP TABLE WorkflowFencingTokens;
+ALTER TABLE WorkflowFencingTokens_New RENAME TO WorkflowFencingTokens;
+
+CREATE TABLE Checkpoints_New (
+    namespace VARCHAR(255) NOT NULL,
+    workflow_id VARCHAR(255) NOT NULL,
+    position INTEGER NOT NULL,
+    value BYTEA,
+    idempotency_key VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    task_name VARCHAR(255)
+);
+INSERT INTO Checkpoints_New (namespace, workflow_id, position, value, idempotency_key, created_at, task_name)
+    SELECT 'default', workflow_id, position, value, idempotency_key, created_at, task_name FROM Checkpoints;
+DROP TABLE Checkpoints;
+ALTER TABLE Checkpoints_New RENAME TO Checkpoints;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_checkpoints_namespace_workflow_id_position ON Checkpoints (namespace, workflow_id, position);
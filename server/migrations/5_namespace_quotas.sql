@@ -0,0 +1,4 @@
+ALTER TABLE Namespaces ADD COLUMN max_running_workflows INTEGER;
+ALTER TABLE Namespaces ADD COLUMN max_checkpoints_per_second INTEGER;
+ALTER TABLE Namespaces ADD COLUMN max_checkpoint_bytes INTEGER;
+ALTER TABLE Namespaces ADD COLUMN max_stored_bytes INTEGER;
@@ -0,0 +1,14 @@
+-- Leases used to live in the replicated cache and were granted under a distributed lock.
+-- As rows, a lease is granted by a single conditional write that the leader applies in log order.
+CREATE TABLE IF NOT EXISTS Leases (
+    namespace VARCHAR(255) NOT NULL,
+    workflow_id VARCHAR(255) NOT NULL,
+    position INTEGER NOT NULL,
+    -- unique per grant, tells the granted worker apart from others with the same fencing token
+    lease_id VARCHAR(36) NOT NULL,
+    fencing_token INTEGER NOT NULL,
+    worker_id VARCHAR(255),
+    lease_timeout INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (namespace, workflow_id, position)
+);
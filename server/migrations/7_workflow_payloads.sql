@@ -0,0 +1,8 @@
+-- kept apart from Workflows so listing workflows doesn't load the payloads
+CREATE TABLE IF NOT EXISTS WorkflowPayloads (
+    namespace VARCHAR(255) NOT NULL,
+    workflow_id VARCHAR(255) NOT NULL,
+    input BYTEA,
+    result BYTEA,
+    PRIMARY KEY (namespace, workflow_id)
+);
@@ -0,0 +1,3 @@
+ALTER TABLE Workflows ADD COLUMN execution_deadline TIMESTAMP;
+
+CREATE INDEX IF NOT EXISTS idx_workflows_status_execution_deadline ON Workflows (status, execution_deadline);
@@ -0,0 +1,3 @@
+ALTER TABLE Workflows ADD COLUMN last_heartbeat_at TIMESTAMP;
+
+CREATE INDEX IF NOT EXISTS idx_workflows_status_last_heartbeat_at ON Workflows (status, last_heartbeat_at);
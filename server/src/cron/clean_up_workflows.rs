@@ -3,11 +3,23 @@ use std::error::Error;
 use hiqlite::Client;
 use tokio_cron_scheduler::{Job, JobScheduler};
 
-use crate::services::workflow_service::handle_workflow_cleanup;
+use crate::services::workflow_service::{handle_workflow_cleanup, handle_workflow_timeouts};
 
 pub async fn clean_up_expired_workflows(client: &Client) -> Result<JobScheduler, Box<dyn Error>> {
     let scheduler = JobScheduler::new().await?;
     let cloned_client = client.clone();
+    let timeouts_client = client.clone();
+
+    scheduler
+        .add(Job::new_async("0/5 * * * * *", move |_uuid, _l| {
+            let job_client = timeouts_client.clone();
+            Box::pin(async move {
+                if let Err(e) = handle_workflow_timeouts(&job_client).await {
+                    eprintln!("Error during scheduled timeout: {e}");
+                }
+            })
+        })?)
+        .await?;
 
     scheduler
         .add(Job::new_async("1/10 * * * * *", move |_uuid, _l| {
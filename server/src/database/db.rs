@@ -23,8 +23,8 @@ impl CacheIndex for Cache {
     }
 }
 
-async fn node_config(node_id: u64, nodes: Vec<Server>) -> NodeConfig {
-    let mut config = NodeConfig::from_toml("hiqlite.toml", None, None)
+async fn node_config(config_path: &str, node_id: u64, nodes: Vec<Server>) -> NodeConfig {
+    let mut config = NodeConfig::from_toml(config_path, None, None)
         .await
         .unwrap();
     config.node_id = node_id;
@@ -37,8 +37,6 @@ async fn node_config(node_id: u64, nodes: Vec<Server>) -> NodeConfig {
         })
         .collect();
     config.log_statements = false;
-    config.tls_raft = None;
-    config.tls_api = None;
     config
 }
 
@@ -46,9 +44,10 @@ pub async fn get_client(
     args: Server,
     data_dir: String,
     nodes: Vec<Server>,
+    config_path: &str,
 ) -> Result<Client, Error> {
     let config = {
-        let mut config = node_config(args.id, nodes).await;
+        let mut config = node_config(config_path, args.id, nodes).await;
 
         // to make this example work when starting all nodes on the same host,
         // we need to save into custom folders for each one
@@ -12,7 +12,7 @@ impl Server {
         let parts: Vec<&str> = s.split_whitespace().collect();
         match &parts[..] {
             [id, api, raft] => Ok(Server {
-                id: id.parse::<u64>().unwrap(),
+                id: id.parse::<u64>()?,
                 addr_api: api.to_string(),
                 addr_raft: raft.to_string(),
             }),
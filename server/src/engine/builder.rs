@@ -0,0 +1,220 @@
+use std::env::var;
+use std::error::Error;
+
+use hiqlite::Client;
+use tokio_cron_scheduler::JobScheduler;
+use tracing::warn;
+
+use crate::cron::clean_up_workflows::clean_up_expired_workflows;
+use crate::database::db::{get_client, init_tables};
+use crate::database::server::Server;
+use crate::events::workflow_events::WorkflowEvents;
+use crate::quotas::quota_tracker::QuotaTracker;
+use crate::rpc_server::auth::{AuthConfig, Authenticator};
+use crate::rpc_server::server::{AuthenticatedWorkflowServer, WorkflowService, start_server};
+use crate::rpc_server::tls::TlsConfig;
+
+/// Cluster configuration of the node the engine runs on.
+#[derive(Debug, Clone)]
+pub struct EngineConfig {
+    pub node: Server,
+    pub nodes: Vec<Server>,
+    pub data_dir: String,
+    pub hiqlite_config_path: String,
+    pub auth: AuthConfig,
+    pub tls: Option<TlsConfig>,
+}
+
+impl EngineConfig {
+    ///
+    /// Reads `NODE_ID`, `ADDR_API`, `ADDR_RAFT`, `NODES` and `DATA_DIR`.
+    /// `HIQLITE_CONFIG` is optional and defaults to `hiqlite.toml`.
+    /// See [`AuthConfig::from_env`] and [`TlsConfig::from_env`] for the optional security settings.
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+        let node = Server {
+            id: required_var("NODE_ID")?.parse::<u64>()?,
+            addr_api: required_var("ADDR_API")?,
+            addr_raft: required_var("ADDR_RAFT")?,
+        };
+        let nodes = required_var("NODES")?
+            .split(",")
+            .map(Server::parse)
+            .collect::<Result<Vec<Server>, _>>()?;
+        Ok(Self {
+            node,
+            nodes,
+            data_dir: required_var("DATA_DIR")?,
+            hiqlite_config_path: var("HIQLITE_CONFIG").unwrap_or("hiqlite.toml".to_string()),
+            auth: AuthConfig::from_env()?,
+            tls: TlsConfig::from_env()?,
+        })
+    }
+}
+
+fn required_var(name: &str) -> Result<String, Box<dyn Error>> {
+    var(name).map_err(|_| format!("{name} is not set").into())
+}
+
+/// Builds an [`Engine`], either starting its own hiqlite node from an [`EngineConfig`]
+/// or reusing a client that was started with [`crate::database::db::Cache`].
+pub struct EngineBuilder {
+    config: Option<EngineConfig>,
+    client: Option<Client>,
+    cleanup_scheduler: bool,
+    auth: AuthConfig,
+    tls: Option<TlsConfig>,
+}
+
+impl Default for EngineBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EngineBuilder {
+    pub fn new() -> Self {
+        Self {
+            config: None,
+            client: None,
+            cleanup_scheduler: true,
+            auth: AuthConfig::default(),
+            tls: None,
+        }
+    }
+
+    ///
+    /// Also takes over the auth and TLS settings of the config.
+    pub fn config(mut self, config: EngineConfig) -> Self {
+        self.auth = config.auth.clone();
+        self.tls = config.tls.clone();
+        self.config = Some(config);
+        self
+    }
+
+    ///
+    /// Callers of the RPCs are accepted without credentials unless API keys or a JWT secret are set.
+    pub fn auth(mut self, auth: AuthConfig) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    ///
+    /// TLS of the listener started by [`Engine::serve`].
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    ///
+    /// Uses an already running hiqlite client instead of starting a node.
+    /// The engine runs its own migrations on it, so the database must be dedicated to the engine.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    ///
+    /// Whether the job deleting expired workflows runs on this node, enabled by default.
+    pub fn cleanup_scheduler(mut self, enabled: bool) -> Self {
+        self.cleanup_scheduler = enabled;
+        self
+    }
+
+    pub async fn build(self) -> Result<Engine, Box<dyn Error>> {
+        let client = match (self.client, self.config) {
+            (Some(client), _) => client,
+            (None, Some(config)) => {
+                get_client(
+                    config.node,
+                    config.data_dir,
+                    config.nodes,
+                    &config.hiqlite_config_path,
+                )
+                .await?
+            }
+            (None, None) => return Err("either a config or a client is required".into()),
+        };
+        init_tables(&client).await?;
+        let scheduler = if self.cleanup_scheduler {
+            Some(clean_up_expired_workflows(&client).await?)
+        } else {
+            None
+        };
+        if !self.auth.is_enabled() {
+            warn!(
+                "Authentication is disabled, every caller is accepted with the admin scope. Set API_KEYS or JWT_SECRET to require credentials."
+            );
+        }
+        let events = WorkflowEvents::spawn(&client);
+        Ok(Engine {
+            client,
+            events,
+            quota_tracker: QuotaTracker::new(),
+            scheduler,
+            authenticator: Authenticator::new(self.auth),
+            tls: self.tls,
+        })
+    }
+}
+
+/// A running idempotency engine: the hiqlite client, the event fan-out, the quota tracker
+/// and optionally the cleanup scheduler.
+pub struct Engine {
+    client: Client,
+    events: WorkflowEvents,
+    quota_tracker: QuotaTracker,
+    scheduler: Option<JobScheduler>,
+    authenticator: Authenticator,
+    tls: Option<TlsConfig>,
+}
+
+impl Engine {
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    ///
+    /// The gRPC service, cheap to clone because the client and the event sender are `Arc`s inside.
+    pub fn service(&self) -> WorkflowService {
+        WorkflowService::new(
+            self.client.clone(),
+            self.events.clone(),
+            self.quota_tracker.clone(),
+        )
+    }
+
+    ///
+    /// The authenticated tonic server to add next to other services, e.g. `Server::builder().add_service(engine.grpc_service())`.
+    pub fn grpc_service(&self) -> AuthenticatedWorkflowServer {
+        self.service().into_server(self.authenticator.clone())
+    }
+
+    ///
+    /// Serves only the engine on `rpc_addr` until the server stops.
+    pub async fn serve(&self, rpc_addr: &str) -> Result<(), Box<dyn Error>> {
+        start_server(
+            rpc_addr,
+            self.service(),
+            self.authenticator.clone(),
+            self.tls.as_ref(),
+        )
+        .await
+    }
+
+    ///
+    /// Stops the cleanup scheduler. The hiqlite client is left running, so its owner can shut it down.
+    pub async fn stop_scheduler(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(mut scheduler) = self.scheduler.take() {
+            scheduler.shutdown().await?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Stops the cleanup scheduler and shuts the hiqlite node down.
+    pub async fn shutdown(mut self) -> Result<(), Box<dyn Error>> {
+        self.stop_scheduler().await?;
+        self.client.shutdown().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+use hiqlite::Client;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio::time::{Instant, timeout_at};
+
+use crate::events::workflow_events::{WorkflowEvent, publish_workflow_event};
+
+/// Published on the cluster wide event bus whenever a lease on a position ends,
+/// either because the checkpoint was written or the lease was released.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaseEvent {
+    pub namespace: String,
+    pub workflow_id: String,
+    /// Encoded path of the branch, empty for the top level of the workflow.
+    pub branch: String,
+    pub position: i64,
+}
+
+pub async fn publish_lease_event(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    branch: &str,
+    position: i64,
+) {
+    let event = LeaseEvent {
+        namespace: namespace.to_string(),
+        workflow_id: workflow_id.to_string(),
+        branch: branch.to_string(),
+        position,
+    };
+    publish_workflow_event(client, WorkflowEvent::Lease(event)).await;
+}
+
+///
+/// Waits until the lease on the given position ends or the deadline is reached.
+/// Returns early when events were dropped, so the caller re-checks the lease state.
+pub async fn wait_for_lease_event(
+    receiver: &mut broadcast::Receiver<WorkflowEvent>,
+    namespace: &str,
+    workflow_id: &str,
+    branch: &str,
+    position: i64,
+    deadline: Instant,
+) {
+    let _ = timeout_at(deadline, async {
+        while let Ok(event) = receiver.recv().await {
+            if let WorkflowEvent::Lease(event) = event
+                && event.namespace == namespace
+                && event.workflow_id == workflow_id
+                && event.branch == branch
+                && event.position == position
+            {
+                return;
+            }
+        }
+    })
+    .await;
+}
@@ -0,0 +1,4 @@
+pub mod lease_events;
+pub mod signal_events;
+pub mod timer_events;
+pub mod workflow_events;
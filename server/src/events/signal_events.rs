@@ -0,0 +1,47 @@
+use hiqlite::Client;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio::time::{Instant, timeout_at};
+
+use crate::events::workflow_events::{WorkflowEvent, publish_workflow_event};
+
+/// Published on the cluster wide event bus whenever a signal is sent to a workflow.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalEvent {
+    pub namespace: String,
+    pub workflow_id: String,
+    pub name: String,
+}
+
+pub async fn publish_signal_event(client: &Client, namespace: &str, workflow_id: &str, name: &str) {
+    let event = SignalEvent {
+        namespace: namespace.to_string(),
+        workflow_id: workflow_id.to_string(),
+        name: name.to_string(),
+    };
+    publish_workflow_event(client, WorkflowEvent::Signal(event)).await;
+}
+
+///
+/// Waits until a signal with the given name is sent to the workflow or the deadline is reached.
+/// Returns early when events were dropped, so the caller re-checks the stored signals.
+pub async fn wait_for_signal_event(
+    receiver: &mut broadcast::Receiver<WorkflowEvent>,
+    namespace: &str,
+    workflow_id: &str,
+    name: &str,
+    deadline: Instant,
+) {
+    let _ = timeout_at(deadline, async {
+        while let Ok(event) = receiver.recv().await {
+            if let WorkflowEvent::Signal(event) = event
+                && event.namespace == namespace
+                && event.workflow_id == workflow_id
+                && event.name == name
+            {
+                return;
+            }
+        }
+    })
+    .await;
+}
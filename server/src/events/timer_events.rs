@@ -0,0 +1,40 @@
+use hiqlite::Client;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio::time::{Instant, timeout_at};
+
+use crate::events::workflow_events::{WorkflowEvent, publish_workflow_event};
+
+/// Published on the cluster wide event bus whenever a timer of the namespace may have become due
+/// earlier than the pollers expect, i.e. a timer was scheduled or its workflow was resumed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimerEvent {
+    pub namespace: String,
+}
+
+pub async fn publish_timer_event(client: &Client, namespace: &str) {
+    let event = TimerEvent {
+        namespace: namespace.to_string(),
+    };
+    publish_workflow_event(client, WorkflowEvent::Timer(event)).await;
+}
+
+///
+/// Waits until the timers of the namespace changed or the deadline is reached.
+/// Returns early when events were dropped, so the caller re-reads the next due timer.
+pub async fn wait_for_timer_event(
+    receiver: &mut broadcast::Receiver<WorkflowEvent>,
+    namespace: &str,
+    deadline: Instant,
+) {
+    let _ = timeout_at(deadline, async {
+        while let Ok(event) = receiver.recv().await {
+            if let WorkflowEvent::Timer(event) = event
+                && event.namespace == namespace
+            {
+                return;
+            }
+        }
+    })
+    .await;
+}
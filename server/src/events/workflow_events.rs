@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use bincode::error::DecodeError;
+use hiqlite::Client;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::{error, warn};
+
+use crate::events::lease_events::LeaseEvent;
+use crate::events::signal_events::SignalEvent;
+use crate::events::timer_events::TimerEvent;
+
+/// Starts every versioned event. Nodes that predate the versioning sent a bare lease event, whose
+/// encoding starts with the length of its namespace, which can't be this. Those nodes can't decode
+/// versioned events and log an error instead, so until they are upgraded their waiters only wake up
+/// on their timeouts.
+const EVENT_MARKER: u64 = u64::MAX;
+/// Version of the [`WorkflowEvent`] encoding. Nodes skip events of versions they don't know, so
+/// waiters on them fall back to their timeouts during a rolling upgrade instead of failing.
+pub const EVENT_VERSION: u32 = 1;
+
+/// Every event sent over the cluster wide event bus.
+///
+/// New variants are only appended, which leaves the encoding of the existing ones and therefore
+/// [`EVENT_VERSION`] unchanged. A node that doesn't know a variant yet logs a decode error for it
+/// and keeps handling the others, so only the waiters for the new event fall back to timeouts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WorkflowEvent {
+    Lease(LeaseEvent),
+    Signal(SignalEvent),
+    Timer(TimerEvent),
+}
+
+#[derive(Serialize)]
+struct VersionedEvent<'a> {
+    marker: u64,
+    version: u32,
+    event: &'a WorkflowEvent,
+}
+
+#[derive(Deserialize)]
+struct EventHeader {
+    marker: u64,
+    version: u32,
+}
+
+/// The lease event published by nodes that predate the versioned events.
+#[derive(Deserialize)]
+struct LegacyLeaseEvent {
+    namespace: String,
+    workflow_id: String,
+    position: i64,
+}
+
+///
+/// Decodes an event of the event bus, either a versioned one or the bare lease event of an older
+/// node. Returns `None` for events of a newer version.
+pub fn decode_workflow_event(bytes: &[u8]) -> Result<Option<WorkflowEvent>, DecodeError> {
+    let config = bincode::config::legacy();
+    let header = bincode::serde::decode_from_slice::<EventHeader, _>(bytes, config)
+        .ok()
+        .filter(|(header, _)| header.marker == EVENT_MARKER);
+    let Some((header, read)) = header else {
+        let (event, _) = bincode::serde::decode_from_slice::<LegacyLeaseEvent, _>(bytes, config)?;
+        return Ok(Some(WorkflowEvent::Lease(LeaseEvent {
+            namespace: event.namespace,
+            workflow_id: event.workflow_id,
+            branch: String::new(),
+            position: event.position,
+        })));
+    };
+    if header.version != EVENT_VERSION {
+        warn!(
+            "Skipping workflow event of unknown version {}",
+            header.version
+        );
+        return Ok(None);
+    }
+    let (event, _) = bincode::serde::decode_from_slice(&bytes[read..], config)?;
+    Ok(Some(event))
+}
+
+/// Fans out events received from the hiqlite event bus to every waiting request on this node.
+///
+/// The hiqlite listener is a single consumer channel, so only one task must read from it.
+#[derive(Clone)]
+pub struct WorkflowEvents {
+    sender: broadcast::Sender<WorkflowEvent>,
+}
+
+impl WorkflowEvents {
+    pub fn spawn(client: &Client) -> Self {
+        let (sender, _) = broadcast::channel(1024);
+        let listener_client = client.clone();
+        let listener_sender = sender.clone();
+        tokio::spawn(async move {
+            loop {
+                let bytes = match listener_client.listen_bytes().await {
+                    Ok((_, bytes)) => bytes,
+                    Err(e) => {
+                        error!("Error listening to workflow events: {}", e);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+                match decode_workflow_event(&bytes) {
+                    // no receivers only means nobody is waiting right now
+                    Ok(Some(event)) => {
+                        let _ = listener_sender.send(event);
+                    }
+                    Ok(None) => {}
+                    // a single undecodable event must not hold up the ones behind it
+                    Err(e) => error!("Error decoding workflow event: {}", e),
+                }
+            }
+        });
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<WorkflowEvent> {
+        self.sender.subscribe()
+    }
+}
+
+///
+/// Encodes an event the way it is sent over the event bus.
+pub fn encode_workflow_event(event: &WorkflowEvent) -> Vec<u8> {
+    bincode::serde::encode_to_vec(versioned(event), bincode::config::legacy())
+        .expect("workflow events are always encodable")
+}
+
+fn versioned(event: &WorkflowEvent) -> VersionedEvent<'_> {
+    VersionedEvent {
+        marker: EVENT_MARKER,
+        version: EVENT_VERSION,
+        event,
+    }
+}
+
+pub(crate) async fn publish_workflow_event(client: &Client, event: WorkflowEvent) {
+    // waiters fall back to polling or timeouts, so a lost notification only delays them
+    if let Err(e) = client.notify(&versioned(&event)).await {
+        error!("Error publishing workflow event: {}", e);
+    }
+}
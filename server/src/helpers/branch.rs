@@ -0,0 +1,41 @@
+use crate::helpers::common::return_error_if_true;
+use crate::helpers::errors::WorkflowError;
+
+/// The branch of the steps a workflow runs one after the other.
+pub const TOP_LEVEL_BRANCH: &str = "";
+
+/// Deepest branch accepted, so an encoded branch fits its column.
+const MAX_BRANCH_DEPTH: usize = 12;
+
+///
+/// Encodes the path of a branch, e.g. `[3, 1]`, into the form stored with its checkpoints and
+/// leases, e.g. `3.1`. The top level of a workflow is the empty path.
+pub fn encode_branch(branch: &[i64]) -> Result<String, WorkflowError> {
+    return_error_if_true(
+        branch.len() > MAX_BRANCH_DEPTH || branch.iter().any(|segment| *segment < 0),
+        WorkflowError::InvalidArgument("invalid_branch"),
+    )?;
+    Ok(branch
+        .iter()
+        .map(|segment| segment.to_string())
+        .collect::<Vec<_>>()
+        .join("."))
+}
+
+///
+/// Decodes a branch encoded by `encode_branch`.
+pub fn decode_branch(branch: &str) -> Vec<i64> {
+    branch
+        .split('.')
+        .filter_map(|segment| segment.parse().ok())
+        .collect()
+}
+
+///
+/// Full path of a position of a branch, e.g. `[3, 1, 2]` for position 2 of branch `3.1`. Paths
+/// compare like the steps run, a position before the branches forked at it.
+pub fn position_path(branch: &str, position: i64) -> Vec<i64> {
+    let mut path = decode_branch(branch);
+    path.push(position);
+    path
+}
@@ -1,8 +1,5 @@
-use std::error::Error;
+use crate::helpers::errors::WorkflowError;
 
-pub fn return_error_if_true(
-    condition: bool,
-    status: Box<dyn Error + Send + Sync>,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    if condition { Err(status) } else { Ok(()) }
+pub fn return_error_if_true(condition: bool, error: WorkflowError) -> Result<(), WorkflowError> {
+    if condition { Err(error) } else { Ok(()) }
 }
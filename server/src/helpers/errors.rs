@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// Errors returned by the repositories and services.
+///
+/// Every variant has a stable `reason` that is sent to clients as the status message and
+/// as the reason of the `google.rpc.ErrorInfo` detail, so clients can branch on it.
+#[derive(Debug)]
+pub enum WorkflowError {
+    WorkflowNotFound,
+    NamespaceNotFound,
+    NamespaceAlreadyExists,
+    /// Namespaces can only be deleted once all their workflows expired.
+    NamespaceNotEmpty,
+    FencingTokenNotFound,
+    FencingTokenExpired {
+        current_fencing_token: i64,
+        sent_fencing_token: i64,
+    },
+    /// A replay reached a position that was recorded with a different idempotency key or task,
+    /// which means the workflow code changed the order of its steps.
+    NonDeterministicCheckpoint(Box<CheckpointMismatch>),
+    LeaseNotFound,
+    /// Compensations can only be registered for positions whose step was recorded.
+    CheckpointNotFound,
+    LeaseExpired,
+    /// The position is leased by another worker, which is still running the task.
+    LeaseHeld {
+        lease_fencing_token: i64,
+        lease_worker_id: Option<String>,
+    },
+    /// A release was sent by another worker than the one holding the lease.
+    StaleLeaseRelease {
+        lease_fencing_token: i64,
+        lease_worker_id: Option<String>,
+        sent_fencing_token: i64,
+    },
+    WorkflowPaused,
+    /// The workflow is being compensated, its steps can't run anymore.
+    WorkflowCompensating,
+    /// Compensations are only handed out while the workflow is compensating.
+    WorkflowNotCompensating {
+        status: i64,
+    },
+    WorkflowTerminated {
+        status: i64,
+    },
+    InvalidStatusTransition {
+        status: i64,
+    },
+    InvalidArgument(&'static str),
+    /// The caller sent no credentials or credentials that are not valid.
+    Unauthenticated,
+    /// The caller is authenticated but lacks the scope the RPC requires.
+    PermissionDenied {
+        required_scope: &'static str,
+    },
+    /// A quota of the namespace is exhausted, e.g. `max_running_workflows`.
+    QuotaExceeded {
+        quota: &'static str,
+        limit: i64,
+    },
+    /// The caller's credentials are restricted to other namespaces.
+    NamespaceNotPermitted {
+        namespace: String,
+    },
+    Storage(hiqlite::Error),
+    Internal(String),
+}
+
+/// What was recorded at a position and what a replay sent there instead.
+#[derive(Debug)]
+pub struct CheckpointMismatch {
+    /// Encoded path of the branch of the position, empty for the top level of the workflow.
+    pub branch: String,
+    pub position: i64,
+    pub recorded_idempotency_key: String,
+    pub received_idempotency_key: String,
+    pub recorded_task_name: Option<String>,
+    pub received_task_name: Option<String>,
+}
+
+impl WorkflowError {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            WorkflowError::WorkflowNotFound => "workflow_not_found",
+            WorkflowError::NamespaceNotFound => "namespace_not_found",
+            WorkflowError::NamespaceAlreadyExists => "namespace_already_exists",
+            WorkflowError::NamespaceNotEmpty => "namespace_not_empty",
+            WorkflowError::FencingTokenNotFound => "fencing_token_not_found",
+            WorkflowError::FencingTokenExpired { .. } => "fencing_token_expired",
+            WorkflowError::NonDeterministicCheckpoint(_) => "non_deterministic_checkpoint_found",
+            WorkflowError::LeaseNotFound => "leased_checkpoint_not_found",
+            WorkflowError::CheckpointNotFound => "checkpoint_not_found",
+            WorkflowError::LeaseExpired => "lease_expired",
+            WorkflowError::LeaseHeld { .. } => "lease_held_by_another_worker",
+            WorkflowError::StaleLeaseRelease { .. } => "stale_lease_release",
+            WorkflowError::WorkflowPaused => "workflow_paused",
+            WorkflowError::WorkflowCompensating => "workflow_compensating",
+            WorkflowError::WorkflowNotCompensating { .. } => "workflow_not_compensating",
+            WorkflowError::WorkflowTerminated { .. } => "workflow_terminated",
+            WorkflowError::InvalidStatusTransition { .. } => "invalid_workflow_status_transition",
+            WorkflowError::InvalidArgument(reason) => reason,
+            WorkflowError::Unauthenticated => "unauthenticated",
+            WorkflowError::PermissionDenied { .. } => "permission_denied",
+            WorkflowError::NamespaceNotPermitted { .. } => "namespace_not_permitted",
+            WorkflowError::QuotaExceeded { .. } => "quota_exceeded",
+            WorkflowError::Storage(_) => "storage_error",
+            WorkflowError::Internal(_) => "internal_error",
+        }
+    }
+
+    /// Machine readable context of the error, e.g. the current and the sent fencing token.
+    pub fn metadata(&self) -> HashMap<String, String> {
+        let mut metadata = HashMap::new();
+        match self {
+            WorkflowError::FencingTokenExpired {
+                current_fencing_token,
+                sent_fencing_token,
+            } => {
+                metadata.insert(
+                    "current_fencing_token".to_string(),
+                    current_fencing_token.to_string(),
+                );
+                metadata.insert(
+                    "sent_fencing_token".to_string(),
+                    sent_fencing_token.to_string(),
+                );
+            }
+            WorkflowError::LeaseHeld {
+                lease_fencing_token,
+                lease_worker_id,
+            } => {
+                metadata.insert(
+                    "lease_fencing_token".to_string(),
+                    lease_fencing_token.to_string(),
+                );
+                if let Some(worker_id) = lease_worker_id {
+                    metadata.insert("lease_worker_id".to_string(), worker_id.clone());
+                }
+            }
+            WorkflowError::StaleLeaseRelease {
+                lease_fencing_token,
+                lease_worker_id,
+                sent_fencing_token,
+            } => {
+                metadata.insert(
+                    "lease_fencing_token".to_string(),
+                    lease_fencing_token.to_string(),
+                );
+                if let Some(worker_id) = lease_worker_id {
+                    metadata.insert("lease_worker_id".to_string(), worker_id.clone());
+                }
+                metadata.insert(
+                    "sent_fencing_token".to_string(),
+                    sent_fencing_token.to_string(),
+                );
+            }
+            WorkflowError::NonDeterministicCheckpoint(mismatch) => {
+                let CheckpointMismatch {
+                    branch,
+                    position,
+                    recorded_idempotency_key,
+                    received_idempotency_key,
+                    recorded_task_name,
+                    received_task_name,
+                } = mismatch.as_ref();
+                metadata.insert("branch".to_string(), branch.clone());
+                metadata.insert("position".to_string(), position.to_string());
+                // the full path of the position, e.g. `3.1.2` for position 2 of branch `3.1`
+                let path = if branch.is_empty() {
+                    position.to_string()
+                } else {
+                    format!("{branch}.{position}")
+                };
+                metadata.insert("path".to_string(), path);
+                metadata.insert(
+                    "recorded_idempotency_key".to_string(),
+                    recorded_idempotency_key.clone(),
+                );
+                metadata.insert(
+                    "received_idempotency_key".to_string(),
+                    received_idempotency_key.clone(),
+                );
+                if let Some(task_name) = recorded_task_name {
+                    metadata.insert("recorded_task_name".to_string(), task_name.clone());
+                }
+                if let Some(task_name) = received_task_name {
+                    metadata.insert("received_task_name".to_string(), task_name.clone());
+                }
+            }
+            WorkflowError::WorkflowTerminated { status }
+            | WorkflowError::WorkflowNotCompensating { status }
+            | WorkflowError::InvalidStatusTransition { status } => {
+                metadata.insert("status".to_string(), status.to_string());
+            }
+            WorkflowError::PermissionDenied { required_scope } => {
+                metadata.insert("required_scope".to_string(), required_scope.to_string());
+            }
+            WorkflowError::QuotaExceeded { quota, limit } => {
+                metadata.insert("quota".to_string(), quota.to_string());
+                metadata.insert("limit".to_string(), limit.to_string());
+            }
+            WorkflowError::NamespaceNotPermitted { namespace } => {
+                metadata.insert("namespace".to_string(), namespace.clone());
+            }
+            _ => {}
+        }
+        metadata
+    }
+}
+
+impl fmt::Display for WorkflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkflowError::Storage(e) => write!(f, "{}: {}", self.reason(), e),
+            WorkflowError::Internal(message) => write!(f, "{}: {}", self.reason(), message),
+            _ => write!(f, "{}", self.reason()),
+        }
+    }
+}
+
+impl std::error::Error for WorkflowError {}
+
+impl From<hiqlite::Error> for WorkflowError {
+    fn from(e: hiqlite::Error) -> Self {
+        WorkflowError::Storage(e)
+    }
+}
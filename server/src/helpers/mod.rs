@@ -1 +1,4 @@
+pub mod branch;
 pub mod common;
+pub mod errors;
+pub mod pagination;
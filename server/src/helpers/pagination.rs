@@ -0,0 +1,23 @@
+///
+/// Encodes the sort key of the last returned row into an opaque page cursor.
+pub fn encode_cursor(created_at: i64, id: &str) -> String {
+    format!("{created_at}:{id}")
+        .bytes()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+///
+/// Decodes a page cursor created by `encode_cursor`. Returns `None` for malformed cursors.
+pub fn decode_cursor(cursor: &str) -> Option<(i64, String)> {
+    if !cursor.len().is_multiple_of(2) {
+        return None;
+    }
+    let bytes = (0..cursor.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(cursor.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    let decoded = String::from_utf8(bytes).ok()?;
+    let (created_at, id) = decoded.split_once(':')?;
+    Some((created_at.parse::<i64>().ok()?, id.to_string()))
+}
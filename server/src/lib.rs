@@ -0,0 +1,14 @@
+pub mod cron;
+pub mod database;
+pub mod engine;
+pub mod events;
+pub mod helpers;
+pub mod metrics;
+pub mod quotas;
+pub mod repositories;
+pub mod rpc_server;
+pub mod schema;
+pub mod services;
+pub mod telemetry;
+
+pub use engine::builder::{Engine, EngineBuilder, EngineConfig};
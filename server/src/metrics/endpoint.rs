@@ -0,0 +1,73 @@
+use std::env::var;
+use std::error::Error;
+
+use axum::Router;
+use axum::extract::State;
+use axum::http::{StatusCode, header};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use chrono::Utc;
+use hiqlite::Client;
+use prometheus::{Encoder, TextEncoder};
+use tracing::error;
+
+use crate::metrics::workflow_metrics::metrics;
+use crate::repositories::lease_checkpoint::count_active_leases;
+use crate::repositories::workflows::count_stale_workflows;
+
+/// 1 minute, the default age of the last heartbeat after which a running workflow counts as stale.
+const DEFAULT_STALE_HEARTBEAT_AFTER: i64 = 1000 * 60;
+
+#[derive(Clone)]
+struct MetricsState {
+    client: Client,
+    stale_heartbeat_after: i64,
+}
+
+async fn render_metrics(State(state): State<MetricsState>) -> impl IntoResponse {
+    let client = &state.client;
+    // the Leases table is replicated, so every node reports the leases of the whole cluster
+    match count_active_leases(client).await {
+        Ok(active_leases) => metrics().active_leases.set(active_leases as i64),
+        Err(e) => error!("Error counting active leases: {}", e),
+    }
+    let heartbeat_before = Utc::now().timestamp_millis() - state.stale_heartbeat_after;
+    match count_stale_workflows(client, heartbeat_before).await {
+        Ok(stale_workflows) => metrics().stale_workflows.set(stale_workflows),
+        Err(e) => error!("Error counting stale workflows: {}", e),
+    }
+    let mut buffer = Vec::new();
+    let encoder = TextEncoder::new();
+    if let Err(e) = encoder.encode(&metrics().registry.gather(), &mut buffer) {
+        error!("Error encoding metrics: {}", e);
+        return (StatusCode::INTERNAL_SERVER_ERROR, Vec::new()).into_response();
+    }
+    (
+        [(header::CONTENT_TYPE, encoder.format_type().to_string())],
+        buffer,
+    )
+        .into_response()
+}
+
+///
+/// Router serving `GET /metrics` in the Prometheus text format, to be merged into an existing axum app.
+/// `STALE_HEARTBEAT_AFTER` sets the heartbeat age in milliseconds after which a workflow counts as stale.
+pub fn metrics_router(client: Client) -> Router {
+    let stale_heartbeat_after = var("STALE_HEARTBEAT_AFTER")
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_STALE_HEARTBEAT_AFTER);
+    Router::new()
+        .route("/metrics", get(render_metrics))
+        .with_state(MetricsState {
+            client,
+            stale_heartbeat_after,
+        })
+}
+
+pub async fn serve_metrics(metrics_addr: &str, client: Client) -> Result<(), Box<dyn Error>> {
+    let listener = tokio::net::TcpListener::bind(metrics_addr).await?;
+    println!("Metrics listening on {metrics_addr}");
+    axum::serve(listener, metrics_router(client)).await?;
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use std::sync::LazyLock;
+
+use prometheus::{
+    Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    exponential_buckets,
+};
+
+/// Metrics of the engine, registered in their own registry so embedding processes can keep theirs.
+pub struct WorkflowMetrics {
+    pub registry: Registry,
+    /// RPC latency in seconds by RPC name.
+    pub rpc_duration_seconds: HistogramVec,
+    /// Failed RPCs by RPC name and gRPC code.
+    pub rpc_errors_total: IntCounterVec,
+    /// Leases that did not expire yet, set when the metrics are scraped.
+    pub active_leases: IntGauge,
+    /// Running workflows whose last heartbeat is older than `STALE_HEARTBEAT_AFTER`, set when the metrics are scraped.
+    pub stale_workflows: IntGauge,
+    /// Lease requests answered with `remaining_lease_timeout` because another worker holds the position.
+    pub lease_contention_total: IntCounter,
+    /// Requests rejected with `fencing_token_expired` and checkpoints answered with `abort`, by RPC name.
+    pub fencing_token_rejections_total: IntCounterVec,
+    /// Size of checkpoint values in bytes.
+    pub checkpoint_value_bytes: Histogram,
+    /// Rows deleted by the cleanup job by table.
+    pub cleanup_deleted_rows_total: IntCounterVec,
+    /// Requests rejected with `quota_exceeded` by namespace and quota.
+    pub quota_rejections_total: IntCounterVec,
+    /// Workflows timed out by their execution deadline, by namespace.
+    pub workflow_timeouts_total: IntCounterVec,
+}
+
+impl WorkflowMetrics {
+    fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new_custom(Some("idempotency".to_string()), None)?;
+        let rpc_duration_seconds = HistogramVec::new(
+            HistogramOpts::new("rpc_duration_seconds", "RPC latency in seconds"),
+            &["rpc"],
+        )?;
+        let rpc_errors_total = IntCounterVec::new(
+            Opts::new("rpc_errors_total", "Failed RPCs by gRPC code"),
+            &["rpc", "code"],
+        )?;
+        let active_leases = IntGauge::new("active_leases", "Leases that did not expire yet")?;
+        let stale_workflows = IntGauge::new(
+            "stale_workflows",
+            "Running workflows whose workers stopped sending heartbeats",
+        )?;
+        let lease_contention_total = IntCounter::new(
+            "lease_contention_total",
+            "Lease requests rejected because another worker holds the position",
+        )?;
+        let fencing_token_rejections_total = IntCounterVec::new(
+            Opts::new(
+                "fencing_token_rejections_total",
+                "Requests rejected or aborted because of a stale fencing token",
+            ),
+            &["rpc"],
+        )?;
+        let checkpoint_value_bytes = Histogram::with_opts(
+            HistogramOpts::new(
+                "checkpoint_value_bytes",
+                "Size of checkpoint values in bytes",
+            )
+            .buckets(exponential_buckets(64.0, 4.0, 10)?),
+        )?;
+        let cleanup_deleted_rows_total = IntCounterVec::new(
+            Opts::new(
+                "cleanup_deleted_rows_total",
+                "Rows deleted by the cleanup of expired workflows",
+            ),
+            &["table"],
+        )?;
+        let quota_rejections_total = IntCounterVec::new(
+            Opts::new(
+                "quota_rejections_total",
+                "Requests rejected because a namespace quota is exhausted",
+            ),
+            &["namespace", "quota"],
+        )?;
+        let workflow_timeouts_total = IntCounterVec::new(
+            Opts::new(
+                "workflow_timeouts_total",
+                "Workflows still running at their execution deadline",
+            ),
+            &["namespace"],
+        )?;
+
+        registry.register(Box::new(rpc_duration_seconds.clone()))?;
+        registry.register(Box::new(rpc_errors_total.clone()))?;
+        registry.register(Box::new(active_leases.clone()))?;
+        registry.register(Box::new(stale_workflows.clone()))?;
+        registry.register(Box::new(lease_contention_total.clone()))?;
+        registry.register(Box::new(fencing_token_rejections_total.clone()))?;
+        registry.register(Box::new(checkpoint_value_bytes.clone()))?;
+        registry.register(Box::new(cleanup_deleted_rows_total.clone()))?;
+        registry.register(Box::new(quota_rejections_total.clone()))?;
+        registry.register(Box::new(workflow_timeouts_total.clone()))?;
+
+        Ok(Self {
+            registry,
+            rpc_duration_seconds,
+            rpc_errors_total,
+            active_leases,
+            stale_workflows,
+            lease_contention_total,
+            fencing_token_rejections_total,
+            checkpoint_value_bytes,
+            cleanup_deleted_rows_total,
+            quota_rejections_total,
+            workflow_timeouts_total,
+        })
+    }
+}
+
+static METRICS: LazyLock<WorkflowMetrics> =
+    LazyLock::new(|| WorkflowMetrics::new().expect("metrics must be valid"));
+
+pub fn metrics() -> &'static WorkflowMetrics {
+    &METRICS
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::time::{Duration, Instant};
+
+/// How long the summed checkpoint sizes of a namespace are reused before they are queried again.
+const STORED_BYTES_TTL: Duration = Duration::from_secs(1);
+
+struct CheckpointRate {
+    /// Token bucket holding up to one second worth of checkpoints.
+    tokens: f64,
+    refilled_at: Instant,
+    /// Accepted checkpoints of the current and the previous second, reported as usage.
+    window_started_at: Instant,
+    current_window: i64,
+    previous_window: i64,
+}
+
+struct StoredBytes {
+    bytes: i64,
+    queried_at: Instant,
+}
+
+#[derive(Default)]
+struct QuotaState {
+    checkpoint_rates: HashMap<String, CheckpointRate>,
+    stored_bytes: HashMap<String, StoredBytes>,
+}
+
+/// Node local bookkeeping of the quotas that can not be read from the database on every request.
+///
+/// Nothing here is shared with the other nodes: every node has its own checkpoint rate buckets,
+/// so the rate quota applies per node, and its own cache of the stored bytes.
+#[derive(Clone, Default)]
+pub struct QuotaTracker {
+    state: Arc<Mutex<QuotaState>>,
+}
+
+impl QuotaTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Takes a checkpoint from the namespace's token bucket of this node. Returns false when the
+    /// rate is exceeded.
+    pub fn try_acquire_checkpoint(&self, namespace: &str, max_per_second: i64) -> bool {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let rate = state
+            .checkpoint_rates
+            .entry(namespace.to_string())
+            .or_insert_with(|| CheckpointRate {
+                tokens: max_per_second as f64,
+                refilled_at: now,
+                window_started_at: now,
+                current_window: 0,
+                previous_window: 0,
+            });
+        let elapsed = now.duration_since(rate.refilled_at).as_secs_f64();
+        rate.tokens = (rate.tokens + elapsed * max_per_second as f64).min(max_per_second as f64);
+        rate.refilled_at = now;
+        if rate.tokens < 1.0 {
+            return false;
+        }
+        rate.tokens -= 1.0;
+        rotate_window(rate, now);
+        rate.current_window += 1;
+        true
+    }
+
+    ///
+    /// Checkpoints accepted by this node during the last full second.
+    pub fn checkpoints_last_second(&self, namespace: &str) -> i64 {
+        let mut state = self.state.lock().unwrap();
+        match state.checkpoint_rates.get_mut(namespace) {
+            Some(rate) => {
+                rotate_window(rate, Instant::now());
+                rate.previous_window
+            }
+            None => 0,
+        }
+    }
+
+    pub fn cached_stored_bytes(&self, namespace: &str) -> Option<i64> {
+        let state = self.state.lock().unwrap();
+        state
+            .stored_bytes
+            .get(namespace)
+            .filter(|stored| stored.queried_at.elapsed() < STORED_BYTES_TTL)
+            .map(|stored| stored.bytes)
+    }
+
+    pub fn set_stored_bytes(&self, namespace: &str, bytes: i64) {
+        let mut state = self.state.lock().unwrap();
+        state.stored_bytes.insert(
+            namespace.to_string(),
+            StoredBytes {
+                bytes,
+                queried_at: Instant::now(),
+            },
+        );
+    }
+
+    ///
+    /// Counts a newly stored value until the next query, so bursts within the TTL can't pass the
+    /// limit unnoticed. Values that were already stored must not be counted again.
+    pub fn add_stored_bytes(&self, namespace: &str, bytes: i64) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(stored) = state.stored_bytes.get_mut(namespace) {
+            stored.bytes += bytes;
+        }
+    }
+}
+
+fn rotate_window(rate: &mut CheckpointRate, now: Instant) {
+    let elapsed = now.duration_since(rate.window_started_at);
+    if elapsed >= Duration::from_secs(2) {
+        rate.previous_window = 0;
+        rate.current_window = 0;
+        rate.window_started_at = now;
+    } else if elapsed >= Duration::from_secs(1) {
+        rate.previous_window = rate.current_window;
+        rate.current_window = 0;
+        rate.window_started_at += Duration::from_secs(1);
+    }
+}
@@ -1,51 +1,143 @@
 use chrono::Utc;
-use std::error::Error;
 
 use hiqlite::Client;
 use hiqlite_macros::params;
+use tracing::instrument;
 
-use crate::schema::checkpoint::CheckpointValue;
+use crate::helpers::common::return_error_if_true;
+use crate::helpers::errors::{CheckpointMismatch, WorkflowError};
+use crate::schema::checkpoint::{CheckpointHistoryEntry, CheckpointValue, NewCheckpoint};
 
+#[instrument(skip(client))]
 pub async fn get_checkpoint(
     client: &Client,
+    namespace: &str,
     workflow_id: &str,
+    branch: &str,
     position: i64,
-) -> Result<Option<CheckpointValue>, Box<dyn Error + Send + Sync>> {
+) -> Result<Option<CheckpointValue>, WorkflowError> {
     let checkpoint = client
         .query_as_optional::<CheckpointValue, _>(
-            "SELECT position, idempotency_key, value FROM Checkpoints WHERE workflow_id = $1 AND position = $2",
-            params![workflow_id, position],
+            "SELECT position, idempotency_key, value, task_name FROM Checkpoints WHERE namespace = $1 AND workflow_id = $2 AND branch = $3 AND position = $4",
+            params![namespace, workflow_id, branch, position],
         )
         .await?;
     Ok(checkpoint)
 }
 
+///
+/// Returns all checkpoints of a workflow in branch and position order. Values are only loaded if
+/// `include_values` is set.
+#[instrument(skip(client))]
+pub async fn get_checkpoints(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    include_values: bool,
+) -> Result<Vec<CheckpointHistoryEntry>, WorkflowError> {
+    let checkpoints = client
+        .query_as::<CheckpointHistoryEntry, _>(
+            "SELECT branch, position, idempotency_key, task_name, created_at, LENGTH(value) AS value_size, CASE WHEN $1 THEN value ELSE NULL END AS value FROM Checkpoints WHERE namespace = $2 AND workflow_id = $3 ORDER BY branch, position",
+            params![include_values, namespace, workflow_id],
+        )
+        .await?;
+    Ok(checkpoints)
+}
+
+///
+/// Sums the sizes of all checkpoint values and compensation payloads of a namespace.
+#[instrument(skip(client))]
+pub async fn get_stored_bytes(client: &Client, namespace: &str) -> Result<i64, WorkflowError> {
+    let stored_bytes = client
+        .query_as_one::<i64, _>(
+            "SELECT (SELECT COALESCE(SUM(LENGTH(value)), 0) FROM Checkpoints WHERE namespace = $1) + (SELECT COALESCE(SUM(LENGTH(payload)), 0) FROM Compensations WHERE namespace = $1) AS stored_bytes",
+            params![namespace],
+        )
+        .await?;
+    Ok(stored_bytes)
+}
+
+///
+/// Writes the value of the position unless it already holds one. Returns whether this call wrote
+/// the value, so retries and concurrent writers of the same position are only counted once. Fails
+/// if the position was recorded under another idempotency key or task name.
+#[instrument(skip(client, checkpoint))]
 pub async fn create_checkpoint(
     client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    branch: &str,
+    position: i64,
+    checkpoint: NewCheckpoint,
+) -> Result<bool, WorkflowError> {
+    // A row without value only reserves the idempotency key of the position, so the first written
+    // value and task name are kept.
+    let written = !client
+        .execute_returning(
+            "INSERT INTO Checkpoints (namespace, workflow_id, branch, position, idempotency_key, value, created_at, task_name) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT (namespace, workflow_id, branch, position) DO UPDATE SET created_at = $7, value = $6, task_name = COALESCE(Checkpoints.task_name, $8) WHERE Checkpoints.value IS NULL AND Checkpoints.idempotency_key = $5 AND (Checkpoints.task_name IS NULL OR $8 IS NULL OR Checkpoints.task_name = $8) RETURNING position",
+            params![namespace, workflow_id, branch, position, checkpoint.idempotency_key.clone(), checkpoint.value, Utc::now().timestamp_millis(), checkpoint.task_name.clone()],
+        )
+        .await?
+        .is_empty();
+    if written {
+        return Ok(true);
+    }
+    // nothing was written, either the step was recorded already or another step holds the position
+    let Some(recorded) = get_checkpoint(client, namespace, workflow_id, branch, position).await?
+    else {
+        return Ok(false);
+    };
+    let is_task_name_changed = matches!(
+        (&recorded.task_name, &checkpoint.task_name),
+        (Some(recorded), Some(received)) if recorded != received
+    );
+    return_error_if_true(
+        recorded.idempotency_key != checkpoint.idempotency_key || is_task_name_changed,
+        WorkflowError::NonDeterministicCheckpoint(Box::new(CheckpointMismatch {
+            branch: branch.to_string(),
+            position,
+            recorded_idempotency_key: recorded.idempotency_key,
+            received_idempotency_key: checkpoint.idempotency_key,
+            recorded_task_name: recorded.task_name,
+            received_task_name: checkpoint.task_name,
+        })),
+    )?;
+    Ok(false)
+}
+
+///
+/// Records `idempotency_key` for the position unless one was recorded before, and returns the
+/// recorded key. Concurrent reservations of a position therefore all return the same key.
+#[instrument(skip(client))]
+pub async fn reserve_idempotency_key(
+    client: &Client,
+    namespace: &str,
     workflow_id: &str,
-    value: Option<Vec<u8>>,
+    branch: &str,
     position: i64,
     idempotency_key: String,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    client
-        .execute(
-            "INSERT INTO Checkpoints (workflow_id, position, idempotency_key, value, created_at) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (workflow_id, position) DO UPDATE SET created_at = $5",
-            params![workflow_id, position, idempotency_key, value, Utc::now().timestamp_millis()],
+    task_name: Option<String>,
+) -> Result<String, WorkflowError> {
+    let mut row = client
+        .execute_returning_one(
+            "INSERT INTO Checkpoints (namespace, workflow_id, branch, position, idempotency_key, value, created_at, task_name) VALUES ($1, $2, $3, $4, $5, NULL, $6, $7) ON CONFLICT (namespace, workflow_id, branch, position) DO UPDATE SET idempotency_key = Checkpoints.idempotency_key RETURNING idempotency_key",
+            params![namespace, workflow_id, branch, position, idempotency_key, Utc::now().timestamp_millis(), task_name],
         )
         .await?;
-    Ok(())
+    Ok(row.get("idempotency_key"))
 }
 
+#[instrument(skip(client))]
 pub async fn delete_expired_checkpoints(
     client: &Client,
     current_timestamp: i64,
     status: i8,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    client
+) -> Result<usize, WorkflowError> {
+    let deleted_rows = client
         .execute(
-            "DELETE FROM Checkpoints WHERE workflow_id IN (SELECT id FROM Workflows WHERE expire_at < $1 AND status = $2)",
+            "DELETE FROM Checkpoints WHERE (namespace, workflow_id) IN (SELECT namespace, id FROM Workflows WHERE expire_at < $1 AND status = $2)",
             params![current_timestamp, status ],
         )
         .await?;
-    Ok(())
+    Ok(deleted_rows)
 }
@@ -0,0 +1,142 @@
+use chrono::Utc;
+use hiqlite::Client;
+use hiqlite_macros::params;
+use tracing::instrument;
+
+use crate::helpers::errors::WorkflowError;
+use crate::schema::compensation::Compensation;
+use crate::schema::leased_checkpoint::LeaseRequest;
+
+const COMPENSATION_COLUMNS: &str = "branch, position, name, payload, lease_expire_at, lease_fencing_token, lease_worker_id, compensated_at";
+
+///
+/// Records the compensation of the position unless one was recorded before, so a replay keeps the
+/// payload of the first run. Returns whether this call recorded it.
+#[instrument(skip(client, payload))]
+pub async fn create_compensation(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    branch: &str,
+    position: i64,
+    name: &str,
+    payload: Vec<u8>,
+) -> Result<bool, WorkflowError> {
+    let created = client
+        .execute_returning(
+            "INSERT INTO Compensations (namespace, workflow_id, branch, position, name, payload, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT (namespace, workflow_id, branch, position) DO NOTHING RETURNING position",
+            params![namespace, workflow_id, branch, position, name, payload, Utc::now().timestamp_millis()],
+        )
+        .await?;
+    Ok(!created.is_empty())
+}
+
+#[instrument(skip(client))]
+pub async fn get_compensation(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    branch: &str,
+    position: i64,
+) -> Result<Option<Compensation>, WorkflowError> {
+    let compensation = client
+        .query_as_optional::<Compensation, _>(
+            format!("SELECT {COMPENSATION_COLUMNS} FROM Compensations WHERE namespace = $1 AND workflow_id = $2 AND branch = $3 AND position = $4"),
+            params![namespace, workflow_id, branch, position],
+        )
+        .await?;
+    Ok(compensation)
+}
+
+///
+/// Returns the compensations of the workflow that did not run yet.
+#[instrument(skip(client))]
+pub async fn get_pending_compensations(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+) -> Result<Vec<Compensation>, WorkflowError> {
+    let compensations = client
+        .query_as::<Compensation, _>(
+            format!("SELECT {COMPENSATION_COLUMNS} FROM Compensations WHERE namespace = $1 AND workflow_id = $2 AND compensated_at IS NULL"),
+            params![namespace, workflow_id],
+        )
+        .await?;
+    Ok(compensations)
+}
+
+///
+/// Leases the pending compensation of the position in a single write if it is not leased, its
+/// lease expired or it is leased by the same worker already. Returns `None` if it ran in the
+/// meantime or another worker holds it.
+#[instrument(skip(client))]
+pub async fn lease_pending_compensation(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    branch: &str,
+    position: i64,
+    lease: LeaseRequest,
+) -> Result<Option<Compensation>, WorkflowError> {
+    let now = Utc::now().timestamp_millis();
+    let compensation = client
+        .execute_returning_map::<_, Compensation>(
+            format!("UPDATE Compensations SET lease_expire_at = $1, lease_fencing_token = $2, lease_worker_id = $3 \
+            WHERE namespace = $4 AND workflow_id = $5 AND branch = $6 AND position = $7 AND compensated_at IS NULL \
+            AND (lease_expire_at IS NULL OR lease_expire_at <= $8 OR (\
+                lease_fencing_token = $2 AND (lease_worker_id IS NULL OR $3 IS NULL OR lease_worker_id = $3)\
+            )) \
+            RETURNING {COMPENSATION_COLUMNS}"),
+            params![
+                now.saturating_add(lease.lease_timeout),
+                lease.fencing_token,
+                lease.worker_id,
+                namespace,
+                workflow_id,
+                branch,
+                position,
+                now
+            ],
+        )
+        .await?
+        .into_iter()
+        .next()
+        .transpose()?;
+    Ok(compensation)
+}
+
+///
+/// Marks the compensation as done if it is leased by the worker. Returns false otherwise.
+#[instrument(skip(client))]
+pub async fn complete_compensation(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    branch: &str,
+    position: i64,
+    fencing_token: i64,
+    worker_id: Option<&str>,
+) -> Result<bool, WorkflowError> {
+    let affected_rows = client
+        .execute(
+            "UPDATE Compensations SET compensated_at = $1, lease_expire_at = NULL WHERE namespace = $2 AND workflow_id = $3 AND branch = $4 AND position = $5 AND compensated_at IS NULL AND lease_fencing_token = $6 AND (lease_worker_id IS NULL OR $7 IS NULL OR lease_worker_id = $7)",
+            params![Utc::now().timestamp_millis(), namespace, workflow_id, branch, position, fencing_token, worker_id],
+        )
+        .await?;
+    Ok(affected_rows > 0)
+}
+
+#[instrument(skip(client))]
+pub async fn delete_expired_compensations(
+    client: &Client,
+    current_timestamp: i64,
+    status: i8,
+) -> Result<usize, WorkflowError> {
+    let deleted_rows = client
+        .execute(
+            "DELETE FROM Compensations WHERE (namespace, workflow_id) IN (SELECT namespace, id FROM Workflows WHERE expire_at < $1 AND status = $2)",
+            params![current_timestamp, status],
+        )
+        .await?;
+    Ok(deleted_rows)
+}
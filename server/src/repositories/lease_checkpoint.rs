@@ -1,58 +1,178 @@
-use std::error::Error;
-
 use chrono::Utc;
 use hiqlite::Client;
+use hiqlite_macros::params;
+use tracing::instrument;
+use uuid::Uuid;
 
-use crate::{database::db::Cache, schema::leased_checkpoint::LeasedCheckpointValue};
+use crate::helpers::errors::WorkflowError;
+use crate::schema::leased_checkpoint::{
+    LeaseGrant, LeaseRequest, LeasedCheckpoint, LeasedCheckpointValue,
+};
 
-fn generate_leased_checkpoint_key(workflow_id: &str, position: i64) -> String {
-    format!("{}:{}", workflow_id, position)
+///
+/// Grants the lease unless another one of the position is still active or the position was
+/// checkpointed, in a single write, so concurrent workers can't both see the position unleased and
+/// a worker that read the position before another one checkpointed it can't lease it anymore.
+/// Positions holding a timer or a delivered signal, which record no value or only later, are never
+/// leased either, so a step replayed there needs no extra read to be refused.
+/// The write returns the lease held afterwards, which is this request's lease if its `lease_id` matches.
+#[instrument(skip(client))]
+pub async fn lease_checkpoint(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    branch: &str,
+    position: i64,
+    lease: LeaseRequest,
+) -> Result<LeaseGrant, WorkflowError> {
+    let lease_id = Uuid::new_v4().to_string();
+    // SET expressions read the row before the update, so an active lease is written back unchanged.
+    // Nothing is inserted or updated once the position holds a value, a timer or a delivered signal,
+    // so no row is returned then.
+    let mut leased_checkpoint = client
+        .execute_returning_map::<_, LeasedCheckpointValue>(
+            "INSERT INTO Leases (namespace, workflow_id, branch, position, lease_id, fencing_token, worker_id, lease_timeout, created_at) \
+            SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9 \
+            WHERE NOT EXISTS (SELECT 1 FROM Checkpoints WHERE namespace = $1 AND workflow_id = $2 AND branch = $3 AND position = $4 AND value IS NOT NULL) \
+            AND NOT EXISTS (SELECT 1 FROM Timers WHERE namespace = $1 AND workflow_id = $2 AND branch = $3 AND position = $4) \
+            AND NOT EXISTS (SELECT 1 FROM Signals WHERE namespace = $1 AND workflow_id = $2 AND delivered_branch = $3 AND delivered_position = $4) \
+            ON CONFLICT (namespace, workflow_id, branch, position) DO UPDATE SET \
+                lease_id = CASE WHEN Leases.created_at + Leases.lease_timeout <= $9 THEN $5 ELSE Leases.lease_id END, \
+                fencing_token = CASE WHEN Leases.created_at + Leases.lease_timeout <= $9 THEN $6 ELSE Leases.fencing_token END, \
+                worker_id = CASE WHEN Leases.created_at + Leases.lease_timeout <= $9 THEN $7 ELSE Leases.worker_id END, \
+                lease_timeout = CASE WHEN Leases.created_at + Leases.lease_timeout <= $9 THEN $8 ELSE Leases.lease_timeout END, \
+                created_at = CASE WHEN Leases.created_at + Leases.lease_timeout <= $9 THEN $9 ELSE Leases.created_at END \
+            RETURNING lease_id, lease_timeout, created_at, fencing_token, worker_id",
+            params![namespace, workflow_id, branch, position, lease_id.clone(), lease.fencing_token, lease.worker_id, lease.lease_timeout, Utc::now().timestamp_millis()],
+        )
+        .await?;
+    let Some(leased_checkpoint) = leased_checkpoint.pop().transpose()? else {
+        return Ok(LeaseGrant::Recorded);
+    };
+    if leased_checkpoint.lease_id == lease_id {
+        Ok(LeaseGrant::Granted(leased_checkpoint))
+    } else {
+        Ok(LeaseGrant::Held(leased_checkpoint))
+    }
 }
 
-pub async fn lease_checkpoint(
+///
+/// Restarts the lease timeout of an active lease held with `fencing_token`. Returns `None` if there is none.
+#[instrument(skip(client))]
+pub async fn renew_leased_checkpoint(
     client: &Client,
-    workflow_id: String,
+    namespace: &str,
+    workflow_id: &str,
+    branch: &str,
     position: i64,
     lease_timeout: i64,
-) -> Result<LeasedCheckpointValue, Box<dyn Error + Send + Sync>> {
-    let created_at = Utc::now().timestamp_millis();
-    let key = generate_leased_checkpoint_key(&workflow_id, position);
-    let minimum_cache_ttl = 30;
-    client
-        .put(
-            Cache::One,
-            key.clone(),
-            &LeasedCheckpointValue {
-                lease_timeout: lease_timeout,
-                created_at,
-            },
-            Some((lease_timeout / 1000).max(minimum_cache_ttl)),
+    fencing_token: i64,
+) -> Result<Option<LeasedCheckpointValue>, WorkflowError> {
+    let mut leased_checkpoint = client
+        .execute_returning_map::<_, LeasedCheckpointValue>(
+            "UPDATE Leases SET lease_timeout = $1, created_at = $2 WHERE namespace = $3 AND workflow_id = $4 AND branch = $5 AND position = $6 AND fencing_token = $7 AND created_at + lease_timeout > $2 RETURNING lease_id, lease_timeout, created_at, fencing_token, worker_id",
+            params![lease_timeout, Utc::now().timestamp_millis(), namespace, workflow_id, branch, position, fencing_token],
         )
         .await?;
-
-    Ok(LeasedCheckpointValue {
-        lease_timeout: lease_timeout,
-        created_at: created_at,
-    })
+    leased_checkpoint.pop().transpose().map_err(Into::into)
 }
 
+///
+/// Deletes the lease of the position. With a `fencing_token`, only a lease held with that token and
+/// `worker_id` is deleted, a lease without worker id matches any. Returns whether a lease was deleted.
+#[instrument(skip(client))]
 pub async fn remove_leased_checkpoint(
     client: &Client,
+    namespace: &str,
     workflow_id: &str,
+    branch: &str,
     position: i64,
-) -> Result<Option<LeasedCheckpointValue>, Box<dyn Error + Send + Sync>> {
-    let key = generate_leased_checkpoint_key(&workflow_id, position);
-    let result: Option<LeasedCheckpointValue> = client.get(Cache::One, key.clone()).await?;
-    client.delete(Cache::One, key).await?;
-    Ok(result)
+    fencing_token: Option<i64>,
+    worker_id: Option<&str>,
+) -> Result<bool, WorkflowError> {
+    let deleted_rows = client
+        .execute(
+            "DELETE FROM Leases WHERE namespace = $1 AND workflow_id = $2 AND branch = $3 AND position = $4 AND ($5 IS NULL OR (fencing_token = $5 AND ($6 IS NULL OR worker_id IS NULL OR worker_id = $6)))",
+            params![namespace, workflow_id, branch, position, fencing_token, worker_id],
+        )
+        .await?;
+    Ok(deleted_rows > 0)
 }
 
+#[instrument(skip(client))]
 pub async fn get_leased_checkpoint(
     client: &Client,
+    namespace: &str,
     workflow_id: &str,
+    branch: &str,
     position: i64,
-) -> Result<Option<LeasedCheckpointValue>, Box<dyn Error + Send + Sync>> {
-    let key = generate_leased_checkpoint_key(workflow_id, position);
-    let leased_checkpoint: Option<LeasedCheckpointValue> = client.get(Cache::One, key).await?;
+) -> Result<Option<LeasedCheckpointValue>, WorkflowError> {
+    let leased_checkpoint = client
+        .query_as_optional::<LeasedCheckpointValue, _>(
+            "SELECT lease_id, lease_timeout, created_at, fencing_token, worker_id FROM Leases WHERE namespace = $1 AND workflow_id = $2 AND branch = $3 AND position = $4",
+            params![namespace, workflow_id, branch, position],
+        )
+        .await?;
     Ok(leased_checkpoint)
 }
+
+///
+/// Returns all leases of a workflow by branch and position, including expired ones that were not
+/// deleted yet.
+#[instrument(skip(client))]
+pub async fn get_leased_checkpoints(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+) -> Result<Vec<(String, i64, LeasedCheckpointValue)>, WorkflowError> {
+    let leased_checkpoints = client
+        .query_as::<LeasedCheckpoint, _>(
+            "SELECT namespace, workflow_id, branch, position, lease_id, fencing_token, worker_id, lease_timeout, created_at FROM Leases WHERE namespace = $1 AND workflow_id = $2 ORDER BY branch, position",
+            params![namespace, workflow_id],
+        )
+        .await?
+        .into_iter()
+        .map(|leased_checkpoint| {
+            (
+                leased_checkpoint.branch.clone(),
+                leased_checkpoint.position,
+                leased_checkpoint.into(),
+            )
+        })
+        .collect();
+    Ok(leased_checkpoints)
+}
+
+///
+/// Counts the leases of all workflows that did not expire yet.
+#[instrument(skip_all)]
+pub async fn count_active_leases(client: &Client) -> Result<usize, WorkflowError> {
+    let active_leases = client
+        .query_as_one::<i64, _>(
+            "SELECT COUNT(*) AS active_leases FROM Leases WHERE created_at + lease_timeout > $1",
+            params![Utc::now().timestamp_millis()],
+        )
+        .await?;
+    Ok(active_leases as usize)
+}
+
+/// Milliseconds an expired lease is kept, so a late checkpoint of its worker is still checked
+/// against it. The same as the minimum TTL of the former lease cache.
+const EXPIRED_LEASE_RETENTION: i64 = 30_000;
+
+///
+/// Deletes leases that expired before `current_timestamp` minus the retention, left behind by workers
+/// that neither checkpointed nor released them.
+#[instrument(skip(client))]
+pub async fn delete_expired_leases(
+    client: &Client,
+    current_timestamp: i64,
+) -> Result<usize, WorkflowError> {
+    let deleted_rows = client
+        .execute(
+            "DELETE FROM Leases WHERE created_at + lease_timeout < $1",
+            params![current_timestamp.saturating_sub(EXPIRED_LEASE_RETENTION)],
+        )
+        .await?;
+    Ok(deleted_rows)
+}
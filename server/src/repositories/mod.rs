@@ -1,4 +1,10 @@
 pub mod checkpoints;
+pub mod compensations;
 pub mod lease_checkpoint;
+pub mod namespaces;
+pub mod positions;
+pub mod signals;
+pub mod timers;
+pub mod workflow_payloads;
 pub mod workflows;
 pub mod workflows_fencing_tokens;
@@ -0,0 +1,102 @@
+use chrono::Utc;
+use hiqlite::Client;
+use hiqlite_macros::params;
+use tracing::instrument;
+
+use crate::helpers::errors::WorkflowError;
+use crate::schema::namespace::{Namespace, NamespaceQuotas};
+
+///
+/// Returns `None` when a namespace with the name already exists.
+#[instrument(skip(client))]
+pub async fn create_namespace(
+    client: &Client,
+    name: &str,
+    default_retention: i64,
+    quotas: NamespaceQuotas,
+) -> Result<Option<Namespace>, WorkflowError> {
+    let created_at = Utc::now().timestamp_millis();
+    let inserted_rows = client
+        .execute(
+            "INSERT INTO Namespaces (name, default_retention, created_at, max_running_workflows, max_checkpoints_per_second, max_checkpoint_bytes, max_stored_bytes) VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT (name) DO NOTHING",
+            params![
+                name,
+                default_retention,
+                created_at,
+                quotas.max_running_workflows,
+                quotas.max_checkpoints_per_second,
+                quotas.max_checkpoint_bytes,
+                quotas.max_stored_bytes
+            ],
+        )
+        .await?;
+    Ok((inserted_rows > 0).then(|| Namespace {
+        name: name.to_string(),
+        default_retention,
+        created_at,
+        max_running_workflows: quotas.max_running_workflows,
+        max_checkpoints_per_second: quotas.max_checkpoints_per_second,
+        max_checkpoint_bytes: quotas.max_checkpoint_bytes,
+        max_stored_bytes: quotas.max_stored_bytes,
+    }))
+}
+
+#[instrument(skip(client))]
+pub async fn get_namespace(
+    client: &Client,
+    name: &str,
+) -> Result<Option<Namespace>, WorkflowError> {
+    let namespace = client
+        .query_as_optional::<Namespace, _>(
+            "SELECT * FROM Namespaces WHERE name = $1",
+            params![name],
+        )
+        .await?;
+    Ok(namespace)
+}
+
+#[instrument(skip(client))]
+pub async fn get_namespaces(client: &Client) -> Result<Vec<Namespace>, WorkflowError> {
+    let namespaces = client
+        .query_as::<Namespace, _>("SELECT * FROM Namespaces ORDER BY name", params![])
+        .await?;
+    Ok(namespaces)
+}
+
+///
+/// Returns false when the namespace does not exist.
+#[instrument(skip(client))]
+pub async fn update_namespace_settings(
+    client: &Client,
+    name: &str,
+    default_retention: i64,
+    quotas: NamespaceQuotas,
+) -> Result<bool, WorkflowError> {
+    let affected_rows = client
+        .execute(
+            "UPDATE Namespaces SET default_retention = $1, max_running_workflows = $2, max_checkpoints_per_second = $3, max_checkpoint_bytes = $4, max_stored_bytes = $5 WHERE name = $6",
+            params![
+                default_retention,
+                quotas.max_running_workflows,
+                quotas.max_checkpoints_per_second,
+                quotas.max_checkpoint_bytes,
+                quotas.max_stored_bytes,
+                name
+            ],
+        )
+        .await?;
+    Ok(affected_rows > 0)
+}
+
+///
+/// Deletes the namespace only if it holds no workflows. Returns false otherwise.
+#[instrument(skip(client))]
+pub async fn delete_empty_namespace(client: &Client, name: &str) -> Result<bool, WorkflowError> {
+    let deleted_rows = client
+        .execute(
+            "DELETE FROM Namespaces WHERE name = $1 AND NOT EXISTS (SELECT 1 FROM Workflows WHERE namespace = $1)",
+            params![name],
+        )
+        .await?;
+    Ok(deleted_rows > 0)
+}
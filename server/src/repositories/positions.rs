@@ -0,0 +1,32 @@
+use hiqlite::Client;
+use hiqlite_macros::params;
+use tracing::instrument;
+
+use crate::helpers::errors::WorkflowError;
+use crate::schema::position::PositionRecords;
+
+///
+/// Reads the checkpoint, the timer, the delivered signal and the compensation of the position in
+/// one query.
+#[instrument(skip(client))]
+pub async fn get_position_records(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    branch: &str,
+    position: i64,
+) -> Result<PositionRecords, WorkflowError> {
+    let records = client
+        .query_as_one::<PositionRecords, _>(
+            "SELECT \
+                (SELECT idempotency_key FROM Checkpoints WHERE namespace = $1 AND workflow_id = $2 AND branch = $3 AND position = $4) AS checkpoint_idempotency_key, \
+                (SELECT task_name FROM Checkpoints WHERE namespace = $1 AND workflow_id = $2 AND branch = $3 AND position = $4) AS checkpoint_task_name, \
+                (SELECT name FROM Timers WHERE namespace = $1 AND workflow_id = $2 AND branch = $3 AND position = $4) AS timer_name, \
+                (SELECT signal_id FROM Signals WHERE namespace = $1 AND workflow_id = $2 AND delivered_branch = $3 AND delivered_position = $4) AS signal_id, \
+                (SELECT name FROM Signals WHERE namespace = $1 AND workflow_id = $2 AND delivered_branch = $3 AND delivered_position = $4) AS signal_name, \
+                (SELECT name FROM Compensations WHERE namespace = $1 AND workflow_id = $2 AND branch = $3 AND position = $4) AS compensation_name",
+            params![namespace, workflow_id, branch, position],
+        )
+        .await?;
+    Ok(records)
+}
@@ -0,0 +1,90 @@
+use chrono::Utc;
+use hiqlite::Client;
+use hiqlite_macros::params;
+use tracing::instrument;
+
+use crate::helpers::errors::WorkflowError;
+use crate::schema::signal::Signal;
+
+///
+/// Stores a pending signal. Returns `false` if a signal with the same id was sent before,
+/// in which case nothing is written.
+#[instrument(skip(client, payload))]
+pub async fn create_signal(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    signal_id: &str,
+    name: &str,
+    payload: Vec<u8>,
+) -> Result<bool, WorkflowError> {
+    let inserted_rows = client
+        .execute(
+            "INSERT INTO Signals (namespace, workflow_id, signal_id, name, payload, created_at) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT (namespace, workflow_id, signal_id) DO NOTHING",
+            params![namespace, workflow_id, signal_id, name, payload, Utc::now().timestamp_millis()],
+        )
+        .await?;
+    Ok(inserted_rows > 0)
+}
+
+///
+/// Delivers the oldest pending signal named `name` to the position in a single write, unless a
+/// signal was delivered to the position before. Concurrent deliveries to a position therefore
+/// deliver one signal, which `get_delivered_signal` returns to the others.
+#[instrument(skip(client))]
+pub async fn deliver_signal(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    name: &str,
+    branch: &str,
+    position: i64,
+) -> Result<Option<Signal>, WorkflowError> {
+    let signal = client
+        .execute_returning_map::<_, Signal>(
+            "UPDATE Signals SET delivered_branch = $1, delivered_position = $2 WHERE rowid = (\
+                SELECT rowid FROM Signals WHERE namespace = $3 AND workflow_id = $4 AND name = $5 \
+                AND delivered_position IS NULL ORDER BY created_at, rowid LIMIT 1\
+            ) AND NOT EXISTS (\
+                SELECT 1 FROM Signals WHERE namespace = $3 AND workflow_id = $4 AND delivered_branch = $1 AND delivered_position = $2\
+            ) RETURNING signal_id, name, payload, delivered_branch, delivered_position",
+            params![branch, position, namespace, workflow_id, name],
+        )
+        .await?
+        .into_iter()
+        .next()
+        .transpose()?;
+    Ok(signal)
+}
+
+#[instrument(skip(client))]
+pub async fn get_delivered_signal(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    branch: &str,
+    position: i64,
+) -> Result<Option<Signal>, WorkflowError> {
+    let signal = client
+        .query_as_optional::<Signal, _>(
+            "SELECT signal_id, name, payload, delivered_branch, delivered_position FROM Signals WHERE namespace = $1 AND workflow_id = $2 AND delivered_branch = $3 AND delivered_position = $4",
+            params![namespace, workflow_id, branch, position],
+        )
+        .await?;
+    Ok(signal)
+}
+
+#[instrument(skip(client))]
+pub async fn delete_expired_signals(
+    client: &Client,
+    current_timestamp: i64,
+    status: i8,
+) -> Result<usize, WorkflowError> {
+    let deleted_rows = client
+        .execute(
+            "DELETE FROM Signals WHERE (namespace, workflow_id) IN (SELECT namespace, id FROM Workflows WHERE expire_at < $1 AND status = $2)",
+            params![current_timestamp, status],
+        )
+        .await?;
+    Ok(deleted_rows)
+}
@@ -0,0 +1,112 @@
+use chrono::Utc;
+use hiqlite::Client;
+use hiqlite_macros::params;
+use tracing::instrument;
+
+use crate::helpers::errors::WorkflowError;
+use crate::schema::timer::Timer;
+use crate::schema::workflow::WorkflowStatus;
+
+///
+/// Records a timer at the position unless one was recorded before, and returns the recorded timer.
+/// A replay therefore keeps the fire time of the first run.
+#[instrument(skip(client))]
+pub async fn create_or_get_timer(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    branch: &str,
+    position: i64,
+    name: &str,
+    fire_at: i64,
+) -> Result<Timer, WorkflowError> {
+    let timer = client
+        .execute_returning_map_one::<_, Timer>(
+            "INSERT INTO Timers (namespace, workflow_id, branch, position, name, fire_at, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT (namespace, workflow_id, branch, position) DO UPDATE SET name = Timers.name RETURNING workflow_id, branch, position, name, fire_at, fired_at",
+            params![namespace, workflow_id, branch, position, name, fire_at, Utc::now().timestamp_millis()],
+        )
+        .await?;
+    Ok(timer)
+}
+
+#[instrument(skip(client))]
+pub async fn mark_timer_fired(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    branch: &str,
+    position: i64,
+    fired_at: i64,
+) -> Result<(), WorkflowError> {
+    client
+        .execute(
+            "UPDATE Timers SET fired_at = $1 WHERE namespace = $2 AND workflow_id = $3 AND branch = $4 AND position = $5 AND fired_at IS NULL",
+            params![fired_at, namespace, workflow_id, branch, position],
+        )
+        .await?;
+    Ok(())
+}
+
+///
+/// Claims up to `limit` due timers of running workflows in the namespace until `claimed_until`,
+/// in a single write, so concurrent pollers never get the same timer. Timers claimed by another
+/// poller are due again once their claim expired.
+#[instrument(skip(client))]
+pub async fn claim_due_timers(
+    client: &Client,
+    namespace: &str,
+    current_timestamp: i64,
+    claimed_until: i64,
+    limit: i64,
+) -> Result<Vec<Timer>, WorkflowError> {
+    let mut timers = client
+        .execute_returning_map::<_, Timer>(
+            "UPDATE Timers SET claimed_until = $1 WHERE (namespace, workflow_id, branch, position) IN (\
+                SELECT Timers.namespace, Timers.workflow_id, Timers.branch, Timers.position FROM Timers \
+                JOIN Workflows ON Workflows.namespace = Timers.namespace AND Workflows.id = Timers.workflow_id \
+                WHERE Timers.namespace = $2 AND Timers.fired_at IS NULL AND Timers.fire_at <= $3 \
+                AND (Timers.claimed_until IS NULL OR Timers.claimed_until <= $3) AND Workflows.status = $4 \
+                ORDER BY Timers.fire_at LIMIT $5\
+            ) RETURNING workflow_id, branch, position, name, fire_at, fired_at",
+            params![claimed_until, namespace, current_timestamp, WorkflowStatus::Running as i64, limit],
+        )
+        .await?
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+    timers.sort_by_key(|timer| timer.fire_at);
+    Ok(timers)
+}
+
+///
+/// Returns when the next timer of a running workflow in the namespace becomes due, either because
+/// it fires or because its claim expires.
+#[instrument(skip(client))]
+pub async fn get_next_timer_due_at(
+    client: &Client,
+    namespace: &str,
+) -> Result<Option<i64>, WorkflowError> {
+    let due_at = client
+        .query_as_one::<Option<i64>, _>(
+            "SELECT MIN(MAX(Timers.fire_at, COALESCE(Timers.claimed_until, 0))) AS due_at FROM Timers \
+            JOIN Workflows ON Workflows.namespace = Timers.namespace AND Workflows.id = Timers.workflow_id \
+            WHERE Timers.namespace = $1 AND Timers.fired_at IS NULL AND Workflows.status = $2",
+            params![namespace, WorkflowStatus::Running as i64],
+        )
+        .await?;
+    Ok(due_at)
+}
+
+#[instrument(skip(client))]
+pub async fn delete_expired_timers(
+    client: &Client,
+    current_timestamp: i64,
+    status: i8,
+) -> Result<usize, WorkflowError> {
+    let deleted_rows = client
+        .execute(
+            "DELETE FROM Timers WHERE (namespace, workflow_id) IN (SELECT namespace, id FROM Workflows WHERE expire_at < $1 AND status = $2)",
+            params![current_timestamp, status],
+        )
+        .await?;
+    Ok(deleted_rows)
+}
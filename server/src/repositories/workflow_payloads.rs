@@ -0,0 +1,70 @@
+use hiqlite::Client;
+use hiqlite_macros::params;
+use tracing::instrument;
+
+use crate::helpers::errors::WorkflowError;
+use crate::schema::workflow_payload::WorkflowPayload;
+
+#[instrument(skip(client))]
+pub async fn get_workflow_payload(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+) -> Result<Option<WorkflowPayload>, WorkflowError> {
+    let payload = client
+        .query_as_optional::<WorkflowPayload, _>(
+            "SELECT input, result FROM WorkflowPayloads WHERE namespace = $1 AND workflow_id = $2",
+            params![namespace, workflow_id],
+        )
+        .await?;
+    Ok(payload)
+}
+
+///
+/// Records the input of a workflow. The first recorded input is kept, so a restart can't change it.
+#[instrument(skip(client, input))]
+pub async fn save_workflow_input(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    input: Vec<u8>,
+) -> Result<(), WorkflowError> {
+    client
+        .execute(
+            "INSERT INTO WorkflowPayloads (namespace, workflow_id, input) VALUES ($1, $2, $3) ON CONFLICT (namespace, workflow_id) DO UPDATE SET input = COALESCE(WorkflowPayloads.input, $3)",
+            params![namespace, workflow_id, input],
+        )
+        .await?;
+    Ok(())
+}
+
+#[instrument(skip(client, result))]
+pub async fn save_workflow_result(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    result: Vec<u8>,
+) -> Result<(), WorkflowError> {
+    client
+        .execute(
+            "INSERT INTO WorkflowPayloads (namespace, workflow_id, result) VALUES ($1, $2, $3) ON CONFLICT (namespace, workflow_id) DO UPDATE SET result = $3",
+            params![namespace, workflow_id, result],
+        )
+        .await?;
+    Ok(())
+}
+
+#[instrument(skip(client))]
+pub async fn delete_expired_workflow_payloads(
+    client: &Client,
+    current_timestamp: i64,
+    status: i8,
+) -> Result<usize, WorkflowError> {
+    let deleted_rows = client
+        .execute(
+            "DELETE FROM WorkflowPayloads WHERE (namespace, workflow_id) IN (SELECT namespace, id FROM Workflows WHERE expire_at < $1 AND status = $2)",
+            params![current_timestamp, status],
+        )
+        .await?;
+    Ok(deleted_rows)
+}
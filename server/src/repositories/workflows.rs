@@ -1,59 +1,346 @@
-use std::error::Error;
-
 use chrono::Utc;
-use hiqlite::Client;
+use hiqlite::{Client, Param};
 use hiqlite_macros::params;
+use tracing::instrument;
 
-use crate::schema::workflow::{Workflow, WorkflowStatus};
+use crate::helpers::errors::WorkflowError;
+use crate::schema::workflow::{Workflow, WorkflowParent, WorkflowStatus};
 
+#[instrument(skip(client))]
 pub async fn create_or_get_workflow(
     client: &Client,
+    namespace: &str,
     workflow_id: &str,
     status: WorkflowStatus,
     name: Option<String>,
-) -> Result<Workflow, Box<dyn Error + Send + Sync>> {
+    execution_deadline: Option<i64>,
+    parent: Option<WorkflowParent>,
+) -> Result<Workflow, WorkflowError> {
+    let (parent_workflow_id, parent_branch, parent_position) = match parent {
+        Some(parent) => (
+            Some(parent.workflow_id),
+            Some(parent.branch),
+            Some(parent.position),
+        ),
+        None => (None, None, None),
+    };
+    // a restart keeps the deadline of the first start, so it can't extend the execution, and gives
+    // workflows that send heartbeats a fresh one, so the new worker isn't taken for abandoned.
+    // The parent is only set by the first start as well.
     let mut result = client.execute_returning_one(
-        "INSERT INTO Workflows (id, status, created_at, name) VALUES ($1, $2, $3, $4) ON CONFLICT (id) DO UPDATE SET name = $4 RETURNING *",
+        "INSERT INTO Workflows (namespace, id, status, created_at, name, execution_deadline, parent_workflow_id, parent_branch, parent_position) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) ON CONFLICT (namespace, id) DO UPDATE SET name = $5, execution_deadline = COALESCE(Workflows.execution_deadline, $6), last_heartbeat_at = CASE WHEN Workflows.last_heartbeat_at IS NULL THEN NULL ELSE $4 END RETURNING *",
         params![
+            namespace,
             workflow_id,
             status as i64,
             Utc::now().timestamp_millis(),
-            name
+            name,
+            execution_deadline,
+            parent_workflow_id,
+            parent_branch,
+            parent_position
         ],
     ).await?;
     Ok(Workflow {
+        namespace: result.get::<String>("namespace"),
         id: result.get::<String>("id"),
         status: result.get::<i64>("status"),
         created_at: result.get::<i64>("created_at"),
         expire_at: result.get::<Option<i64>>("expire_at"),
         completed_at: result.get::<Option<i64>>("completed_at"),
         name: result.get::<Option<String>>("name"),
+        execution_deadline: result.get::<Option<i64>>("execution_deadline"),
+        last_heartbeat_at: result.get::<Option<i64>>("last_heartbeat_at"),
+        parent_workflow_id: result.get::<Option<String>>("parent_workflow_id"),
+        parent_branch: result.get::<Option<String>>("parent_branch"),
+        parent_position: result.get::<Option<i64>>("parent_position"),
     })
 }
 
+///
+/// Returns the child workflow started at the position of the parent.
+#[instrument(skip(client))]
+pub async fn get_child_workflow(
+    client: &Client,
+    namespace: &str,
+    parent_workflow_id: &str,
+    parent_branch: &str,
+    parent_position: i64,
+) -> Result<Option<Workflow>, WorkflowError> {
+    let result = client
+        .query_as_optional::<Workflow, _>(
+            "SELECT * FROM Workflows WHERE namespace = $1 AND parent_workflow_id = $2 AND parent_branch = $3 AND parent_position = $4",
+            params![namespace, parent_workflow_id, parent_branch, parent_position],
+        )
+        .await?;
+    Ok(result)
+}
+
+///
+/// Returns the child workflows of the parent in the order of their branches and positions.
+#[instrument(skip(client))]
+pub async fn get_child_workflows(
+    client: &Client,
+    namespace: &str,
+    parent_workflow_id: &str,
+) -> Result<Vec<Workflow>, WorkflowError> {
+    let workflows = client
+        .query_as::<Workflow, _>(
+            "SELECT * FROM Workflows WHERE namespace = $1 AND parent_workflow_id = $2 ORDER BY parent_branch, parent_position",
+            params![namespace, parent_workflow_id],
+        )
+        .await?;
+    Ok(workflows)
+}
+
+///
+/// Records a heartbeat of a running workflow. Returns false when the workflow does not exist or is not running.
+#[instrument(skip(client))]
+pub async fn record_workflow_heartbeat(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    current_timestamp: i64,
+) -> Result<bool, WorkflowError> {
+    let affected_rows = client
+        .execute(
+            "UPDATE Workflows SET last_heartbeat_at = $1 WHERE namespace = $2 AND id = $3 AND status = $4",
+            params![
+                current_timestamp,
+                namespace,
+                workflow_id,
+                WorkflowStatus::Running as i64
+            ],
+        )
+        .await?;
+    Ok(affected_rows > 0)
+}
+
+///
+/// Counts the running workflows of all namespaces whose last heartbeat is older than `heartbeat_before`.
+#[instrument(skip(client))]
+pub async fn count_stale_workflows(
+    client: &Client,
+    heartbeat_before: i64,
+) -> Result<i64, WorkflowError> {
+    let stale_workflows = client
+        .query_as_one::<i64, _>(
+            "SELECT COUNT(*) AS stale_workflows FROM Workflows WHERE status = $1 AND last_heartbeat_at < $2",
+            params![WorkflowStatus::Running as i64, heartbeat_before],
+        )
+        .await?;
+    Ok(stale_workflows)
+}
+
+#[instrument(skip(client))]
 pub async fn get_workflow(
     client: &Client,
+    namespace: &str,
     workflow_id: &str,
-) -> Result<Option<Workflow>, Box<dyn Error + Send + Sync>> {
+) -> Result<Option<Workflow>, WorkflowError> {
     let result = client
         .query_as_optional::<Workflow, _>(
-            "SELECT * FROM Workflows WHERE id = $1",
-            params![workflow_id],
+            "SELECT * FROM Workflows WHERE namespace = $1 AND id = $2",
+            params![namespace, workflow_id],
         )
         .await?;
     Ok(result)
 }
 
+///
+/// Counts the workflows of a namespace that are running, paused or compensating.
+#[instrument(skip(client))]
+pub async fn count_running_workflows(
+    client: &Client,
+    namespace: &str,
+) -> Result<i64, WorkflowError> {
+    let running_workflows = client
+        .query_as_one::<i64, _>(
+            "SELECT COUNT(*) AS running_workflows FROM Workflows WHERE namespace = $1 AND status IN ($2, $3, $4)",
+            params![
+                namespace,
+                WorkflowStatus::Running as i64,
+                WorkflowStatus::Paused as i64,
+                WorkflowStatus::Compensating as i64
+            ],
+        )
+        .await?;
+    Ok(running_workflows)
+}
+
+///
+/// Moves running workflows whose execution deadline passed to `TimedOut` and starts the default
+/// retention of their namespace, like a completion without `expire_after`. Returns the timed out workflows.
+#[instrument(skip(client))]
+pub async fn time_out_overdue_workflows(
+    client: &Client,
+    current_timestamp: i64,
+) -> Result<Vec<Workflow>, WorkflowError> {
+    let workflows = client
+        .execute_returning_map::<_, Workflow>(
+            "UPDATE Workflows SET status = $1, completed_at = $2, expire_at = $2 + COALESCE((SELECT default_retention FROM Namespaces WHERE name = Workflows.namespace), 0) WHERE status = $3 AND execution_deadline <= $2 RETURNING *",
+            params![
+                WorkflowStatus::TimedOut as i64,
+                current_timestamp,
+                WorkflowStatus::Running as i64
+            ],
+        )
+        .await?
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(workflows)
+}
+
+/// Moves a workflow from `from_status` to `to_status`.
+/// Returns false when the workflow does not exist or is no longer in `from_status`.
+#[instrument(skip(client))]
+pub async fn update_workflow_status(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    from_status: WorkflowStatus,
+    to_status: WorkflowStatus,
+    expire_at: Option<i64>,
+    completed_at: Option<i64>,
+) -> Result<bool, WorkflowError> {
+    let affected_rows = client
+        .execute(
+            "UPDATE Workflows SET status = $1, expire_at = $2, completed_at = $3 WHERE namespace = $4 AND id = $5 AND status = $6",
+            params![
+                to_status as i64,
+                expire_at,
+                completed_at,
+                namespace,
+                workflow_id,
+                from_status as i64
+            ],
+        )
+        .await?;
+    Ok(affected_rows > 0)
+}
+
+///
+/// Like `update_workflow_status`, but bumps the fencing token of the workflow in the same
+/// transaction, so in-flight workers are fenced off exactly when the status changed.
+#[instrument(skip(client))]
+pub async fn update_workflow_status_and_fencing_token(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    from_status: WorkflowStatus,
+    to_status: WorkflowStatus,
+    expire_at: Option<i64>,
+    completed_at: Option<i64>,
+) -> Result<bool, WorkflowError> {
+    // the token is bumped first, while the status it is conditioned on is still the old one
+    let mut results = client
+        .txn([
+            (
+                "UPDATE WorkflowFencingTokens SET fencing_token = fencing_token + 1 WHERE namespace = $1 AND workflow_id = $2 AND EXISTS (SELECT 1 FROM Workflows WHERE namespace = $1 AND id = $2 AND status = $3)",
+                params![namespace, workflow_id, from_status as i64],
+            ),
+            (
+                "UPDATE Workflows SET status = $1, expire_at = $2, completed_at = $3 WHERE namespace = $4 AND id = $5 AND status = $6",
+                params![
+                    to_status as i64,
+                    expire_at,
+                    completed_at,
+                    namespace,
+                    workflow_id,
+                    from_status as i64
+                ],
+            ),
+        ])
+        .await?;
+    let affected_rows = results.pop().transpose()?.unwrap_or_default();
+    Ok(affected_rows > 0)
+}
+
+#[derive(Debug, Default)]
+pub struct ListWorkflowsFilter {
+    pub namespace: String,
+    pub status: Option<i64>,
+    pub name: Option<String>,
+    pub created_after: Option<i64>,
+    pub created_before: Option<i64>,
+    pub completed_after: Option<i64>,
+    pub completed_before: Option<i64>,
+    /// Only workflows that sent heartbeats, the last one before this time
+    pub last_heartbeat_before: Option<i64>,
+    /// `(created_at, id)` of the last workflow of the previous page
+    pub after: Option<(i64, String)>,
+}
+
+///
+/// Lists workflows matching the filter, newest first.
+#[instrument(skip(client, filter))]
+pub async fn get_workflows(
+    client: &Client,
+    filter: ListWorkflowsFilter,
+    limit: i64,
+) -> Result<Vec<Workflow>, WorkflowError> {
+    let mut params: Vec<Param> = vec![filter.namespace.into()];
+    let mut conditions: Vec<String> = vec!["namespace = $1".to_string()];
+
+    if let Some(status) = filter.status {
+        params.push(status.into());
+        conditions.push(format!("status = ${}", params.len()));
+    }
+    if let Some(name) = filter.name {
+        params.push(name.into());
+        conditions.push(format!("name = ${}", params.len()));
+    }
+    if let Some(created_after) = filter.created_after {
+        params.push(created_after.into());
+        conditions.push(format!("created_at >= ${}", params.len()));
+    }
+    if let Some(created_before) = filter.created_before {
+        params.push(created_before.into());
+        conditions.push(format!("created_at < ${}", params.len()));
+    }
+    if let Some(completed_after) = filter.completed_after {
+        params.push(completed_after.into());
+        conditions.push(format!("completed_at >= ${}", params.len()));
+    }
+    if let Some(completed_before) = filter.completed_before {
+        params.push(completed_before.into());
+        conditions.push(format!("completed_at < ${}", params.len()));
+    }
+    if let Some(last_heartbeat_before) = filter.last_heartbeat_before {
+        params.push(last_heartbeat_before.into());
+        conditions.push(format!("last_heartbeat_at < ${}", params.len()));
+    }
+    if let Some((created_at, id)) = filter.after {
+        params.push(created_at.into());
+        let created_at_idx = params.len();
+        params.push(id.into());
+        conditions.push(format!(
+            "(created_at < ${created_at_idx} OR (created_at = ${created_at_idx} AND id < ${}))",
+            params.len()
+        ));
+    }
+    params.push(limit.into());
+
+    let sql = format!(
+        "SELECT * FROM Workflows WHERE {} ORDER BY created_at DESC, id DESC LIMIT ${}",
+        conditions.join(" AND "),
+        params.len()
+    );
+    let workflows = client.query_as::<Workflow, _>(sql, params).await?;
+    Ok(workflows)
+}
+
+#[instrument(skip(client))]
 pub async fn delete_expired_workflows(
     client: &Client,
     current_timestamp: i64,
     status: i8,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    client
+) -> Result<usize, WorkflowError> {
+    let deleted_rows = client
         .execute(
             "DELETE FROM Workflows WHERE expire_at < $1 AND status = $2",
             params![current_timestamp, status],
         )
         .await?;
-    Ok(())
+    Ok(deleted_rows)
 }
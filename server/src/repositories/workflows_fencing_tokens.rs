@@ -1,43 +1,49 @@
-use std::error::Error;
-
 use hiqlite::Client;
 use hiqlite_macros::params;
+use tracing::instrument;
+
+use crate::helpers::errors::WorkflowError;
 
+#[instrument(skip(client))]
 pub async fn get_workflow_fencing_token(
     client: &Client,
+    namespace: &str,
     workflow_id: &str,
-) -> Result<Option<i64>, Box<dyn Error + Send + Sync>> {
+) -> Result<Option<i64>, WorkflowError> {
     let fencing_token = client
         .query_as_optional::<i64, _>(
-            "SELECT fencing_token FROM WorkflowFencingTokens WHERE workflow_id = $1",
-            params![workflow_id],
+            "SELECT fencing_token FROM WorkflowFencingTokens WHERE namespace = $1 AND workflow_id = $2",
+            params![namespace, workflow_id],
         )
         .await?;
     Ok(fencing_token)
 }
 
+#[instrument(skip(client))]
 pub async fn increment_workflow_fencing_token(
     client: &Client,
+    namespace: &str,
     workflow_id: &str,
     fencing_token: i64,
-) -> Result<i64, Box<dyn Error + Send + Sync>> {
+) -> Result<i64, WorkflowError> {
     let mut result = client.execute_returning_one(
-        "INSERT INTO WorkflowFencingTokens (workflow_id, fencing_token) VALUES ($1, $2) ON CONFLICT (workflow_id) DO UPDATE SET fencing_token = WorkflowFencingTokens.fencing_token + 1 RETURNING fencing_token",
-        params![workflow_id, fencing_token],
+        "INSERT INTO WorkflowFencingTokens (namespace, workflow_id, fencing_token) VALUES ($1, $2, $3) ON CONFLICT (namespace, workflow_id) DO UPDATE SET fencing_token = WorkflowFencingTokens.fencing_token + 1 RETURNING fencing_token",
+        params![namespace, workflow_id, fencing_token],
     ).await?;
     Ok(result.get::<i64>("fencing_token"))
 }
 
+#[instrument(skip(client))]
 pub async fn delete_expired_workflow_fencing_tokens(
     client: &Client,
     current_timestamp: i64,
     status: i8,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    client
+) -> Result<usize, WorkflowError> {
+    let deleted_rows = client
         .execute(
-            "DELETE FROM WorkflowFencingTokens WHERE workflow_id IN (SELECT id FROM Workflows WHERE expire_at < $1 AND status = $2)",
+            "DELETE FROM WorkflowFencingTokens WHERE (namespace, workflow_id) IN (SELECT namespace, id FROM Workflows WHERE expire_at < $1 AND status = $2)",
             params![current_timestamp, status ],
         )
         .await?;
-    Ok(())
+    Ok(deleted_rows)
 }
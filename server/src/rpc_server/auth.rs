@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::env::var;
+use std::error::Error;
+use std::sync::Arc;
+
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode};
+use serde::Deserialize;
+use tonic::metadata::MetadataMap;
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+use crate::helpers::errors::WorkflowError;
+
+/// Permission levels, each one includes the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Scope {
+    /// Workflow status, listing and history, and reading namespaces.
+    Read,
+    /// Everything a worker does: starting workflows, leasing, checkpointing and releasing positions.
+    Write,
+    /// Cancelling, pausing and resuming workflows on behalf of operators, and managing namespaces.
+    Admin,
+}
+
+impl Scope {
+    pub fn parse(scope: &str) -> Option<Self> {
+        match scope {
+            "read" => Some(Scope::Read),
+            "write" => Some(Scope::Write),
+            "admin" => Some(Scope::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Write => "write",
+            Scope::Admin => "admin",
+        }
+    }
+
+    ///
+    /// The scope a caller needs for an RPC of `WorkflowServiceImpl`.
+    pub fn required_for(rpc: &str) -> Self {
+        match rpc {
+            "workflow_status"
+            | "list_workflows"
+            | "get_workflow_history"
+            | "list_child_workflows"
+            | "get_namespace"
+            | "list_namespaces"
+            | "get_namespace_usage" => Scope::Read,
+            "cancel_workflow"
+            | "pause_workflow"
+            | "resume_workflow"
+            | "compensate_workflow"
+            | "create_namespace"
+            | "update_namespace"
+            | "delete_namespace" => Scope::Admin,
+            _ => Scope::Write,
+        }
+    }
+}
+
+/// The authenticated caller, added to the request extensions by the [`Authenticator`].
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub subject: String,
+    pub scopes: Vec<Scope>,
+    /// `None` gives access to every namespace.
+    pub namespaces: Option<Vec<String>>,
+}
+
+impl Principal {
+    pub fn grants(&self, required: Scope) -> bool {
+        self.scopes.iter().any(|scope| *scope >= required)
+    }
+
+    pub fn permits_namespace(&self, namespace: &str) -> bool {
+        self.namespaces
+            .as_ref()
+            .is_none_or(|namespaces| namespaces.iter().any(|permitted| permitted == namespace))
+    }
+}
+
+/// Scopes and namespaces of an API key.
+#[derive(Debug, Clone)]
+pub struct ApiKeyGrant {
+    pub scopes: Vec<Scope>,
+    /// `None` gives access to every namespace.
+    pub namespaces: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AuthConfig {
+    /// API keys sent in the `x-api-key` metadata, with their scopes and namespaces.
+    pub api_keys: HashMap<String, ApiKeyGrant>,
+    /// Secret of HS256 signed JWTs sent as `authorization: Bearer <token>`.
+    /// The `scope` claim holds the space separated scopes, the optional `namespaces` claim
+    /// the namespaces the token is restricted to.
+    pub jwt_secret: Option<String>,
+}
+
+impl AuthConfig {
+    ///
+    /// Reads `API_KEYS`, e.g. `worker-key:write,ops-key:admin,dashboard-key:read`, and `JWT_SECRET`.
+    /// Several scopes of a key are separated by `+`. A key is restricted to namespaces
+    /// with an `@` suffix, e.g. `orders-key:write@orders|billing`. A malformed `API_KEYS` is an
+    /// error rather than being skipped, since a node must not start with less protection than intended.
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+        let api_keys = match var("API_KEYS") {
+            Ok(api_keys) => Self::parse_api_keys(&api_keys)?,
+            Err(_) => HashMap::new(),
+        };
+        Ok(Self {
+            api_keys,
+            jwt_secret: var("JWT_SECRET").ok(),
+        })
+    }
+
+    ///
+    /// Parses the `API_KEYS` format described in [`AuthConfig::from_env`]. Errors name the entry
+    /// by its index, so the keys don't end up in logs.
+    pub fn parse_api_keys(api_keys: &str) -> Result<HashMap<String, ApiKeyGrant>, Box<dyn Error>> {
+        api_keys
+            .split(",")
+            .filter(|entry| !entry.is_empty())
+            .enumerate()
+            .map(|(index, entry)| {
+                let Some((key, grant)) = entry.split_once(":").filter(|(key, _)| !key.is_empty())
+                else {
+                    return Err(format!("API_KEYS entry {index} must be <key>:<scopes>").into());
+                };
+                let (scopes, namespaces) = match grant.split_once("@") {
+                    Some((scopes, namespaces)) => {
+                        let namespaces: Vec<String> = namespaces
+                            .split("|")
+                            .filter(|namespace| !namespace.is_empty())
+                            .map(str::to_string)
+                            .collect();
+                        if namespaces.is_empty() {
+                            return Err(format!(
+                                "API_KEYS entry {index} has no namespace after '@'"
+                            )
+                            .into());
+                        }
+                        (scopes, Some(namespaces))
+                    }
+                    None => (grant, None),
+                };
+                let scopes = scopes
+                    .split("+")
+                    .map(|scope| {
+                        Scope::parse(scope).ok_or_else(|| {
+                            format!("API_KEYS entry {index} has the unknown scope '{scope}'")
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((key.to_string(), ApiKeyGrant { scopes, namespaces }))
+            })
+            .collect()
+    }
+
+    ///
+    /// Without API keys and a JWT secret authentication is off and EVERY caller, including
+    /// anonymous ones, is accepted with the admin scope on every namespace. Only run a node
+    /// that way behind a network boundary that already restricts who can reach it.
+    pub fn is_enabled(&self) -> bool {
+        !self.api_keys.is_empty() || self.jwt_secret.is_some()
+    }
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    sub: Option<String>,
+    #[serde(default)]
+    scope: String,
+    namespaces: Option<Vec<String>>,
+}
+
+/// Tonic interceptor resolving the credentials of a request into a [`Principal`].
+/// Which scope an RPC requires is checked by the service, since interceptors don't see the method.
+#[derive(Clone)]
+pub struct Authenticator {
+    config: Arc<AuthConfig>,
+    jwt_key: Option<DecodingKey>,
+}
+
+impl Authenticator {
+    pub fn new(config: AuthConfig) -> Self {
+        let jwt_key = config
+            .jwt_secret
+            .as_ref()
+            .map(|secret| DecodingKey::from_secret(secret.as_bytes()));
+        Self {
+            config: Arc::new(config),
+            jwt_key,
+        }
+    }
+
+    fn authenticate(&self, metadata: &MetadataMap) -> Result<Principal, WorkflowError> {
+        // see `AuthConfig::is_enabled`, an open node grants everything to everyone
+        if !self.config.is_enabled() {
+            return Ok(Principal {
+                subject: "anonymous".to_string(),
+                scopes: vec![Scope::Admin],
+                namespaces: None,
+            });
+        }
+        if let Some(api_key) = metadata.get("x-api-key") {
+            let api_key = api_key
+                .to_str()
+                .map_err(|_| WorkflowError::Unauthenticated)?;
+            let grant = self
+                .config
+                .api_keys
+                .get(api_key)
+                .ok_or(WorkflowError::Unauthenticated)?;
+            return Ok(Principal {
+                subject: "api_key".to_string(),
+                scopes: grant.scopes.clone(),
+                namespaces: grant.namespaces.clone(),
+            });
+        }
+        let token = metadata
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(WorkflowError::Unauthenticated)?;
+        let jwt_key = self
+            .jwt_key
+            .as_ref()
+            .ok_or(WorkflowError::Unauthenticated)?;
+        let claims = decode::<Claims>(token, jwt_key, &Validation::new(Algorithm::HS256))
+            .map_err(|_| WorkflowError::Unauthenticated)?
+            .claims;
+        Ok(Principal {
+            subject: claims.sub.unwrap_or_default(),
+            // unknown scopes are ignored, so tokens can carry scopes of other services
+            scopes: claims
+                .scope
+                .split_whitespace()
+                .filter_map(Scope::parse)
+                .collect(),
+            namespaces: claims.namespaces,
+        })
+    }
+}
+
+impl Interceptor for Authenticator {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let principal = self.authenticate(request.metadata())?;
+        request.extensions_mut().insert(principal);
+        Ok(request)
+    }
+}
+
+///
+/// Checks that the principal added by the [`Authenticator`] may call `rpc` on `namespace`.
+pub fn authorize<T>(
+    rpc: &str,
+    namespace: Option<&str>,
+    request: &Request<T>,
+) -> Result<(), WorkflowError> {
+    let principal = request
+        .extensions()
+        .get::<Principal>()
+        .ok_or(WorkflowError::Unauthenticated)?;
+    let required = Scope::required_for(rpc);
+    if !principal.grants(required) {
+        return Err(WorkflowError::PermissionDenied {
+            required_scope: required.as_str(),
+        });
+    }
+    if let Some(namespace) = namespace
+        && !principal.permits_namespace(namespace)
+    {
+        return Err(WorkflowError::NamespaceNotPermitted {
+            namespace: namespace.to_string(),
+        });
+    }
+    Ok(())
+}
@@ -4,52 +4,350 @@ use tonic::{Request, Response, Status, transport::Server};
 pub mod workflow_service {
     tonic::include_proto!("workflow_service");
 }
-use std::error::Error;
-use std::io;
+use tonic::Code;
+use tonic::codegen::InterceptedService;
+use tonic_types::{ErrorDetails, StatusExt};
+use tracing::field::Empty;
+use tracing::{Instrument, error, info_span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 
+use crate::events::workflow_events::WorkflowEvents;
+use crate::helpers::branch::decode_branch;
+use crate::helpers::errors::WorkflowError;
+use crate::metrics::workflow_metrics::metrics;
+use crate::quotas::quota_tracker::QuotaTracker;
+use crate::repositories::workflow_payloads::get_workflow_payload;
 use crate::repositories::workflows::get_workflow;
+use crate::rpc_server::auth::{Authenticator, Principal, authorize};
 use crate::rpc_server::server::workflow_service::{
-    GenerateIdempotencyKeyRequest, GenerateIdempotencyKeyResponse, ReleaseCheckpointRequest,
-    ReleaseCheckpointResponse, WorkflowStartRequest, WorkflowStartResponse, WorkflowStatusRequest,
+    CreateNamespaceRequest, CreateNamespaceResponse, DeleteNamespaceRequest,
+    DeleteNamespaceResponse, GenerateIdempotencyKeyRequest, GenerateIdempotencyKeyResponse,
+    GetNamespaceRequest, GetNamespaceResponse, GetNamespaceUsageRequest, GetNamespaceUsageResponse,
+    ListNamespacesRequest, ListNamespacesResponse, ReleaseCheckpointRequest,
+    ReleaseCheckpointResponse, RenewLeaseRequest, RenewLeaseResponse, UpdateNamespaceRequest,
+    UpdateNamespaceResponse, WorkflowStartRequest, WorkflowStartResponse, WorkflowStatusRequest,
     WorkflowStatusResponse,
 };
+use crate::rpc_server::tls::TlsConfig;
+use crate::schema::namespace::{DEFAULT_NAMESPACE, Namespace, NamespaceQuotas};
+use crate::schema::workflow::Workflow;
 use crate::services::checkpoint_service::{
     CheckpointInput, CreateDurableIdempotencyKeyInput, LeaseCheckpointInput,
-    LeaseCheckpointReturnType, create_durable_idempotency_key, handle_checkpoint,
-    handle_lease_checkpoint, release_checkpoint,
+    LeaseCheckpointReturnType, ReleaseCheckpointInput, RenewLeaseInput, WorkflowHistoryInput,
+    create_durable_idempotency_key, get_workflow_history, handle_checkpoint,
+    handle_lease_checkpoint, handle_renew_lease, handle_wait_lease_checkpoint, release_checkpoint,
+};
+use crate::services::child_workflow_service::{
+    AwaitChildWorkflowInput, ListChildWorkflowsInput, await_child_workflow, list_child_workflows,
+    wait_for_child_workflow,
+};
+use crate::services::compensation_service::{
+    CompleteCompensationInput, LeaseCompensationInput, LeaseCompensationOutput,
+    RegisterCompensationInput, handle_complete_compensation, lease_compensation,
+    register_compensation,
+};
+use crate::services::namespace_service::{
+    CreateNamespaceInput, DeleteNamespaceInput, GetNamespaceInput, UpdateNamespaceInput,
+    create_namespace, delete_namespace, get_namespace, list_namespaces, update_namespace,
+};
+use crate::services::quota_service::{NamespaceUsageInput, get_namespace_usage};
+use crate::services::signal_service::{
+    AwaitSignalInput, SignalWorkflowInput, await_signal, signal_workflow, wait_for_signal,
+};
+use crate::services::timer_service::{
+    PollDueTimersInput, ScheduleTimerInput, poll_due_timers, schedule_timer,
 };
 use crate::services::workflow_service::{
-    CreateWorkflowInput, FinishWorkflowInput, create_workflow, finish_workflow,
+    CancelWorkflowInput, CompensateWorkflowInput, CreateWorkflowInput, FailWorkflowInput,
+    FinishWorkflowInput, HeartbeatWorkflowInput, ListWorkflowsInput, ParentPositionInput,
+    PauseWorkflowInput, ResumeWorkflowInput, cancel_workflow, compensate_workflow, create_workflow,
+    fail_workflow, finish_workflow, heartbeat_workflow, list_workflows, pause_workflow,
+    resume_workflow,
 };
+use crate::telemetry::propagation::extract_trace_context;
 
 use workflow_service::{
-    CheckPointRequest, CheckPointResponse, CompleteWorkflowRequest, CompleteWorkflowResponse,
-    LeaseCheckpointRequest, LeaseCheckpointResponse,
+    ActiveLease, AwaitChildWorkflowRequest, AwaitChildWorkflowResponse, AwaitSignalRequest,
+    AwaitSignalResponse, CancelWorkflowRequest, CancelWorkflowResponse, CheckPointRequest,
+    CheckPointResponse, CheckpointHistoryEntry, ChildWorkflowOutcome, CompensateWorkflowRequest,
+    CompensateWorkflowResponse, CompleteCompensationRequest, CompleteCompensationResponse,
+    CompleteWorkflowRequest, CompleteWorkflowResponse, DueTimer, FailWorkflowRequest,
+    FailWorkflowResponse, GetWorkflowHistoryRequest, GetWorkflowHistoryResponse,
+    HeartbeatWorkflowRequest, HeartbeatWorkflowResponse, LeaseCheckpointRequest,
+    LeaseCheckpointResponse, LeaseCompensationRequest, LeaseCompensationResponse,
+    ListChildWorkflowsRequest, ListChildWorkflowsResponse, ListWorkflowsRequest,
+    ListWorkflowsResponse, PauseWorkflowRequest, PauseWorkflowResponse, PollDueTimersRequest,
+    PollDueTimersResponse, RegisterCompensationRequest, RegisterCompensationResponse,
+    ResumeWorkflowRequest, ResumeWorkflowResponse, ScheduleTimerRequest, ScheduleTimerResponse,
+    SignalWorkflowRequest, SignalWorkflowResponse, WorkflowSummary,
     lease_checkpoint_response::Response::RemainingLeaseTimeout,
-    lease_checkpoint_response::Response::Value, workflow_service_impl_server::WorkflowServiceImpl,
+    lease_checkpoint_response::Response::Value, lease_compensation_response,
+    workflow_service_impl_server::WorkflowServiceImpl,
     workflow_service_impl_server::WorkflowServiceImplServer,
 };
 
-fn to_status<T>(result: Result<T, Box<dyn Error + Send + Sync>>) -> Result<T, Status> {
-    result.map_err(|e| {
-        if let Some(io_err) = e.downcast_ref::<io::Error>() {
-            match io_err.kind() {
-                io::ErrorKind::Interrupted => Status::aborted(io_err.to_string()),
-                io::ErrorKind::InvalidData => Status::aborted(io_err.to_string()),
-                io::ErrorKind::InvalidInput => Status::aborted(io_err.to_string()),
-                io::ErrorKind::Other => Status::internal(io_err.to_string()),
-                _ => Status::internal(io_err.to_string()),
+const ERROR_DOMAIN: &str = "idempotency-server";
+
+///
+/// Requests without a namespace, e.g. from clients older than namespaces, use the default one.
+fn resolve_namespace(namespace: &str) -> &str {
+    if namespace.is_empty() {
+        DEFAULT_NAMESPACE
+    } else {
+        namespace
+    }
+}
+
+/// The namespace a request works on, checked against the namespaces the caller may access.
+trait NamespacedRequest {
+    fn namespace(&self) -> Option<&str>;
+}
+
+macro_rules! impl_namespaced_request {
+    (namespace: $($request:ty),+ $(,)?) => {
+        $(impl NamespacedRequest for $request {
+            fn namespace(&self) -> Option<&str> {
+                Some(resolve_namespace(&self.namespace))
+            }
+        })+
+    };
+    (name: $($request:ty),+ $(,)?) => {
+        $(impl NamespacedRequest for $request {
+            fn namespace(&self) -> Option<&str> {
+                Some(&self.name)
             }
-        } else {
-            // Not an io::Error — treat as unhandled/internal
-            Status::internal("Something went wrong.")
+        })+
+    };
+}
+
+impl_namespaced_request!(
+    namespace: GenerateIdempotencyKeyRequest,
+    CheckPointRequest,
+    LeaseCheckpointRequest,
+    WorkflowStartRequest,
+    CompleteWorkflowRequest,
+    WorkflowStatusRequest,
+    ReleaseCheckpointRequest,
+    RenewLeaseRequest,
+    HeartbeatWorkflowRequest,
+    ScheduleTimerRequest,
+    PollDueTimersRequest,
+    SignalWorkflowRequest,
+    AwaitSignalRequest,
+    ListChildWorkflowsRequest,
+    AwaitChildWorkflowRequest,
+    RegisterCompensationRequest,
+    CompensateWorkflowRequest,
+    LeaseCompensationRequest,
+    CompleteCompensationRequest,
+    CancelWorkflowRequest,
+    FailWorkflowRequest,
+    PauseWorkflowRequest,
+    ResumeWorkflowRequest,
+    ListWorkflowsRequest,
+    GetWorkflowHistoryRequest,
+);
+impl_namespaced_request!(
+    name: CreateNamespaceRequest,
+    GetNamespaceRequest,
+    UpdateNamespaceRequest,
+    DeleteNamespaceRequest,
+    GetNamespaceUsageRequest,
+);
+
+// the listed namespaces are filtered by the caller's access instead
+impl NamespacedRequest for ListNamespacesRequest {
+    fn namespace(&self) -> Option<&str> {
+        None
+    }
+}
+
+fn to_namespace_message(namespace: Namespace) -> workflow_service::Namespace {
+    workflow_service::Namespace {
+        quotas: Some(to_quotas_message(namespace.quotas())),
+        name: namespace.name,
+        default_retention: namespace.default_retention,
+        created_at: namespace.created_at,
+    }
+}
+
+fn to_workflow_summary(workflow: Workflow) -> WorkflowSummary {
+    WorkflowSummary {
+        workflow_id: workflow.id,
+        status: workflow.status,
+        name: workflow.name,
+        expire_at: workflow.expire_at,
+        created_at: workflow.created_at,
+        completed_at: workflow.completed_at,
+        execution_deadline: workflow.execution_deadline,
+        last_heartbeat_at: workflow.last_heartbeat_at,
+        parent_workflow_id: workflow.parent_workflow_id,
+        parent_position: workflow.parent_position,
+        parent_branch: workflow
+            .parent_branch
+            .as_deref()
+            .map(decode_branch)
+            .unwrap_or_default(),
+    }
+}
+
+fn to_quotas_message(quotas: NamespaceQuotas) -> workflow_service::NamespaceQuotas {
+    workflow_service::NamespaceQuotas {
+        max_running_workflows: quotas.max_running_workflows,
+        max_checkpoints_per_second: quotas.max_checkpoints_per_second,
+        max_checkpoint_bytes: quotas.max_checkpoint_bytes,
+        max_stored_bytes: quotas.max_stored_bytes,
+    }
+}
+
+fn from_quotas_message(quotas: workflow_service::NamespaceQuotas) -> NamespaceQuotas {
+    NamespaceQuotas {
+        max_running_workflows: quotas.max_running_workflows,
+        max_checkpoints_per_second: quotas.max_checkpoints_per_second,
+        max_checkpoint_bytes: quotas.max_checkpoint_bytes,
+        max_stored_bytes: quotas.max_stored_bytes,
+    }
+}
+
+fn status_code(error: &WorkflowError) -> Code {
+    match error {
+        WorkflowError::WorkflowNotFound
+        | WorkflowError::NamespaceNotFound
+        | WorkflowError::FencingTokenNotFound
+        | WorkflowError::LeaseNotFound => Code::NotFound,
+        WorkflowError::FencingTokenExpired { .. }
+        | WorkflowError::LeaseHeld { .. }
+        | WorkflowError::StaleLeaseRelease { .. } => Code::Aborted,
+        WorkflowError::NamespaceAlreadyExists => Code::AlreadyExists,
+        WorkflowError::QuotaExceeded { .. } => Code::ResourceExhausted,
+        WorkflowError::NonDeterministicCheckpoint(_)
+        | WorkflowError::CheckpointNotFound
+        | WorkflowError::LeaseExpired
+        | WorkflowError::WorkflowPaused
+        | WorkflowError::WorkflowCompensating
+        | WorkflowError::WorkflowNotCompensating { .. }
+        | WorkflowError::WorkflowTerminated { .. }
+        | WorkflowError::InvalidStatusTransition { .. }
+        | WorkflowError::NamespaceNotEmpty => Code::FailedPrecondition,
+        WorkflowError::InvalidArgument(_) => Code::InvalidArgument,
+        WorkflowError::Unauthenticated => Code::Unauthenticated,
+        WorkflowError::PermissionDenied { .. } | WorkflowError::NamespaceNotPermitted { .. } => {
+            Code::PermissionDenied
         }
-    })
+        WorkflowError::Storage(
+            hiqlite::Error::Connect(_)
+            | hiqlite::Error::LeaderChange(_)
+            | hiqlite::Error::CheckIsLeaderError(_)
+            | hiqlite::Error::Timeout(_),
+        ) => Code::Unavailable,
+        WorkflowError::Storage(_) | WorkflowError::Internal(_) => Code::Internal,
+    }
 }
 
+///
+/// Maps a service error to a status with a stable code, the error reason as message and
+/// a `google.rpc.ErrorInfo` detail carrying the reason and its metadata.
+impl From<WorkflowError> for Status {
+    fn from(e: WorkflowError) -> Self {
+        let code = status_code(&e);
+        if code == Code::Internal || code == Code::Unavailable {
+            error!("Request failed: {}", e);
+        }
+        Status::with_error_details(
+            code,
+            e.reason(),
+            ErrorDetails::with_error_info(e.reason(), ERROR_DOMAIN, e.metadata()),
+        )
+    }
+}
+
+///
+/// Whether the status rejected a superseded worker, told by the reason of its `ErrorInfo` detail
+/// rather than its message.
+fn is_fencing_rejection(status: &Status) -> bool {
+    status
+        .get_details_error_info()
+        .is_some_and(|info| info.domain == ERROR_DOMAIN && info.reason == "fencing_token_expired")
+}
+
+///
+/// Runs an RPC handler in a span that continues the caller's trace and records its latency and outcome.
+async fn observe_rpc<R, T, F, Fut>(
+    rpc: &'static str,
+    request: Request<R>,
+    handler: F,
+) -> Result<T, Status>
+where
+    R: NamespacedRequest,
+    F: FnOnce(Request<R>) -> Fut,
+    Fut: Future<Output = Result<T, Status>>,
+{
+    let span = info_span!(
+        "rpc",
+        otel.name = rpc,
+        otel.kind = "server",
+        rpc.method = rpc,
+        otel.status_code = Empty,
+        rpc.grpc.status_code = Empty,
+    );
+    // requests without a trace context start a new trace
+    let _ = span.set_parent(extract_trace_context(request.metadata()));
+    let timer = metrics()
+        .rpc_duration_seconds
+        .with_label_values(&[rpc])
+        .start_timer();
+    let namespace = request.get_ref().namespace();
+    let result = match authorize(rpc, namespace, &request).map_err(Status::from) {
+        Ok(()) => handler(request).instrument(span.clone()).await,
+        Err(status) => Err(status),
+    };
+    timer.observe_duration();
+    if let Err(status) = &result {
+        span.record("otel.status_code", "ERROR");
+        span.record("rpc.grpc.status_code", status.code() as i32);
+        metrics()
+            .rpc_errors_total
+            .with_label_values(&[rpc, &format!("{:?}", status.code())])
+            .inc();
+        if is_fencing_rejection(status) {
+            metrics()
+                .fencing_token_rejections_total
+                .with_label_values(&[rpc])
+                .inc();
+        }
+    }
+    result
+}
+
+pub type AuthenticatedWorkflowServer =
+    InterceptedService<WorkflowServiceImplServer<WorkflowService>, Authenticator>;
+
 // defining a struct for our service
+#[derive(Clone)]
 pub struct WorkflowService {
     client: Client,
+    events: WorkflowEvents,
+    quota_tracker: QuotaTracker,
+}
+
+impl WorkflowService {
+    ///
+    /// `events` must be shared by every service on the node, since only one task can
+    /// listen to the hiqlite event bus. The same goes for `quota_tracker`, which counts the
+    /// checkpoint rate of the node.
+    pub fn new(client: Client, events: WorkflowEvents, quota_tracker: QuotaTracker) -> Self {
+        Self {
+            client,
+            events,
+            quota_tracker,
+        }
+    }
+
+    ///
+    /// Wraps the service into the generated tonic server behind the authenticator, ready to be added to a `Router`.
+    pub fn into_server(self, authenticator: Authenticator) -> AuthenticatedWorkflowServer {
+        WorkflowServiceImplServer::with_interceptor(self, authenticator)
+    }
 }
 
 // implementing rpc for service defined in .proto
@@ -59,154 +357,888 @@ impl WorkflowServiceImpl for WorkflowService {
         &self,
         request: Request<GenerateIdempotencyKeyRequest>,
     ) -> Result<Response<GenerateIdempotencyKeyResponse>, Status> {
-        let data = request.into_inner();
-        let result = to_status(
-            create_durable_idempotency_key(
+        observe_rpc("generate_idempotency_key", request, |request| async move {
+            let data = request.into_inner();
+            let result = create_durable_idempotency_key(
                 &self.client,
                 CreateDurableIdempotencyKeyInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
                     workflow_id: data.workflow_id,
+                    branch: data.branch,
                     fencing_token: data.fencing_token,
                     position: data.position,
+                    task_name: data.task_name,
                 },
             )
-            .await,
-        )?;
-        Ok(Response::new(GenerateIdempotencyKeyResponse {
-            idempotency_key: result.idempotency_key,
-        }))
+            .await?;
+            Ok(Response::new(GenerateIdempotencyKeyResponse {
+                idempotency_key: result.idempotency_key,
+            }))
+        })
+        .await
     }
     async fn checkpoint(
         &self,
         request: Request<CheckPointRequest>,
     ) -> Result<Response<CheckPointResponse>, Status> {
-        let data = request.into_inner();
-        let result = to_status(
-            handle_checkpoint(
+        observe_rpc("checkpoint", request, |request| async move {
+            let data = request.into_inner();
+            metrics()
+                .checkpoint_value_bytes
+                .observe(data.value.len() as f64);
+            let result = handle_checkpoint(
                 &self.client,
+                &self.quota_tracker,
                 CheckpointInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
                     workflow_id: data.workflow_id,
+                    branch: data.branch,
                     fencing_token: data.fencing_token,
                     position: data.position,
                     value: data.value,
                     idempotency_key: data.idempotency_key,
+                    task_name: data.task_name,
+                    worker_id: data.worker_id,
                 },
             )
-            .await,
-        )?;
-        Ok(Response::new(CheckPointResponse {
-            abort: result.abort,
-        }))
+            .await?;
+            if result.abort {
+                metrics()
+                    .fencing_token_rejections_total
+                    .with_label_values(&["checkpoint"])
+                    .inc();
+            }
+            Ok(Response::new(CheckPointResponse {
+                abort: result.abort,
+            }))
+        })
+        .await
     }
 
     async fn lease_checkpoint(
         &self,
         request: Request<LeaseCheckpointRequest>,
     ) -> Result<Response<LeaseCheckpointResponse>, Status> {
-        let data = request.into_inner();
-        let result = to_status(
-            handle_lease_checkpoint(
-                &self.client,
-                LeaseCheckpointInput {
-                    workflow_id: data.workflow_id,
-                    fencing_token: data.fencing_token,
-                    position: data.position,
-                    lease_timeout: data.lease_timeout,
-                    idempotency_key: data.idempotency_key,
-                },
-            )
-            .await,
-        )?;
-        Ok(Response::new(LeaseCheckpointResponse {
-            response: result.response.map(|r| match r {
-                LeaseCheckpointReturnType::CheckpointValue(value) => Value(value),
-                LeaseCheckpointReturnType::RemainingLeaseTimeout(remaining_lease_timeout) => {
-                    RemainingLeaseTimeout(remaining_lease_timeout)
+        observe_rpc("lease_checkpoint", request, |request| async move {
+            let data = request.into_inner();
+            let input = LeaseCheckpointInput {
+                namespace: resolve_namespace(&data.namespace).to_string(),
+                workflow_id: data.workflow_id,
+                branch: data.branch,
+                fencing_token: data.fencing_token,
+                position: data.position,
+                lease_timeout: data.lease_timeout,
+                idempotency_key: data.idempotency_key,
+                task_name: data.task_name,
+                worker_id: data.worker_id,
+            };
+            let result = match data.wait_timeout {
+                Some(wait_timeout) => {
+                    handle_wait_lease_checkpoint(&self.client, &self.events, input, wait_timeout)
+                        .await
+                }
+                None => handle_lease_checkpoint(&self.client, input).await,
+            }?;
+            let response = match result.response {
+                Some(LeaseCheckpointReturnType::CheckpointValue(value)) => {
+                    LeaseCheckpointResponse {
+                        response: Some(Value(value)),
+                        ..Default::default()
+                    }
                 }
-            }),
-        }))
+                Some(LeaseCheckpointReturnType::RemainingLeaseTimeout {
+                    remaining_lease_timeout,
+                    fencing_token,
+                    worker_id,
+                }) => {
+                    metrics().lease_contention_total.inc();
+                    LeaseCheckpointResponse {
+                        response: Some(RemainingLeaseTimeout(remaining_lease_timeout)),
+                        lease_fencing_token: Some(fencing_token),
+                        lease_worker_id: worker_id,
+                    }
+                }
+                None => LeaseCheckpointResponse::default(),
+            };
+            Ok(Response::new(response))
+        })
+        .await
     }
 
     async fn workflow_start(
         &self,
         request: Request<WorkflowStartRequest>,
     ) -> Result<Response<WorkflowStartResponse>, Status> {
-        let data = request.into_inner();
-        let result = to_status(
-            create_workflow(
+        observe_rpc("workflow_start", request, |request| async move {
+            let data = request.into_inner();
+            // A child workflow needs its parent, the position it was started at and the fencing
+            // token of the parent's worker.
+            let parent = match (
+                data.parent_workflow_id,
+                data.parent_position,
+                data.parent_fencing_token,
+            ) {
+                (Some(parent_workflow_id), Some(parent_position), Some(parent_fencing_token)) => {
+                    Some(ParentPositionInput {
+                        workflow_id: parent_workflow_id,
+                        branch: data.parent_branch,
+                        position: parent_position,
+                        fencing_token: parent_fencing_token,
+                    })
+                }
+                (None, None, None) => None,
+                _ => return Err(WorkflowError::InvalidArgument("invalid_parent_workflow").into()),
+            };
+            let result = create_workflow(
                 &self.client,
                 CreateWorkflowInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
                     workflow_id: data.workflow_id,
                     name: data.context_name,
+                    input: data.input,
+                    execution_timeout: data.execution_timeout,
+                    parent,
                 },
             )
-            .await,
-        )?;
-        Ok(Response::new(WorkflowStartResponse {
-            fencing_token: result.fencing_token,
-        }))
+            .await?;
+            Ok(Response::new(WorkflowStartResponse {
+                fencing_token: result.fencing_token,
+                completed: result.completed,
+                result: result.result,
+            }))
+        })
+        .await
     }
 
     async fn complete_workflow(
         &self,
         request: Request<CompleteWorkflowRequest>,
     ) -> Result<Response<CompleteWorkflowResponse>, Status> {
-        let data = request.into_inner();
-        to_status(
+        observe_rpc("complete_workflow", request, |request| async move {
+            let data = request.into_inner();
             finish_workflow(
                 &self.client,
                 FinishWorkflowInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
                     workflow_id: data.workflow_id,
                     fencing_token: data.fencing_token,
                     expire_after: data.expire_after,
+                    result: data.result,
                 },
             )
-            .await,
-        )?;
-        Ok(Response::new(CompleteWorkflowResponse {}))
+            .await?;
+            Ok(Response::new(CompleteWorkflowResponse {}))
+        })
+        .await
     }
 
     async fn workflow_status(
         &self,
         request: Request<WorkflowStatusRequest>,
     ) -> Result<Response<WorkflowStatusResponse>, Status> {
-        let data = request.into_inner();
-        let result = to_status(get_workflow(&self.client, &data.workflow_id).await)?;
-        if let Some(workflow) = result {
+        observe_rpc("workflow_status", request, |request| async move {
+            let data = request.into_inner();
+            let namespace = resolve_namespace(&data.namespace);
+            let result = get_workflow(&self.client, namespace, &data.workflow_id).await?;
+            let workflow = result.ok_or(WorkflowError::WorkflowNotFound)?;
+            let payload = if data.include_payloads {
+                get_workflow_payload(&self.client, namespace, &data.workflow_id).await?
+            } else {
+                None
+            };
+            let (input, result) = payload
+                .map(|payload| (payload.input, payload.result))
+                .unwrap_or_default();
             Ok(Response::new(WorkflowStatusResponse {
                 workflow_id: workflow.id,
                 status: workflow.status,
                 expire_at: workflow.expire_at,
                 completed_at: workflow.completed_at,
                 created_at: workflow.created_at,
+                input,
+                result,
+                execution_deadline: workflow.execution_deadline,
+                last_heartbeat_at: workflow.last_heartbeat_at,
+                parent_workflow_id: workflow.parent_workflow_id,
+                parent_position: workflow.parent_position,
+                parent_branch: workflow
+                    .parent_branch
+                    .as_deref()
+                    .map(decode_branch)
+                    .unwrap_or_default(),
             }))
-        } else {
-            Err(Status::not_found("workflow_not_found"))
-        }
+        })
+        .await
     }
 
     async fn release_checkpoint(
         &self,
         request: Request<ReleaseCheckpointRequest>,
     ) -> Result<Response<ReleaseCheckpointResponse>, Status> {
-        let data = request.into_inner();
-        to_status(release_checkpoint(&self.client, &data.workflow_id, data.position).await)?;
-        Ok(Response::new(ReleaseCheckpointResponse {}))
+        observe_rpc("release_checkpoint", request, |request| async move {
+            let data = request.into_inner();
+            release_checkpoint(
+                &self.client,
+                ReleaseCheckpointInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
+                    workflow_id: data.workflow_id,
+                    branch: data.branch,
+                    position: data.position,
+                    fencing_token: data.fencing_token,
+                    worker_id: data.worker_id,
+                },
+            )
+            .await?;
+            Ok(Response::new(ReleaseCheckpointResponse {}))
+        })
+        .await
+    }
+
+    async fn renew_lease(
+        &self,
+        request: Request<RenewLeaseRequest>,
+    ) -> Result<Response<RenewLeaseResponse>, Status> {
+        observe_rpc("renew_lease", request, |request| async move {
+            let data = request.into_inner();
+            let result = handle_renew_lease(
+                &self.client,
+                RenewLeaseInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
+                    workflow_id: data.workflow_id,
+                    branch: data.branch,
+                    fencing_token: data.fencing_token,
+                    position: data.position,
+                    lease_timeout: data.lease_timeout,
+                },
+            )
+            .await?;
+            Ok(Response::new(RenewLeaseResponse {
+                lease_expire_at: result.lease_expire_at,
+            }))
+        })
+        .await
+    }
+
+    async fn heartbeat_workflow(
+        &self,
+        request: Request<HeartbeatWorkflowRequest>,
+    ) -> Result<Response<HeartbeatWorkflowResponse>, Status> {
+        observe_rpc("heartbeat_workflow", request, |request| async move {
+            let data = request.into_inner();
+            let result = heartbeat_workflow(
+                &self.client,
+                HeartbeatWorkflowInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
+                    workflow_id: data.workflow_id,
+                    fencing_token: data.fencing_token,
+                },
+            )
+            .await?;
+            Ok(Response::new(HeartbeatWorkflowResponse {
+                last_heartbeat_at: result.last_heartbeat_at,
+            }))
+        })
+        .await
+    }
+
+    async fn schedule_timer(
+        &self,
+        request: Request<ScheduleTimerRequest>,
+    ) -> Result<Response<ScheduleTimerResponse>, Status> {
+        observe_rpc("schedule_timer", request, |request| async move {
+            let data = request.into_inner();
+            let result = schedule_timer(
+                &self.client,
+                ScheduleTimerInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
+                    workflow_id: data.workflow_id,
+                    branch: data.branch,
+                    fencing_token: data.fencing_token,
+                    position: data.position,
+                    name: data.name,
+                    delay: data.delay,
+                },
+            )
+            .await?;
+            Ok(Response::new(ScheduleTimerResponse {
+                fire_at: result.fire_at,
+                fired: result.fired,
+            }))
+        })
+        .await
+    }
+
+    async fn poll_due_timers(
+        &self,
+        request: Request<PollDueTimersRequest>,
+    ) -> Result<Response<PollDueTimersResponse>, Status> {
+        observe_rpc("poll_due_timers", request, |request| async move {
+            let data = request.into_inner();
+            let result = poll_due_timers(
+                &self.client,
+                &self.events,
+                PollDueTimersInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
+                    limit: data.limit,
+                    claim_timeout: data.claim_timeout,
+                    wait_timeout: data.wait_timeout,
+                },
+            )
+            .await?;
+            Ok(Response::new(PollDueTimersResponse {
+                timers: result
+                    .timers
+                    .into_iter()
+                    .map(|timer| DueTimer {
+                        workflow_id: timer.workflow_id,
+                        branch: decode_branch(&timer.branch),
+                        position: timer.position,
+                        name: timer.name,
+                        fire_at: timer.fire_at,
+                    })
+                    .collect(),
+            }))
+        })
+        .await
+    }
+
+    async fn signal_workflow(
+        &self,
+        request: Request<SignalWorkflowRequest>,
+    ) -> Result<Response<SignalWorkflowResponse>, Status> {
+        observe_rpc("signal_workflow", request, |request| async move {
+            let data = request.into_inner();
+            let result = signal_workflow(
+                &self.client,
+                &self.quota_tracker,
+                SignalWorkflowInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
+                    workflow_id: data.workflow_id,
+                    name: data.name,
+                    payload: data.payload,
+                    signal_id: data.signal_id,
+                },
+            )
+            .await?;
+            Ok(Response::new(SignalWorkflowResponse {
+                signal_id: result.signal_id,
+                duplicate: result.duplicate,
+            }))
+        })
+        .await
+    }
+
+    async fn await_signal(
+        &self,
+        request: Request<AwaitSignalRequest>,
+    ) -> Result<Response<AwaitSignalResponse>, Status> {
+        observe_rpc("await_signal", request, |request| async move {
+            let data = request.into_inner();
+            let input = AwaitSignalInput {
+                namespace: resolve_namespace(&data.namespace).to_string(),
+                workflow_id: data.workflow_id,
+                branch: data.branch,
+                fencing_token: data.fencing_token,
+                position: data.position,
+                name: data.name,
+            };
+            let result = match data.wait_timeout {
+                Some(wait_timeout) => {
+                    wait_for_signal(
+                        &self.client,
+                        &self.quota_tracker,
+                        &self.events,
+                        input,
+                        wait_timeout,
+                    )
+                    .await
+                }
+                None => await_signal(&self.client, &self.quota_tracker, &input).await,
+            }?;
+            Ok(Response::new(AwaitSignalResponse {
+                payload: result.payload,
+            }))
+        })
+        .await
+    }
+
+    async fn list_child_workflows(
+        &self,
+        request: Request<ListChildWorkflowsRequest>,
+    ) -> Result<Response<ListChildWorkflowsResponse>, Status> {
+        observe_rpc("list_child_workflows", request, |request| async move {
+            let data = request.into_inner();
+            let result = list_child_workflows(
+                &self.client,
+                ListChildWorkflowsInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
+                    workflow_id: data.workflow_id,
+                },
+            )
+            .await?;
+            Ok(Response::new(ListChildWorkflowsResponse {
+                children: result
+                    .children
+                    .into_iter()
+                    .map(to_workflow_summary)
+                    .collect(),
+            }))
+        })
+        .await
+    }
+
+    async fn await_child_workflow(
+        &self,
+        request: Request<AwaitChildWorkflowRequest>,
+    ) -> Result<Response<AwaitChildWorkflowResponse>, Status> {
+        observe_rpc("await_child_workflow", request, |request| async move {
+            let data = request.into_inner();
+            let child_workflow_id = data.child_workflow_id.clone();
+            let input = AwaitChildWorkflowInput {
+                namespace: resolve_namespace(&data.namespace).to_string(),
+                workflow_id: data.workflow_id,
+                branch: data.branch,
+                fencing_token: data.fencing_token,
+                position: data.position,
+                child_workflow_id: data.child_workflow_id,
+            };
+            let result = match data.wait_timeout {
+                Some(wait_timeout) => {
+                    wait_for_child_workflow(&self.client, &self.events, input, wait_timeout).await
+                }
+                None => await_child_workflow(&self.client, &input).await,
+            }?;
+            Ok(Response::new(AwaitChildWorkflowResponse {
+                outcome: result.outcome.map(|outcome| ChildWorkflowOutcome {
+                    workflow_id: child_workflow_id,
+                    status: outcome.status() as i64,
+                    result: outcome.result().map(<[u8]>::to_vec),
+                }),
+            }))
+        })
+        .await
+    }
+
+    async fn register_compensation(
+        &self,
+        request: Request<RegisterCompensationRequest>,
+    ) -> Result<Response<RegisterCompensationResponse>, Status> {
+        observe_rpc("register_compensation", request, |request| async move {
+            let data = request.into_inner();
+            register_compensation(
+                &self.client,
+                &self.quota_tracker,
+                RegisterCompensationInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
+                    workflow_id: data.workflow_id,
+                    branch: data.branch,
+                    fencing_token: data.fencing_token,
+                    position: data.position,
+                    name: data.name,
+                    payload: data.payload,
+                },
+            )
+            .await?;
+            Ok(Response::new(RegisterCompensationResponse {}))
+        })
+        .await
+    }
+
+    async fn compensate_workflow(
+        &self,
+        request: Request<CompensateWorkflowRequest>,
+    ) -> Result<Response<CompensateWorkflowResponse>, Status> {
+        observe_rpc("compensate_workflow", request, |request| async move {
+            let data = request.into_inner();
+            let result = compensate_workflow(
+                &self.client,
+                CompensateWorkflowInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
+                    workflow_id: data.workflow_id,
+                },
+            )
+            .await?;
+            Ok(Response::new(CompensateWorkflowResponse {
+                fencing_token: result.fencing_token,
+            }))
+        })
+        .await
+    }
+
+    async fn lease_compensation(
+        &self,
+        request: Request<LeaseCompensationRequest>,
+    ) -> Result<Response<LeaseCompensationResponse>, Status> {
+        observe_rpc("lease_compensation", request, |request| async move {
+            let data = request.into_inner();
+            let result = lease_compensation(
+                &self.client,
+                LeaseCompensationInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
+                    workflow_id: data.workflow_id,
+                    fencing_token: data.fencing_token,
+                    lease_timeout: data.lease_timeout,
+                    worker_id: data.worker_id,
+                },
+            )
+            .await?;
+            let response = match result {
+                LeaseCompensationOutput::Granted(compensation) => LeaseCompensationResponse {
+                    response: Some(lease_compensation_response::Response::Compensation(
+                        workflow_service::Compensation {
+                            branch: decode_branch(&compensation.branch),
+                            position: compensation.position,
+                            name: compensation.name,
+                            payload: compensation.payload,
+                        },
+                    )),
+                    ..Default::default()
+                },
+                LeaseCompensationOutput::Held {
+                    remaining_lease_timeout,
+                    lease_fencing_token,
+                    lease_worker_id,
+                } => {
+                    metrics().lease_contention_total.inc();
+                    LeaseCompensationResponse {
+                        response: Some(
+                            lease_compensation_response::Response::RemainingLeaseTimeout(
+                                remaining_lease_timeout,
+                            ),
+                        ),
+                        lease_fencing_token,
+                        lease_worker_id,
+                    }
+                }
+                LeaseCompensationOutput::Done => LeaseCompensationResponse {
+                    response: Some(lease_compensation_response::Response::Done(true)),
+                    ..Default::default()
+                },
+            };
+            Ok(Response::new(response))
+        })
+        .await
+    }
+
+    async fn complete_compensation(
+        &self,
+        request: Request<CompleteCompensationRequest>,
+    ) -> Result<Response<CompleteCompensationResponse>, Status> {
+        observe_rpc("complete_compensation", request, |request| async move {
+            let data = request.into_inner();
+            let result = handle_complete_compensation(
+                &self.client,
+                CompleteCompensationInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
+                    workflow_id: data.workflow_id,
+                    branch: data.branch,
+                    fencing_token: data.fencing_token,
+                    position: data.position,
+                    worker_id: data.worker_id,
+                },
+            )
+            .await?;
+            Ok(Response::new(CompleteCompensationResponse {
+                done: result.done,
+            }))
+        })
+        .await
+    }
+
+    async fn cancel_workflow(
+        &self,
+        request: Request<CancelWorkflowRequest>,
+    ) -> Result<Response<CancelWorkflowResponse>, Status> {
+        observe_rpc("cancel_workflow", request, |request| async move {
+            let data = request.into_inner();
+            cancel_workflow(
+                &self.client,
+                CancelWorkflowInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
+                    workflow_id: data.workflow_id,
+                    expire_after: data.expire_after,
+                },
+            )
+            .await?;
+            Ok(Response::new(CancelWorkflowResponse {}))
+        })
+        .await
+    }
+
+    async fn fail_workflow(
+        &self,
+        request: Request<FailWorkflowRequest>,
+    ) -> Result<Response<FailWorkflowResponse>, Status> {
+        observe_rpc("fail_workflow", request, |request| async move {
+            let data = request.into_inner();
+            fail_workflow(
+                &self.client,
+                FailWorkflowInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
+                    workflow_id: data.workflow_id,
+                    fencing_token: data.fencing_token,
+                    expire_after: data.expire_after,
+                },
+            )
+            .await?;
+            Ok(Response::new(FailWorkflowResponse {}))
+        })
+        .await
+    }
+
+    async fn pause_workflow(
+        &self,
+        request: Request<PauseWorkflowRequest>,
+    ) -> Result<Response<PauseWorkflowResponse>, Status> {
+        observe_rpc("pause_workflow", request, |request| async move {
+            let data = request.into_inner();
+            pause_workflow(
+                &self.client,
+                PauseWorkflowInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
+                    workflow_id: data.workflow_id,
+                },
+            )
+            .await?;
+            Ok(Response::new(PauseWorkflowResponse {}))
+        })
+        .await
+    }
+
+    async fn resume_workflow(
+        &self,
+        request: Request<ResumeWorkflowRequest>,
+    ) -> Result<Response<ResumeWorkflowResponse>, Status> {
+        observe_rpc("resume_workflow", request, |request| async move {
+            let data = request.into_inner();
+            resume_workflow(
+                &self.client,
+                ResumeWorkflowInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
+                    workflow_id: data.workflow_id,
+                },
+            )
+            .await?;
+            Ok(Response::new(ResumeWorkflowResponse {}))
+        })
+        .await
+    }
+
+    async fn list_workflows(
+        &self,
+        request: Request<ListWorkflowsRequest>,
+    ) -> Result<Response<ListWorkflowsResponse>, Status> {
+        observe_rpc("list_workflows", request, |request| async move {
+            let data = request.into_inner();
+            let result = list_workflows(
+                &self.client,
+                ListWorkflowsInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
+                    status: data.status,
+                    name: data.name,
+                    created_after: data.created_after,
+                    created_before: data.created_before,
+                    completed_after: data.completed_after,
+                    completed_before: data.completed_before,
+                    last_heartbeat_before: data.last_heartbeat_before,
+                    page_size: data.page_size,
+                    page_cursor: data.page_cursor,
+                },
+            )
+            .await?;
+            Ok(Response::new(ListWorkflowsResponse {
+                workflows: result
+                    .workflows
+                    .into_iter()
+                    .map(to_workflow_summary)
+                    .collect(),
+                next_page_cursor: result.next_page_cursor,
+            }))
+        })
+        .await
+    }
+
+    async fn get_workflow_history(
+        &self,
+        request: Request<GetWorkflowHistoryRequest>,
+    ) -> Result<Response<GetWorkflowHistoryResponse>, Status> {
+        observe_rpc("get_workflow_history", request, |request| async move {
+            let data = request.into_inner();
+            let workflow_id = data.workflow_id;
+            let result = get_workflow_history(
+                &self.client,
+                WorkflowHistoryInput {
+                    namespace: resolve_namespace(&data.namespace).to_string(),
+                    workflow_id: workflow_id.clone(),
+                    include_values: data.include_values,
+                },
+            )
+            .await?;
+            Ok(Response::new(GetWorkflowHistoryResponse {
+                workflow_id,
+                checkpoints: result
+                    .checkpoints
+                    .into_iter()
+                    .map(|checkpoint| CheckpointHistoryEntry {
+                        branch: decode_branch(&checkpoint.branch),
+                        position: checkpoint.position,
+                        idempotency_key: checkpoint.idempotency_key,
+                        created_at: checkpoint.created_at,
+                        value_size: checkpoint.value_size,
+                        value: checkpoint.value,
+                        task_name: checkpoint.task_name,
+                    })
+                    .collect(),
+                active_leases: result
+                    .active_leases
+                    .into_iter()
+                    .map(|lease| ActiveLease {
+                        branch: lease.branch,
+                        position: lease.position,
+                        lease_timeout: lease.lease_timeout,
+                        created_at: lease.created_at,
+                        remaining_lease_timeout: lease.remaining_lease_timeout,
+                        fencing_token: lease.fencing_token,
+                        worker_id: lease.worker_id,
+                    })
+                    .collect(),
+            }))
+        })
+        .await
+    }
+
+    async fn create_namespace(
+        &self,
+        request: Request<CreateNamespaceRequest>,
+    ) -> Result<Response<CreateNamespaceResponse>, Status> {
+        observe_rpc("create_namespace", request, |request| async move {
+            let data = request.into_inner();
+            let result = create_namespace(
+                &self.client,
+                CreateNamespaceInput {
+                    name: data.name,
+                    default_retention: data.default_retention,
+                    quotas: data.quotas.map(from_quotas_message).unwrap_or_default(),
+                },
+            )
+            .await?;
+            Ok(Response::new(CreateNamespaceResponse {
+                namespace: Some(to_namespace_message(result.namespace)),
+            }))
+        })
+        .await
+    }
+
+    async fn get_namespace(
+        &self,
+        request: Request<GetNamespaceRequest>,
+    ) -> Result<Response<GetNamespaceResponse>, Status> {
+        observe_rpc("get_namespace", request, |request| async move {
+            let data = request.into_inner();
+            let result = get_namespace(&self.client, GetNamespaceInput { name: data.name }).await?;
+            Ok(Response::new(GetNamespaceResponse {
+                namespace: Some(to_namespace_message(result.namespace)),
+            }))
+        })
+        .await
+    }
+
+    async fn list_namespaces(
+        &self,
+        request: Request<ListNamespacesRequest>,
+    ) -> Result<Response<ListNamespacesResponse>, Status> {
+        observe_rpc("list_namespaces", request, |request| async move {
+            let principal = request.extensions().get::<Principal>().cloned();
+            let result = list_namespaces(&self.client).await?;
+            Ok(Response::new(ListNamespacesResponse {
+                namespaces: result
+                    .namespaces
+                    .into_iter()
+                    .filter(|namespace| {
+                        principal
+                            .as_ref()
+                            .is_some_and(|principal| principal.permits_namespace(&namespace.name))
+                    })
+                    .map(to_namespace_message)
+                    .collect(),
+            }))
+        })
+        .await
+    }
+
+    async fn update_namespace(
+        &self,
+        request: Request<UpdateNamespaceRequest>,
+    ) -> Result<Response<UpdateNamespaceResponse>, Status> {
+        observe_rpc("update_namespace", request, |request| async move {
+            let data = request.into_inner();
+            let result = update_namespace(
+                &self.client,
+                UpdateNamespaceInput {
+                    name: data.name,
+                    default_retention: data.default_retention,
+                    quotas: data.quotas.map(from_quotas_message),
+                },
+            )
+            .await?;
+            Ok(Response::new(UpdateNamespaceResponse {
+                namespace: Some(to_namespace_message(result.namespace)),
+            }))
+        })
+        .await
+    }
+
+    async fn delete_namespace(
+        &self,
+        request: Request<DeleteNamespaceRequest>,
+    ) -> Result<Response<DeleteNamespaceResponse>, Status> {
+        observe_rpc("delete_namespace", request, |request| async move {
+            let data = request.into_inner();
+            delete_namespace(&self.client, DeleteNamespaceInput { name: data.name }).await?;
+            Ok(Response::new(DeleteNamespaceResponse {}))
+        })
+        .await
+    }
+
+    async fn get_namespace_usage(
+        &self,
+        request: Request<GetNamespaceUsageRequest>,
+    ) -> Result<Response<GetNamespaceUsageResponse>, Status> {
+        observe_rpc("get_namespace_usage", request, |request| async move {
+            let data = request.into_inner();
+            let result = get_namespace_usage(
+                &self.client,
+                &self.quota_tracker,
+                NamespaceUsageInput { name: data.name },
+            )
+            .await?;
+            Ok(Response::new(GetNamespaceUsageResponse {
+                quotas: Some(to_quotas_message(result.quotas)),
+                running_workflows: result.running_workflows,
+                stored_bytes: result.stored_bytes,
+                checkpoints_last_second: result.checkpoints_last_second,
+            }))
+        })
+        .await
     }
 }
 
 pub async fn start_server(
     rpc_addr: &str,
-    client: &Client,
+    service: WorkflowService,
+    authenticator: Authenticator,
+    tls: Option<&TlsConfig>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // defining address for our service
-    let addr = rpc_addr.parse().unwrap();
+    let addr = rpc_addr.parse()?;
+    let mut builder = Server::builder();
+    if let Some(tls) = tls {
+        builder = builder.tls_config(tls.server_config()?)?;
+    }
     println!("Server listening on {addr}");
     // adding our service to our server.
-    Server::builder()
-        .add_service(WorkflowServiceImplServer::new(WorkflowService {
-            // It's cheap to clone because of inner Arc.
-            client: client.clone(),
-        }))
+    builder
+        .add_service(service.into_server(authenticator))
         .serve(addr)
         .await?;
     Ok(())
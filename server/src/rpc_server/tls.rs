@@ -0,0 +1,56 @@
+use std::env::var;
+use std::error::Error;
+use std::fs;
+
+use tonic::transport::{Certificate, Identity, ServerTlsConfig};
+
+/// TLS of the RPC listener. With a client CA, callers must present a certificate signed by it.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+    pub client_ca_path: Option<String>,
+}
+
+impl TlsConfig {
+    ///
+    /// Reads `TLS_CERT` and `TLS_KEY`, TLS stays disabled when neither is set.
+    /// `TLS_CLIENT_CA` enables mTLS.
+    pub fn from_env() -> Result<Option<Self>, Box<dyn Error>> {
+        Self::from_paths(
+            var("TLS_CERT").ok(),
+            var("TLS_KEY").ok(),
+            var("TLS_CLIENT_CA").ok(),
+        )
+    }
+
+    ///
+    /// Fails on a partial configuration, e.g. a cert without key or a client CA without cert,
+    /// instead of starting a plaintext listener the operator didn't ask for.
+    pub fn from_paths(
+        cert_path: Option<String>,
+        key_path: Option<String>,
+        client_ca_path: Option<String>,
+    ) -> Result<Option<Self>, Box<dyn Error>> {
+        match (cert_path, key_path) {
+            (Some(cert_path), Some(key_path)) => Ok(Some(Self {
+                cert_path,
+                key_path,
+                client_ca_path,
+            })),
+            (None, None) if client_ca_path.is_none() => Ok(None),
+            (None, None) => Err("TLS_CLIENT_CA is set without TLS_CERT and TLS_KEY".into()),
+            (Some(_), None) => Err("TLS_CERT is set without TLS_KEY".into()),
+            (None, Some(_)) => Err("TLS_KEY is set without TLS_CERT".into()),
+        }
+    }
+
+    pub fn server_config(&self) -> Result<ServerTlsConfig, std::io::Error> {
+        let identity = Identity::from_pem(fs::read(&self.cert_path)?, fs::read(&self.key_path)?);
+        let mut config = ServerTlsConfig::new().identity(identity);
+        if let Some(client_ca_path) = &self.client_ca_path {
+            config = config.client_ca_root(Certificate::from_pem(fs::read(client_ca_path)?));
+        }
+        Ok(config)
+    }
+}
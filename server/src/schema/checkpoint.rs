@@ -6,16 +6,39 @@ pub struct CheckpointValue {
     pub position: i64,
     pub idempotency_key: String,
     pub value: Option<Vec<u8>>,
+    pub task_name: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Checkpoint {
+/// What a step records at its position.
+#[derive(Debug, Clone)]
+pub struct NewCheckpoint {
+    pub idempotency_key: String,
+    pub value: Vec<u8>,
+    pub task_name: Option<String>,
+}
+
+/// The checkpoint value recorded at the parent position of a finished child workflow. Encoded like
+/// the `ChildWorkflowOutcome` message, which clients decode it as.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ChildOutcomeValue {
+    #[prost(string, tag = "1")]
     pub workflow_id: String,
-    pub value: Option<Vec<u8>>,
-    pub lease_timeout: i64,
+    #[prost(int64, tag = "2")]
+    pub status: i64,
+    #[prost(bytes = "vec", optional, tag = "3")]
+    pub result: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointHistoryEntry {
+    pub branch: String,
     pub position: i64,
     pub idempotency_key: String,
+    pub task_name: Option<String>,
     pub created_at: i64,
+    /// `None` while only an idempotency key was generated for the position
+    pub value_size: Option<i64>,
+    pub value: Option<Vec<u8>>,
 }
 
 impl From<Row<'_>> for CheckpointValue {
@@ -24,19 +47,21 @@ impl From<Row<'_>> for CheckpointValue {
             position: row.get("position"),
             idempotency_key: row.get("idempotency_key"),
             value: row.get("value"),
+            task_name: row.get("task_name"),
         }
     }
 }
 
-impl From<Row<'_>> for Checkpoint {
+impl From<Row<'_>> for CheckpointHistoryEntry {
     fn from(mut row: Row<'_>) -> Self {
         Self {
-            workflow_id: row.get("workflow_id"),
-            value: row.get("value"),
-            lease_timeout: row.get("lease_timeout"),
+            branch: row.get("branch"),
             position: row.get("position"),
             idempotency_key: row.get("idempotency_key"),
+            task_name: row.get("task_name"),
             created_at: row.get("created_at"),
+            value_size: row.get("value_size"),
+            value: row.get("value"),
         }
     }
 }
@@ -0,0 +1,44 @@
+use hiqlite::Row;
+use serde::{Deserialize, Serialize};
+
+/// Undoes the step at `position` of `branch` when the workflow is compensated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Compensation {
+    /// Encoded path of the branch of the position, empty for the top level of the workflow.
+    pub branch: String,
+    pub position: i64,
+    pub name: String,
+    pub payload: Vec<u8>,
+    pub lease_expire_at: Option<i64>,
+    pub lease_fencing_token: Option<i64>,
+    pub lease_worker_id: Option<String>,
+    pub compensated_at: Option<i64>,
+}
+
+impl Compensation {
+    ///
+    /// Whether the lease belongs to another worker than the one identified by `fencing_token` and
+    /// `worker_id`, like [`LeasedCheckpointValue::is_held_by_other`](crate::schema::leased_checkpoint::LeasedCheckpointValue::is_held_by_other).
+    pub fn is_held_by_other(&self, fencing_token: i64, worker_id: Option<&str>) -> bool {
+        self.lease_fencing_token != Some(fencing_token)
+            || matches!(
+                (self.lease_worker_id.as_deref(), worker_id),
+                (Some(holder), Some(requester)) if holder != requester
+            )
+    }
+}
+
+impl From<Row<'_>> for Compensation {
+    fn from(mut row: Row<'_>) -> Self {
+        Self {
+            branch: row.get("branch"),
+            position: row.get("position"),
+            name: row.get("name"),
+            payload: row.get("payload"),
+            lease_expire_at: row.get("lease_expire_at"),
+            lease_fencing_token: row.get("lease_fencing_token"),
+            lease_worker_id: row.get("lease_worker_id"),
+            compensated_at: row.get("compensated_at"),
+        }
+    }
+}
@@ -3,23 +3,82 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct LeasedCheckpointValue {
+    /// Unique per grant, so a worker can tell whether its lease request won.
+    pub lease_id: String,
     pub lease_timeout: i64,
     pub created_at: i64,
+    /// Fencing token of the worker holding the lease.
+    pub fencing_token: i64,
+    /// Identity the worker sent with the lease request, e.g. its pod name.
+    pub worker_id: Option<String>,
 }
 
+impl LeasedCheckpointValue {
+    ///
+    /// Whether the lease was taken by another worker than the one with `fencing_token` and `worker_id`.
+    /// A request without worker id is only matched by the fencing token.
+    pub fn is_held_by_other(&self, fencing_token: i64, worker_id: Option<&str>) -> bool {
+        self.fencing_token != fencing_token
+            || matches!(
+                (self.worker_id.as_deref(), worker_id),
+                (Some(holder), Some(requester)) if holder != requester
+            )
+    }
+}
+
+/// A lease a worker requests for a position.
+#[derive(Debug, Clone)]
+pub struct LeaseRequest {
+    pub lease_timeout: i64,
+    pub fencing_token: i64,
+    pub worker_id: Option<String>,
+}
+
+/// Outcome of a lease request, holding the lease of the position after the request.
+#[derive(Debug, Clone)]
+pub enum LeaseGrant {
+    Granted(LeasedCheckpointValue),
+    /// Another worker holds an active lease.
+    Held(LeasedCheckpointValue),
+    /// The position was checkpointed after the request read it, or holds the timer or the delivered
+    /// signal of another step, so there is nothing to lease.
+    Recorded,
+}
+
+/// A row of the `Leases` table.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LeasedCheckpoint {
+    pub namespace: String,
     pub workflow_id: String,
-    pub position_checksum: i64,
+    pub branch: String,
+    pub position: i64,
+    pub lease_id: String,
+    pub fencing_token: i64,
+    pub worker_id: Option<String>,
     pub lease_timeout: i64,
     pub created_at: i64,
 }
 
+impl From<LeasedCheckpoint> for LeasedCheckpointValue {
+    fn from(leased_checkpoint: LeasedCheckpoint) -> Self {
+        Self {
+            lease_id: leased_checkpoint.lease_id,
+            lease_timeout: leased_checkpoint.lease_timeout,
+            created_at: leased_checkpoint.created_at,
+            fencing_token: leased_checkpoint.fencing_token,
+            worker_id: leased_checkpoint.worker_id,
+        }
+    }
+}
+
 impl From<Row<'_>> for LeasedCheckpointValue {
     fn from(mut row: Row<'_>) -> Self {
         Self {
+            lease_id: row.get("lease_id"),
             lease_timeout: row.get("lease_timeout"),
             created_at: row.get("created_at"),
+            fencing_token: row.get("fencing_token"),
+            worker_id: row.get("worker_id"),
         }
     }
 }
@@ -1,4 +1,9 @@
 pub mod checkpoint;
+pub mod compensation;
 pub mod leased_checkpoint;
+pub mod namespace;
+pub mod position;
+pub mod signal;
+pub mod timer;
 pub mod workflow;
-pub mod workflow_fencing_token;
+pub mod workflow_payload;
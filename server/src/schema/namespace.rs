@@ -0,0 +1,54 @@
+use hiqlite::Row;
+use serde::{Deserialize, Serialize};
+
+/// Namespace of requests that don't name one, holding all workflows created before namespaces existed.
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// Admission limits of a namespace, `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct NamespaceQuotas {
+    /// Running and paused workflows.
+    pub max_running_workflows: Option<i64>,
+    /// Enforced by every node on its own, so a cluster accepts up to `nodes * limit`.
+    pub max_checkpoints_per_second: Option<i64>,
+    pub max_checkpoint_bytes: Option<i64>,
+    /// Sum of all checkpoint values of the namespace.
+    pub max_stored_bytes: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Namespace {
+    pub name: String,
+    /// Retention in milliseconds of finished workflows when the request has no `expire_after`.
+    pub default_retention: i64,
+    pub created_at: i64,
+    pub max_running_workflows: Option<i64>,
+    pub max_checkpoints_per_second: Option<i64>,
+    pub max_checkpoint_bytes: Option<i64>,
+    pub max_stored_bytes: Option<i64>,
+}
+
+impl Namespace {
+    pub fn quotas(&self) -> NamespaceQuotas {
+        NamespaceQuotas {
+            max_running_workflows: self.max_running_workflows,
+            max_checkpoints_per_second: self.max_checkpoints_per_second,
+            max_checkpoint_bytes: self.max_checkpoint_bytes,
+            max_stored_bytes: self.max_stored_bytes,
+        }
+    }
+}
+
+impl From<Row<'_>> for Namespace {
+    fn from(mut row: Row<'_>) -> Self {
+        Self {
+            name: row.get("name"),
+            default_retention: row.get("default_retention"),
+            created_at: row.get("created_at"),
+            max_running_workflows: row.get("max_running_workflows"),
+            max_checkpoints_per_second: row.get("max_checkpoints_per_second"),
+            max_checkpoint_bytes: row.get("max_checkpoint_bytes"),
+            max_stored_bytes: row.get("max_stored_bytes"),
+        }
+    }
+}
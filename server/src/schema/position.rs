@@ -0,0 +1,30 @@
+use hiqlite::Row;
+use serde::{Deserialize, Serialize};
+
+/// The records kept at a position, which tell what kind of step ran there.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PositionRecords {
+    /// Idempotency key and task name of the checkpoint of the position.
+    pub checkpoint_idempotency_key: Option<String>,
+    pub checkpoint_task_name: Option<String>,
+    /// Name of the timer scheduled at the position.
+    pub timer_name: Option<String>,
+    /// Id and name of the signal delivered to the position.
+    pub signal_id: Option<String>,
+    pub signal_name: Option<String>,
+    /// Name of the compensation registered for the step at the position.
+    pub compensation_name: Option<String>,
+}
+
+impl From<Row<'_>> for PositionRecords {
+    fn from(mut row: Row<'_>) -> Self {
+        Self {
+            checkpoint_idempotency_key: row.get("checkpoint_idempotency_key"),
+            checkpoint_task_name: row.get("checkpoint_task_name"),
+            timer_name: row.get("timer_name"),
+            signal_id: row.get("signal_id"),
+            signal_name: row.get("signal_name"),
+            compensation_name: row.get("compensation_name"),
+        }
+    }
+}
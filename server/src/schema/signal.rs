@@ -0,0 +1,25 @@
+use hiqlite::Row;
+use serde::{Deserialize, Serialize};
+
+/// A signal sent to a workflow, delivered in the order signals of its name were sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Signal {
+    pub signal_id: String,
+    pub name: String,
+    pub payload: Vec<u8>,
+    /// Encoded path of the branch of `delivered_position`, set together with it.
+    pub delivered_branch: Option<String>,
+    pub delivered_position: Option<i64>,
+}
+
+impl From<Row<'_>> for Signal {
+    fn from(mut row: Row<'_>) -> Self {
+        Self {
+            signal_id: row.get("signal_id"),
+            name: row.get("name"),
+            payload: row.get("payload"),
+            delivered_branch: row.get("delivered_branch"),
+            delivered_position: row.get("delivered_position"),
+        }
+    }
+}
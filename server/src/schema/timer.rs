@@ -0,0 +1,28 @@
+use hiqlite::Row;
+use serde::{Deserialize, Serialize};
+
+/// A durable timer, recorded at a position of a workflow like a step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Timer {
+    pub workflow_id: String,
+    /// Encoded path of the branch of the position, empty for the top level of the workflow.
+    pub branch: String,
+    pub position: i64,
+    pub name: String,
+    pub fire_at: i64,
+    /// Set once a replay of the workflow passed the timer after it fired.
+    pub fired_at: Option<i64>,
+}
+
+impl From<Row<'_>> for Timer {
+    fn from(mut row: Row<'_>) -> Self {
+        Self {
+            workflow_id: row.get("workflow_id"),
+            branch: row.get("branch"),
+            position: row.get("position"),
+            name: row.get("name"),
+            fire_at: row.get("fire_at"),
+            fired_at: row.get("fired_at"),
+        }
+    }
+}
@@ -1,35 +1,93 @@
 use hiqlite::Row;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum WorkflowStatus {
     Running = 0,
     Completed = 1,
+    Failed = 2,
+    Cancelled = 3,
+    Paused = 4,
+    /// The workflow was still running at its execution deadline.
+    TimedOut = 5,
+    /// Set by `compensate_workflow`, the registered compensations are being run.
+    Compensating = 6,
+    /// All compensations of the workflow ran.
+    Compensated = 7,
+}
+
+impl WorkflowStatus {
+    pub const TERMINAL: [WorkflowStatus; 5] = [
+        WorkflowStatus::Completed,
+        WorkflowStatus::Failed,
+        WorkflowStatus::Cancelled,
+        WorkflowStatus::TimedOut,
+        WorkflowStatus::Compensated,
+    ];
+
+    pub fn from_i64(value: i64) -> Option<Self> {
+        match value {
+            0 => Some(WorkflowStatus::Running),
+            1 => Some(WorkflowStatus::Completed),
+            2 => Some(WorkflowStatus::Failed),
+            3 => Some(WorkflowStatus::Cancelled),
+            4 => Some(WorkflowStatus::Paused),
+            5 => Some(WorkflowStatus::TimedOut),
+            6 => Some(WorkflowStatus::Compensating),
+            7 => Some(WorkflowStatus::Compensated),
+            _ => None,
+        }
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        WorkflowStatus::TERMINAL.contains(self)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Workflow {
+    pub namespace: String,
     pub id: String,
     pub status: i64,
     pub expire_at: Option<i64>,
     pub completed_at: Option<i64>,
     pub name: Option<String>,
     pub created_at: i64,
+    /// Set by the execution timeout of `workflow_start`, the workflow times out if it is still running then.
+    pub execution_deadline: Option<i64>,
+    /// Time of the last `heartbeat_workflow`, `None` if the workers of the workflow don't send heartbeats.
+    pub last_heartbeat_at: Option<i64>,
+    /// Set for a child workflow, whose outcome is checkpointed at `parent_position` of
+    /// `parent_branch` of the parent.
+    pub parent_workflow_id: Option<String>,
+    pub parent_branch: Option<String>,
+    pub parent_position: Option<i64>,
+}
+
+/// The position of the parent a child workflow is started at.
+#[derive(Debug, Clone)]
+pub struct WorkflowParent {
+    pub workflow_id: String,
+    /// Encoded path of the branch of the position, empty for the top level of the parent.
+    pub branch: String,
+    pub position: i64,
 }
 
 impl From<Row<'_>> for Workflow {
     fn from(mut row: Row<'_>) -> Self {
         Self {
+            namespace: row.get("namespace"),
             id: row.get("id"),
-            status: match row.get::<i64>("status") {
-                0 => WorkflowStatus::Running,
-                1 => WorkflowStatus::Completed,
-                _ => panic!("Invalid workflow status"),
-            } as i64,
+            status: row.get("status"),
             expire_at: row.get::<Option<i64>>("expire_at"),
             completed_at: row.get::<Option<i64>>("completed_at"),
             name: row.get::<Option<String>>("name"),
             created_at: row.get("created_at"),
+            execution_deadline: row.get::<Option<i64>>("execution_deadline"),
+            last_heartbeat_at: row.get::<Option<i64>>("last_heartbeat_at"),
+            parent_workflow_id: row.get::<Option<String>>("parent_workflow_id"),
+            parent_branch: row.get::<Option<String>>("parent_branch"),
+            parent_position: row.get::<Option<i64>>("parent_position"),
         }
     }
 }
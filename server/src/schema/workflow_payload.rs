@@ -0,0 +1,20 @@
+use hiqlite::Row;
+use serde::{Deserialize, Serialize};
+
+/// Opaque payloads of a workflow, encoded by the client.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowPayload {
+    /// Sent with the first `workflow_start` that carried one.
+    pub input: Option<Vec<u8>>,
+    /// Sent with `complete_workflow`.
+    pub result: Option<Vec<u8>>,
+}
+
+impl From<Row<'_>> for WorkflowPayload {
+    fn from(mut row: Row<'_>) -> Self {
+        Self {
+            input: row.get("input"),
+            result: row.get("result"),
+        }
+    }
+}
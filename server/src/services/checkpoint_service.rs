@@ -1,47 +1,75 @@
-use std::error::Error;
-
 use hiqlite::Client;
+use tokio::time::{Duration, Instant};
+use tracing::instrument;
 use uuid::Uuid;
 
+use crate::events::lease_events::{publish_lease_event, wait_for_lease_event};
+use crate::events::workflow_events::WorkflowEvents;
+use crate::helpers::branch::{decode_branch, encode_branch, position_path};
 use crate::helpers::common::return_error_if_true;
-use crate::repositories::lease_checkpoint::{get_leased_checkpoint, lease_checkpoint};
+use crate::helpers::errors::{CheckpointMismatch, WorkflowError};
+use crate::quotas::quota_tracker::QuotaTracker;
+use crate::repositories::lease_checkpoint::{
+    get_leased_checkpoint, get_leased_checkpoints, lease_checkpoint, renew_leased_checkpoint,
+};
 use crate::repositories::{
-    checkpoints::{create_checkpoint, get_checkpoint},
+    checkpoints::{create_checkpoint, get_checkpoint, get_checkpoints, reserve_idempotency_key},
     lease_checkpoint::remove_leased_checkpoint,
+    workflows::get_workflow,
     workflows_fencing_tokens::get_workflow_fencing_token,
 };
-use crate::schema::leased_checkpoint::LeasedCheckpointValue;
+use crate::schema::checkpoint::{CheckpointHistoryEntry, NewCheckpoint};
+use crate::schema::leased_checkpoint::{LeaseGrant, LeaseRequest, LeasedCheckpointValue};
+use crate::schema::workflow::WorkflowStatus;
+use crate::services::position_service::{PositionStep, check_position_step};
+use crate::services::quota_service::check_checkpoint_admission;
 
 pub struct CheckpointInput {
+    pub namespace: String,
     pub workflow_id: String,
+    /// Path of the branch the position belongs to, empty for the top level of the workflow.
+    pub branch: Vec<i64>,
     pub fencing_token: i64,
     pub position: i64,
     pub value: Vec<u8>,
     pub idempotency_key: String,
+    pub task_name: Option<String>,
+    pub worker_id: Option<String>,
 }
 
 pub struct CheckpointOutput {
     pub abort: bool,
 }
 
+/// Upper bound for how long a lease request can be parked, in milliseconds.
+const MAX_LEASE_WAIT_TIMEOUT: i64 = 60_000;
+
 ///
 /// Returns the remaining lease timeout in milliseconds.
 fn diff_lease_expiry_from_now(leased_checkpoint: &LeasedCheckpointValue) -> i64 {
     let now = chrono::Utc::now().timestamp_millis();
-    let remaining_lease_timeout = leased_checkpoint
+    leased_checkpoint
         .created_at
         .saturating_add(leased_checkpoint.lease_timeout)
-        .saturating_sub(now);
-    remaining_lease_timeout
+        .saturating_sub(now)
 }
 
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, branch = ?data.branch, position = data.position, fencing_token = data.fencing_token))]
 pub async fn handle_checkpoint(
     client: &Client,
+    quota_tracker: &QuotaTracker,
     data: CheckpointInput,
-) -> Result<CheckpointOutput, Box<dyn Error + Send + Sync>> {
+) -> Result<CheckpointOutput, WorkflowError> {
+    let branch = encode_branch(&data.branch)?;
     let (internal_fencing_token, leased_checkpoint) = tokio::join!(
-        get_workflow_fencing_token(client, &data.workflow_id),
-        remove_leased_checkpoint(client, &data.workflow_id, data.position),
+        get_workflow_fencing_token(client, &data.namespace, &data.workflow_id),
+        get_leased_checkpoint(
+            client,
+            &data.namespace,
+            &data.workflow_id,
+            &branch,
+            data.position
+        ),
     );
 
     let stored_fencing_token = internal_fencing_token?;
@@ -49,172 +77,566 @@ pub async fn handle_checkpoint(
 
     return_error_if_true(
         stored_fencing_token.is_none(),
-        Box::new(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "fencing_token_not_found",
-        )),
+        WorkflowError::FencingTokenNotFound,
     )?;
+    let stored_fencing_token = stored_fencing_token.unwrap();
 
     let mut abort = false;
-    let is_fencing_token_expired = stored_fencing_token.unwrap() > data.fencing_token;
+    let is_fencing_token_expired = stored_fencing_token > data.fencing_token;
 
-    // Reject the result if the lease timeout is expired for the worker lease used to belong to.
-    // if lease timeout is expired, then we need to check if the fencing token is expired
-    if leased_checkpoint.is_some() && diff_lease_expiry_from_now(&leased_checkpoint.unwrap()) <= 0 {
+    if let Some(leased_checkpoint) = &leased_checkpoint {
+        let is_lease_active = diff_lease_expiry_from_now(leased_checkpoint) > 0;
+        // the lease expired and another worker took the position, which is still running the task
+        return_error_if_true(
+            is_lease_active
+                && leased_checkpoint
+                    .is_held_by_other(data.fencing_token, data.worker_id.as_deref()),
+            WorkflowError::LeaseHeld {
+                lease_fencing_token: leased_checkpoint.fencing_token,
+                lease_worker_id: leased_checkpoint.worker_id.clone(),
+            },
+        )?;
+        // Reject the result if the lease timeout is expired for the worker lease used to belong to.
+        // if lease timeout is expired, then we need to check if the fencing token is expired
         return_error_if_true(
-            is_fencing_token_expired,
-            Box::new(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "fencing_token_expired",
-            )),
+            !is_lease_active && is_fencing_token_expired,
+            WorkflowError::FencingTokenExpired {
+                current_fencing_token: stored_fencing_token,
+                sent_fencing_token: data.fencing_token,
+            },
         )?;
     }
 
     if is_fencing_token_expired {
         abort = true;
     }
-    create_checkpoint(
+    // checked once the worker may write the position, but before its lease is removed, so a
+    // rejected worker can retry within its lease
+    let value_bytes = data.value.len() as i64;
+    check_checkpoint_admission(client, quota_tracker, &data.namespace, value_bytes).await?;
+    let written = create_checkpoint(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &branch,
+        data.position,
+        NewCheckpoint {
+            idempotency_key: data.idempotency_key,
+            value: data.value,
+            task_name: data.task_name,
+        },
+    )
+    .await?;
+    if written {
+        quota_tracker.add_stored_bytes(&data.namespace, value_bytes);
+    }
+    remove_leased_checkpoint(
         client,
+        &data.namespace,
         &data.workflow_id,
-        Some(data.value),
+        &branch,
         data.position,
-        data.idempotency_key,
+        Some(data.fencing_token),
+        data.worker_id.as_deref(),
     )
     .await?;
+    publish_lease_event(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &branch,
+        data.position,
+    )
+    .await;
 
     Ok(CheckpointOutput { abort })
 }
 
+#[derive(Clone)]
 pub struct LeaseCheckpointInput {
+    pub namespace: String,
     pub workflow_id: String,
+    pub branch: Vec<i64>,
     pub fencing_token: i64,
     pub position: i64,
     pub lease_timeout: i64,
     pub idempotency_key: String,
+    pub task_name: Option<String>,
+    pub worker_id: Option<String>,
 }
 
 pub enum LeaseCheckpointReturnType {
     CheckpointValue(Vec<u8>),
-    RemainingLeaseTimeout(i64),
+    /// The position is leased by another worker, identified by its fencing token and worker id.
+    RemainingLeaseTimeout {
+        remaining_lease_timeout: i64,
+        fencing_token: i64,
+        worker_id: Option<String>,
+    },
 }
 
 pub struct LeaseCheckpointOutput {
     pub response: Option<LeaseCheckpointReturnType>,
 }
 
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, branch = ?data.branch, position = data.position, fencing_token = data.fencing_token))]
 pub async fn handle_lease_checkpoint(
     client: &Client,
     data: LeaseCheckpointInput,
-) -> Result<LeaseCheckpointOutput, Box<dyn Error + Send + Sync>> {
-    // A lock key can be any String to provide the most flexibility.
-    // It behaves the same as any other lock - it will be released on drop and as long as it
-    // exists, other locks will have to wait.
-    //
-    // In the current implementation, distributed locks have an internal timeout of 10 seconds.
-    // When this time expires, a lock will be considered "dead" because of network issues, just
-    // in case it has not been possible to release the lock properly. This prevents deadlocks
-    // just because some client or server crashed.
-
-    let result = get_checkpoint(client, &data.workflow_id, data.position).await?;
-
+) -> Result<LeaseCheckpointOutput, WorkflowError> {
+    let branch = encode_branch(&data.branch)?;
     // if checkpoint is already leased, then we need to return the value
-    if let Some(checkpoint) = result {
-        return_error_if_true(
-            checkpoint.idempotency_key != data.idempotency_key,
-            Box::new(std::io::Error::new(
-                std::io::ErrorKind::Interrupted,
-                "non_deterministic_checkpoint_found",
-            )),
-        )?;
-        return_error_if_true(
-            checkpoint.value.is_none(),
-            Box::new(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "checkpoint_value_not_found",
-            )),
-        )?;
-        return Ok(LeaseCheckpointOutput {
-            response: Some(LeaseCheckpointReturnType::CheckpointValue(
-                checkpoint.value.unwrap(),
-            )),
-        });
+    if let Some(output) = recorded_checkpoint_output(client, &branch, &data).await? {
+        return Ok(output);
     }
 
-    let lock_key = format!("{}:{}", data.workflow_id, data.position);
-    let _ = client.lock(lock_key).await?;
-
     let sent_fencing_token = data.fencing_token;
-    let (leased_checkpoint_result, workflow_fencing_token) = tokio::join!(
-        get_leased_checkpoint(client, &data.workflow_id, data.position),
-        get_workflow_fencing_token(client, &data.workflow_id),
+    let (leased_checkpoint_result, workflow_fencing_token, workflow) = tokio::join!(
+        get_leased_checkpoint(
+            client,
+            &data.namespace,
+            &data.workflow_id,
+            &branch,
+            data.position
+        ),
+        get_workflow_fencing_token(client, &data.namespace, &data.workflow_id),
+        get_workflow(client, &data.namespace, &data.workflow_id),
     );
     let leased_checkpoint_option = leased_checkpoint_result?;
     let found_fencing_token = workflow_fencing_token?;
+    let workflow_status = workflow?.map(|workflow| workflow.status);
 
-    if let Some(leased_checkpoint) = leased_checkpoint_option {
-        let remaining_time = diff_lease_expiry_from_now(&leased_checkpoint);
-        if remaining_time > 0 {
-            return Ok(LeaseCheckpointOutput {
-                response: Some(LeaseCheckpointReturnType::RemainingLeaseTimeout(
-                    remaining_time,
-                )),
-            });
-        }
+    // answers contended positions without a write, the lease itself is granted by a conditional write below
+    if let Some(leased_checkpoint) = leased_checkpoint_option
+        && diff_lease_expiry_from_now(&leased_checkpoint) > 0
+    {
+        return Ok(remaining_lease_timeout_output(leased_checkpoint));
     }
 
+    // paused and finished workflows must not make progress
+    if let Some(status) = workflow_status {
+        return_error_if_true(
+            status == WorkflowStatus::Paused as i64,
+            WorkflowError::WorkflowPaused,
+        )?;
+        return_error_if_true(
+            status == WorkflowStatus::Compensating as i64,
+            WorkflowError::WorkflowCompensating,
+        )?;
+        return_error_if_true(
+            WorkflowStatus::from_i64(status).is_some_and(|status| status.is_terminal()),
+            WorkflowError::WorkflowTerminated { status },
+        )?;
+    }
     return_error_if_true(
         found_fencing_token.is_none(),
-        Box::new(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            "fencing_token_not_found",
-        )),
+        WorkflowError::FencingTokenNotFound,
     )?;
     // old fencing tokens can not be used to lease a checkpoint
     let stored_fencing_token = found_fencing_token.unwrap();
 
     return_error_if_true(
         stored_fencing_token > sent_fencing_token,
-        Box::new(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            "fencing_token_expired",
-        )),
+        WorkflowError::FencingTokenExpired {
+            current_fencing_token: stored_fencing_token,
+            sent_fencing_token,
+        },
     )?;
 
     // if fencing token is the same, then we need to lease the checkpoint
     if sent_fencing_token == stored_fencing_token {
-        lease_checkpoint(client, data.workflow_id, data.position, data.lease_timeout).await?;
-        return Ok(LeaseCheckpointOutput { response: None });
+        let lease_grant = lease_checkpoint(
+            client,
+            &data.namespace,
+            &data.workflow_id,
+            &branch,
+            data.position,
+            LeaseRequest {
+                lease_timeout: data.lease_timeout,
+                fencing_token: sent_fencing_token,
+                worker_id: data.worker_id.clone(),
+            },
+        )
+        .await?;
+        return match lease_grant {
+            LeaseGrant::Granted(_) => Ok(LeaseCheckpointOutput { response: None }),
+            // another worker won the race for the position since it was read above
+            LeaseGrant::Held(leased_checkpoint) => {
+                Ok(remaining_lease_timeout_output(leased_checkpoint))
+            }
+            LeaseGrant::Recorded => {
+                // another worker checkpointed the position since it was read above
+                if let Some(output) = recorded_checkpoint_output(client, &branch, &data).await? {
+                    return Ok(output);
+                }
+                // or the position holds the timer or the signal of another step
+                check_position_step(
+                    client,
+                    &data.namespace,
+                    &data.workflow_id,
+                    &branch,
+                    data.position,
+                    PositionStep::Task {
+                        idempotency_key: &data.idempotency_key,
+                        task_name: data.task_name.as_deref(),
+                    },
+                )
+                .await?;
+                Err(WorkflowError::Internal(
+                    "recorded step not found".to_string(),
+                ))
+            }
+        };
+    }
+    Err(WorkflowError::Internal("unexpected state".to_string()))
+}
+
+///
+/// Returns the recorded value of the position, or `None` while it has none and the task still has
+/// to run. Fails if the position was recorded by another step.
+async fn recorded_checkpoint_output(
+    client: &Client,
+    branch: &str,
+    data: &LeaseCheckpointInput,
+) -> Result<Option<LeaseCheckpointOutput>, WorkflowError> {
+    let checkpoint = get_checkpoint(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        branch,
+        data.position,
+    )
+    .await?;
+    let Some(checkpoint) = checkpoint else {
+        return Ok(None);
+    };
+    let is_task_name_changed = matches!(
+        (&checkpoint.task_name, &data.task_name),
+        (Some(recorded), Some(received)) if recorded != received
+    );
+    return_error_if_true(
+        checkpoint.idempotency_key != data.idempotency_key || is_task_name_changed,
+        WorkflowError::NonDeterministicCheckpoint(Box::new(CheckpointMismatch {
+            branch: branch.to_string(),
+            position: data.position,
+            recorded_idempotency_key: checkpoint.idempotency_key,
+            received_idempotency_key: data.idempotency_key.clone(),
+            recorded_task_name: checkpoint.task_name,
+            received_task_name: data.task_name.clone(),
+        })),
+    )?;
+    // a checkpoint without value only holds the generated idempotency key, so the task still has to run
+    Ok(checkpoint.value.map(|value| LeaseCheckpointOutput {
+        response: Some(LeaseCheckpointReturnType::CheckpointValue(value)),
+    }))
+}
+
+fn remaining_lease_timeout_output(
+    leased_checkpoint: LeasedCheckpointValue,
+) -> LeaseCheckpointOutput {
+    LeaseCheckpointOutput {
+        response: Some(LeaseCheckpointReturnType::RemainingLeaseTimeout {
+            // at least 1, so a lease expiring right now is still reported as held
+            remaining_lease_timeout: diff_lease_expiry_from_now(&leased_checkpoint).max(1),
+            fencing_token: leased_checkpoint.fencing_token,
+            worker_id: leased_checkpoint.worker_id,
+        }),
+    }
+}
+
+///
+/// Long-poll variant of `handle_lease_checkpoint`. While the position is leased by another worker,
+/// the request is parked until the lease is released, the checkpoint is written, the lease expires
+/// or `wait_timeout` milliseconds have passed, and then the lease is attempted again.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, branch = ?data.branch, position = data.position, fencing_token = data.fencing_token, wait_timeout))]
+pub async fn handle_wait_lease_checkpoint(
+    client: &Client,
+    events: &WorkflowEvents,
+    data: LeaseCheckpointInput,
+    wait_timeout: i64,
+) -> Result<LeaseCheckpointOutput, WorkflowError> {
+    let branch = encode_branch(&data.branch)?;
+    return_error_if_true(
+        wait_timeout < 0,
+        WorkflowError::InvalidArgument("invalid_wait_timeout"),
+    )?;
+    let wait_timeout = wait_timeout.min(MAX_LEASE_WAIT_TIMEOUT);
+    let deadline = Instant::now() + Duration::from_millis(wait_timeout as u64);
+
+    loop {
+        // subscribe before reading the lease state so no release in between is missed
+        let mut receiver = events.subscribe();
+        let output = handle_lease_checkpoint(client, data.clone()).await?;
+        let Some(LeaseCheckpointReturnType::RemainingLeaseTimeout {
+            remaining_lease_timeout,
+            ..
+        }) = output.response
+        else {
+            return Ok(output);
+        };
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(output);
+        }
+        let lease_expiry = now + Duration::from_millis(remaining_lease_timeout as u64);
+        wait_for_lease_event(
+            &mut receiver,
+            &data.namespace,
+            &data.workflow_id,
+            &branch,
+            data.position,
+            lease_expiry.min(deadline),
+        )
+        .await;
     }
-    Err(Box::new(std::io::Error::other("unexpected state")))
 }
 
+pub struct ReleaseCheckpointInput {
+    pub namespace: String,
+    pub workflow_id: String,
+    pub branch: Vec<i64>,
+    pub position: i64,
+    /// `None` for clients that predate fenced releases, which release any lease of the position.
+    pub fencing_token: Option<i64>,
+    pub worker_id: Option<String>,
+}
+
+pub struct ReleaseCheckpointOutput {}
+
+///
+/// Removes the lease of a position so another attempt can take it right away. Only the worker
+/// holding the lease can release it, so a stale worker retrying a release can't free the position
+/// while the current owner still runs its task. Releasing a position without a lease succeeds.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, branch = ?data.branch, position = data.position, fencing_token = data.fencing_token))]
 pub async fn release_checkpoint(
     client: &Client,
-    workflow_id: &str,
-    position: i64,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    remove_leased_checkpoint(client, workflow_id, position).await?;
-    Ok(())
+    data: ReleaseCheckpointInput,
+) -> Result<ReleaseCheckpointOutput, WorkflowError> {
+    let branch = encode_branch(&data.branch)?;
+    let is_removed = remove_leased_checkpoint(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &branch,
+        data.position,
+        data.fencing_token,
+        data.worker_id.as_deref(),
+    )
+    .await?;
+    if is_removed {
+        publish_lease_event(
+            client,
+            &data.namespace,
+            &data.workflow_id,
+            &branch,
+            data.position,
+        )
+        .await;
+        return Ok(ReleaseCheckpointOutput {});
+    }
+
+    // nothing was removed, either there is no lease or another worker holds it
+    if let Some(sent_fencing_token) = data.fencing_token {
+        let leased_checkpoint = get_leased_checkpoint(
+            client,
+            &data.namespace,
+            &data.workflow_id,
+            &branch,
+            data.position,
+        )
+        .await?;
+        if let Some(leased_checkpoint) = leased_checkpoint {
+            return_error_if_true(
+                leased_checkpoint.is_held_by_other(sent_fencing_token, data.worker_id.as_deref()),
+                WorkflowError::StaleLeaseRelease {
+                    lease_fencing_token: leased_checkpoint.fencing_token,
+                    lease_worker_id: leased_checkpoint.worker_id,
+                    sent_fencing_token,
+                },
+            )?;
+        }
+    }
+    Ok(ReleaseCheckpointOutput {})
+}
+
+pub struct RenewLeaseInput {
+    pub namespace: String,
+    pub workflow_id: String,
+    pub branch: Vec<i64>,
+    pub fencing_token: i64,
+    pub position: i64,
+    pub lease_timeout: i64,
+}
+
+pub struct RenewLeaseOutput {
+    pub lease_expire_at: i64,
+}
+
+///
+/// Extends an active lease so long running tasks can keep it without guessing a huge timeout up front.
+/// The new lease timeout is counted from the time of renewal.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, branch = ?data.branch, position = data.position, fencing_token = data.fencing_token))]
+pub async fn handle_renew_lease(
+    client: &Client,
+    data: RenewLeaseInput,
+) -> Result<RenewLeaseOutput, WorkflowError> {
+    let branch = encode_branch(&data.branch)?;
+    return_error_if_true(
+        data.lease_timeout <= 0,
+        WorkflowError::InvalidArgument("invalid_lease_timeout"),
+    )?;
+
+    let found_fencing_token =
+        get_workflow_fencing_token(client, &data.namespace, &data.workflow_id).await?;
+
+    return_error_if_true(
+        found_fencing_token.is_none(),
+        WorkflowError::FencingTokenNotFound,
+    )?;
+    let stored_fencing_token = found_fencing_token.unwrap();
+    // only the worker holding the latest fencing token can own the lease
+    return_error_if_true(
+        stored_fencing_token != data.fencing_token,
+        WorkflowError::FencingTokenExpired {
+            current_fencing_token: stored_fencing_token,
+            sent_fencing_token: data.fencing_token,
+        },
+    )?;
+
+    let renewed = renew_leased_checkpoint(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &branch,
+        data.position,
+        data.lease_timeout,
+        data.fencing_token,
+    )
+    .await?;
+    let Some(renewed) = renewed else {
+        // the renewal only applies to an active lease of the sender, find out which part failed
+        let leased_checkpoint = get_leased_checkpoint(
+            client,
+            &data.namespace,
+            &data.workflow_id,
+            &branch,
+            data.position,
+        )
+        .await?;
+        let Some(leased_checkpoint) = leased_checkpoint else {
+            return Err(WorkflowError::LeaseNotFound);
+        };
+        // an expired lease may already be taken over by another worker, so it can not be renewed
+        return_error_if_true(
+            diff_lease_expiry_from_now(&leased_checkpoint) <= 0,
+            WorkflowError::LeaseExpired,
+        )?;
+        return Err(WorkflowError::LeaseHeld {
+            lease_fencing_token: leased_checkpoint.fencing_token,
+            lease_worker_id: leased_checkpoint.worker_id,
+        });
+    };
+
+    Ok(RenewLeaseOutput {
+        lease_expire_at: renewed.created_at.saturating_add(renewed.lease_timeout),
+    })
+}
+
+pub struct WorkflowHistoryInput {
+    pub namespace: String,
+    pub workflow_id: String,
+    pub include_values: bool,
+}
+
+pub struct ActiveLease {
+    pub branch: Vec<i64>,
+    pub position: i64,
+    pub lease_timeout: i64,
+    pub created_at: i64,
+    pub remaining_lease_timeout: i64,
+    pub fencing_token: i64,
+    pub worker_id: Option<String>,
+}
+
+pub struct WorkflowHistoryOutput {
+    pub checkpoints: Vec<CheckpointHistoryEntry>,
+    pub active_leases: Vec<ActiveLease>,
+}
+
+///
+/// Returns every checkpoint of a workflow together with its currently active leases, both in path
+/// order, so the steps of the branches forked at a position follow that position.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id))]
+pub async fn get_workflow_history(
+    client: &Client,
+    data: WorkflowHistoryInput,
+) -> Result<WorkflowHistoryOutput, WorkflowError> {
+    let workflow = get_workflow(client, &data.namespace, &data.workflow_id).await?;
+    return_error_if_true(workflow.is_none(), WorkflowError::WorkflowNotFound)?;
+
+    let (checkpoints, leased_checkpoints) = tokio::join!(
+        get_checkpoints(
+            client,
+            &data.namespace,
+            &data.workflow_id,
+            data.include_values
+        ),
+        get_leased_checkpoints(client, &data.namespace, &data.workflow_id),
+    );
+    let mut checkpoints = checkpoints?;
+    checkpoints
+        .sort_by_cached_key(|checkpoint| position_path(&checkpoint.branch, checkpoint.position));
+    let mut leased_checkpoints = leased_checkpoints?;
+    leased_checkpoints.sort_by_cached_key(|(branch, position, _)| position_path(branch, *position));
+    let active_leases = leased_checkpoints
+        .into_iter()
+        .filter_map(|(branch, position, leased_checkpoint)| {
+            let remaining_lease_timeout = diff_lease_expiry_from_now(&leased_checkpoint);
+            (remaining_lease_timeout > 0).then_some(ActiveLease {
+                branch: decode_branch(&branch),
+                position,
+                lease_timeout: leased_checkpoint.lease_timeout,
+                created_at: leased_checkpoint.created_at,
+                remaining_lease_timeout,
+                fencing_token: leased_checkpoint.fencing_token,
+                worker_id: leased_checkpoint.worker_id,
+            })
+        })
+        .collect();
+
+    Ok(WorkflowHistoryOutput {
+        checkpoints,
+        active_leases,
+    })
 }
 
 pub struct CreateDurableIdempotencyKeyInput {
+    pub namespace: String,
     pub workflow_id: String,
+    pub branch: Vec<i64>,
     pub fencing_token: i64,
     pub position: i64,
+    pub task_name: Option<String>,
 }
 
 pub struct CreateDurableIdempotencyKeyOutput {
     pub idempotency_key: String,
 }
 
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, branch = ?data.branch, position = data.position, fencing_token = data.fencing_token))]
 pub async fn create_durable_idempotency_key(
     client: &Client,
     data: CreateDurableIdempotencyKeyInput,
-) -> Result<CreateDurableIdempotencyKeyOutput, Box<dyn Error + Send + Sync>> {
-    let lock_key = format!("{}:{}", data.workflow_id, data.position);
-    let _ = client.lock(lock_key).await?;
-
-    let result = get_checkpoint(client, &data.workflow_id, data.position).await?;
+) -> Result<CreateDurableIdempotencyKeyOutput, WorkflowError> {
+    let branch = encode_branch(&data.branch)?;
+    let result = get_checkpoint(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &branch,
+        data.position,
+    )
+    .await?;
 
     // if checkpoint is already leased, then we need to return the value
     if let Some(checkpoint) = result {
@@ -224,33 +646,32 @@ pub async fn create_durable_idempotency_key(
     }
 
     let sent_fencing_token = data.fencing_token;
-    let workflow_fencing_token = get_workflow_fencing_token(client, &data.workflow_id).await?;
+    let workflow_fencing_token =
+        get_workflow_fencing_token(client, &data.namespace, &data.workflow_id).await?;
 
     return_error_if_true(
         workflow_fencing_token.is_none(),
-        Box::new(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            "fencing_token_not_found",
-        )),
+        WorkflowError::FencingTokenNotFound,
     )?;
+    let stored_fencing_token = workflow_fencing_token.unwrap();
     return_error_if_true(
-        workflow_fencing_token.unwrap() > sent_fencing_token,
-        Box::new(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            "fencing_token_expired",
-        )),
+        stored_fencing_token > sent_fencing_token,
+        WorkflowError::FencingTokenExpired {
+            current_fencing_token: stored_fencing_token,
+            sent_fencing_token,
+        },
     )?;
-    let idempotency_key = Uuid::new_v4().to_string();
-    create_checkpoint(
+    // concurrent requests can all miss the checkpoint above, the write keeps the first key
+    let idempotency_key = reserve_idempotency_key(
         client,
+        &data.namespace,
         &data.workflow_id,
-        None,
+        &branch,
         data.position,
-        idempotency_key.clone(),
+        Uuid::new_v4().to_string(),
+        data.task_name,
     )
     .await?;
 
-    Ok(CreateDurableIdempotencyKeyOutput {
-        idempotency_key: idempotency_key,
-    })
+    Ok(CreateDurableIdempotencyKeyOutput { idempotency_key })
 }
@@ -0,0 +1,285 @@
+use hiqlite::Client;
+use prost::Message;
+use tokio::time::{Duration, Instant};
+use tracing::instrument;
+
+use crate::events::lease_events::{publish_lease_event, wait_for_lease_event};
+use crate::events::workflow_events::WorkflowEvents;
+use crate::helpers::branch::{encode_branch, position_path};
+use crate::helpers::common::return_error_if_true;
+use crate::helpers::errors::{CheckpointMismatch, WorkflowError};
+use crate::repositories::checkpoints::{create_checkpoint, get_checkpoint};
+use crate::repositories::workflow_payloads::get_workflow_payload;
+use crate::repositories::workflows::{get_child_workflow, get_child_workflows, get_workflow};
+use crate::schema::checkpoint::{ChildOutcomeValue, NewCheckpoint};
+use crate::schema::workflow::{Workflow, WorkflowStatus};
+use crate::services::workflow_service::check_workflow_running;
+
+/// Longest time an `await_child_workflow` waits for the child, like the lease wait.
+const MAX_CHILD_WAIT_TIMEOUT: i64 = 60_000;
+
+/// How a child workflow finished.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChildWorkflowOutcome {
+    /// With the result the child was completed with.
+    Completed(Option<Vec<u8>>),
+    Failed,
+    Cancelled,
+    TimedOut,
+    Compensated,
+}
+
+impl ChildWorkflowOutcome {
+    /// `None` for a status that isn't terminal.
+    fn from_status(status: WorkflowStatus, result: Option<Vec<u8>>) -> Option<Self> {
+        match status {
+            WorkflowStatus::Completed => Some(ChildWorkflowOutcome::Completed(result)),
+            WorkflowStatus::Failed => Some(ChildWorkflowOutcome::Failed),
+            WorkflowStatus::Cancelled => Some(ChildWorkflowOutcome::Cancelled),
+            WorkflowStatus::TimedOut => Some(ChildWorkflowOutcome::TimedOut),
+            WorkflowStatus::Compensated => Some(ChildWorkflowOutcome::Compensated),
+            WorkflowStatus::Running | WorkflowStatus::Paused | WorkflowStatus::Compensating => None,
+        }
+    }
+
+    pub fn status(&self) -> WorkflowStatus {
+        match self {
+            ChildWorkflowOutcome::Completed(_) => WorkflowStatus::Completed,
+            ChildWorkflowOutcome::Failed => WorkflowStatus::Failed,
+            ChildWorkflowOutcome::Cancelled => WorkflowStatus::Cancelled,
+            ChildWorkflowOutcome::TimedOut => WorkflowStatus::TimedOut,
+            ChildWorkflowOutcome::Compensated => WorkflowStatus::Compensated,
+        }
+    }
+
+    pub fn result(&self) -> Option<&[u8]> {
+        match self {
+            ChildWorkflowOutcome::Completed(result) => result.as_deref(),
+            _ => None,
+        }
+    }
+
+    fn decode(value: &[u8]) -> Result<Self, WorkflowError> {
+        let value =
+            ChildOutcomeValue::decode(value).map_err(|e| WorkflowError::Internal(e.to_string()))?;
+        WorkflowStatus::from_i64(value.status)
+            .and_then(|status| ChildWorkflowOutcome::from_status(status, value.result))
+            .ok_or_else(|| WorkflowError::Internal("invalid child workflow outcome".to_string()))
+    }
+}
+
+///
+/// Checkpoints the outcome of a finished child workflow at its position of the parent and wakes
+/// up the parent waiting for it. Does nothing for workflows without a parent or still running.
+#[instrument(skip(client))]
+pub(crate) async fn propagate_child_outcome(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+) -> Result<Option<ChildWorkflowOutcome>, WorkflowError> {
+    let Some(child) = get_workflow(client, namespace, workflow_id).await? else {
+        return Ok(None);
+    };
+    record_child_outcome(client, &child).await
+}
+
+async fn record_child_outcome(
+    client: &Client,
+    child: &Workflow,
+) -> Result<Option<ChildWorkflowOutcome>, WorkflowError> {
+    let (Some(parent_workflow_id), Some(parent_branch), Some(parent_position)) = (
+        &child.parent_workflow_id,
+        &child.parent_branch,
+        child.parent_position,
+    ) else {
+        return Ok(None);
+    };
+    let Some(status) = WorkflowStatus::from_i64(child.status).filter(|s| s.is_terminal()) else {
+        return Ok(None);
+    };
+    let result = if status == WorkflowStatus::Completed {
+        get_workflow_payload(client, &child.namespace, &child.id)
+            .await?
+            .and_then(|payload| payload.result)
+    } else {
+        None
+    };
+    let value = ChildOutcomeValue {
+        workflow_id: child.id.clone(),
+        status: child.status,
+        result: result.clone(),
+    };
+    // the first recorded outcome is kept, so a replay of the parent sees the same one
+    create_checkpoint(
+        client,
+        &child.namespace,
+        parent_workflow_id,
+        parent_branch,
+        parent_position,
+        NewCheckpoint {
+            idempotency_key: child.id.clone(),
+            value: value.encode_to_vec(),
+            task_name: None,
+        },
+    )
+    .await?;
+    publish_lease_event(
+        client,
+        &child.namespace,
+        parent_workflow_id,
+        parent_branch,
+        parent_position,
+    )
+    .await;
+    Ok(ChildWorkflowOutcome::from_status(status, result))
+}
+
+pub struct ListChildWorkflowsInput {
+    pub namespace: String,
+    pub workflow_id: String,
+}
+
+pub struct ListChildWorkflowsOutput {
+    pub children: Vec<Workflow>,
+}
+
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id))]
+pub async fn list_child_workflows(
+    client: &Client,
+    data: ListChildWorkflowsInput,
+) -> Result<ListChildWorkflowsOutput, WorkflowError> {
+    let (workflow, children) = tokio::join!(
+        get_workflow(client, &data.namespace, &data.workflow_id),
+        get_child_workflows(client, &data.namespace, &data.workflow_id),
+    );
+    return_error_if_true(workflow?.is_none(), WorkflowError::WorkflowNotFound)?;
+    let mut children = children?;
+    children.sort_by_cached_key(|child| {
+        position_path(
+            child.parent_branch.as_deref().unwrap_or_default(),
+            child.parent_position.unwrap_or_default(),
+        )
+    });
+    Ok(ListChildWorkflowsOutput { children })
+}
+
+pub struct AwaitChildWorkflowInput {
+    pub namespace: String,
+    pub workflow_id: String,
+    /// Path of the branch the position belongs to, empty for the top level of the workflow.
+    pub branch: Vec<i64>,
+    pub fencing_token: i64,
+    pub position: i64,
+    pub child_workflow_id: String,
+}
+
+pub struct AwaitChildWorkflowOutput {
+    /// `None` while the child is running.
+    pub outcome: Option<ChildWorkflowOutcome>,
+}
+
+///
+/// Returns the outcome of the child workflow started at the position of the parent. The outcome
+/// is checkpointed when the child finishes, and here as well in case that write was lost.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, branch = ?data.branch, position = data.position, fencing_token = data.fencing_token))]
+pub async fn await_child_workflow(
+    client: &Client,
+    data: &AwaitChildWorkflowInput,
+) -> Result<AwaitChildWorkflowOutput, WorkflowError> {
+    let branch = encode_branch(&data.branch)?;
+    let checkpoint = get_checkpoint(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &branch,
+        data.position,
+    )
+    .await?;
+    if let Some(checkpoint) = checkpoint {
+        return_error_if_true(
+            checkpoint.idempotency_key != data.child_workflow_id,
+            WorkflowError::NonDeterministicCheckpoint(Box::new(CheckpointMismatch {
+                branch: branch.clone(),
+                position: data.position,
+                recorded_idempotency_key: checkpoint.idempotency_key.clone(),
+                received_idempotency_key: data.child_workflow_id.clone(),
+                recorded_task_name: checkpoint.task_name.clone(),
+                received_task_name: None,
+            })),
+        )?;
+        if let Some(value) = checkpoint.value {
+            return Ok(AwaitChildWorkflowOutput {
+                outcome: Some(ChildWorkflowOutcome::decode(&value)?),
+            });
+        }
+    }
+    check_workflow_running(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        data.fencing_token,
+    )
+    .await?;
+
+    let child = get_child_workflow(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &branch,
+        data.position,
+    )
+    .await?;
+    return_error_if_true(child.is_none(), WorkflowError::WorkflowNotFound)?;
+    let child = child.unwrap();
+    return_error_if_true(
+        child.id != data.child_workflow_id,
+        WorkflowError::NonDeterministicCheckpoint(Box::new(CheckpointMismatch {
+            branch,
+            position: data.position,
+            recorded_idempotency_key: child.id.clone(),
+            received_idempotency_key: data.child_workflow_id.clone(),
+            recorded_task_name: None,
+            received_task_name: None,
+        })),
+    )?;
+    Ok(AwaitChildWorkflowOutput {
+        outcome: record_child_outcome(client, &child).await?,
+    })
+}
+
+///
+/// Long-poll variant of `await_child_workflow`. While the child is running, the request is parked
+/// until it finishes or `wait_timeout` milliseconds have passed.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, branch = ?data.branch, position = data.position, wait_timeout))]
+pub async fn wait_for_child_workflow(
+    client: &Client,
+    events: &WorkflowEvents,
+    data: AwaitChildWorkflowInput,
+    wait_timeout: i64,
+) -> Result<AwaitChildWorkflowOutput, WorkflowError> {
+    return_error_if_true(
+        wait_timeout < 0,
+        WorkflowError::InvalidArgument("invalid_wait_timeout"),
+    )?;
+    let branch = encode_branch(&data.branch)?;
+    let wait_timeout = wait_timeout.min(MAX_CHILD_WAIT_TIMEOUT);
+    let deadline = Instant::now() + Duration::from_millis(wait_timeout as u64);
+
+    loop {
+        // subscribe before reading the child so no outcome written in between is missed
+        let mut receiver = events.subscribe();
+        let output = await_child_workflow(client, &data).await?;
+        if output.outcome.is_some() || Instant::now() >= deadline {
+            return Ok(output);
+        }
+        wait_for_lease_event(
+            &mut receiver,
+            &data.namespace,
+            &data.workflow_id,
+            &branch,
+            data.position,
+            deadline,
+        )
+        .await;
+    }
+}
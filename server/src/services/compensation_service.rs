@@ -0,0 +1,352 @@
+use chrono::Utc;
+use hiqlite::Client;
+use tracing::instrument;
+
+use crate::helpers::branch::{encode_branch, position_path};
+use crate::helpers::common::return_error_if_true;
+use crate::helpers::errors::{CheckpointMismatch, WorkflowError};
+use crate::quotas::quota_tracker::QuotaTracker;
+use crate::repositories::checkpoints::get_checkpoint;
+use crate::repositories::compensations::{
+    complete_compensation, create_compensation, get_compensation, get_pending_compensations,
+    lease_pending_compensation,
+};
+use crate::repositories::workflows::{get_child_workflow, get_workflow, update_workflow_status};
+use crate::repositories::workflows_fencing_tokens::get_workflow_fencing_token;
+use crate::schema::compensation::Compensation;
+use crate::schema::leased_checkpoint::LeaseRequest;
+use crate::schema::workflow::WorkflowStatus;
+use crate::services::namespace_service::resolve_retention;
+use crate::services::quota_service::check_checkpoint_admission;
+use crate::services::workflow_service::{check_workflow_running, propagate_child_outcome_or_log};
+
+pub struct RegisterCompensationInput {
+    pub namespace: String,
+    pub workflow_id: String,
+    /// Path of the branch the position belongs to, empty for the top level of the workflow.
+    pub branch: Vec<i64>,
+    pub fencing_token: i64,
+    pub position: i64,
+    pub name: String,
+    pub payload: Vec<u8>,
+}
+
+pub struct RegisterCompensationOutput {}
+
+///
+/// Records how to undo the step at the position, e.g. which payment to refund. A replay that
+/// registers the compensation again keeps the payload of the first run. The step must have been
+/// recorded at the position, or the child workflow started there.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, branch = ?data.branch, position = data.position, fencing_token = data.fencing_token))]
+pub async fn register_compensation(
+    client: &Client,
+    quota_tracker: &QuotaTracker,
+    data: RegisterCompensationInput,
+) -> Result<RegisterCompensationOutput, WorkflowError> {
+    return_error_if_true(
+        data.position < 0,
+        WorkflowError::InvalidArgument("invalid_position"),
+    )?;
+    return_error_if_true(
+        data.name.is_empty(),
+        WorkflowError::InvalidArgument("invalid_compensation_name"),
+    )?;
+    let branch = encode_branch(&data.branch)?;
+    let (running, checkpoint) = tokio::join!(
+        check_workflow_running(
+            client,
+            &data.namespace,
+            &data.workflow_id,
+            data.fencing_token,
+        ),
+        get_checkpoint(
+            client,
+            &data.namespace,
+            &data.workflow_id,
+            &branch,
+            data.position
+        ),
+    );
+    running?;
+    // a started child only records its outcome at the position once it finishes
+    let is_recorded = match checkpoint? {
+        Some(checkpoint) if checkpoint.value.is_some() => true,
+        Some(_) => get_child_workflow(
+            client,
+            &data.namespace,
+            &data.workflow_id,
+            &branch,
+            data.position,
+        )
+        .await?
+        .is_some(),
+        None => false,
+    };
+    return_error_if_true(!is_recorded, WorkflowError::CheckpointNotFound)?;
+    let payload_bytes = data.payload.len() as i64;
+    check_checkpoint_admission(client, quota_tracker, &data.namespace, payload_bytes).await?;
+
+    let created = create_compensation(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &branch,
+        data.position,
+        &data.name,
+        data.payload,
+    )
+    .await?;
+    if created {
+        quota_tracker.add_stored_bytes(&data.namespace, payload_bytes);
+        return Ok(RegisterCompensationOutput {});
+    }
+    let compensation = get_compensation(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &branch,
+        data.position,
+    )
+    .await?;
+    let Some(compensation) = compensation else {
+        return Err(WorkflowError::Internal(
+            "recorded compensation not found".to_string(),
+        ));
+    };
+    return_error_if_true(
+        compensation.name != data.name,
+        WorkflowError::NonDeterministicCheckpoint(Box::new(CheckpointMismatch {
+            branch,
+            position: data.position,
+            recorded_idempotency_key: String::new(),
+            received_idempotency_key: String::new(),
+            recorded_task_name: Some(compensation.name),
+            received_task_name: Some(data.name),
+        })),
+    )?;
+    Ok(RegisterCompensationOutput {})
+}
+
+///
+/// Checks that the worker holding `fencing_token` runs the compensations of the workflow.
+/// Returns the status of the workflow.
+async fn check_compensating_workflow(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    fencing_token: i64,
+) -> Result<WorkflowStatus, WorkflowError> {
+    let (token, workflow) = tokio::join!(
+        get_workflow_fencing_token(client, namespace, workflow_id),
+        get_workflow(client, namespace, workflow_id),
+    );
+    let token = token?;
+    return_error_if_true(token.is_none(), WorkflowError::FencingTokenNotFound)?;
+    let stored_fencing_token = token.unwrap();
+    return_error_if_true(
+        stored_fencing_token > fencing_token,
+        WorkflowError::FencingTokenExpired {
+            current_fencing_token: stored_fencing_token,
+            sent_fencing_token: fencing_token,
+        },
+    )?;
+    let workflow = workflow?;
+    return_error_if_true(workflow.is_none(), WorkflowError::WorkflowNotFound)?;
+    let status = workflow.unwrap().status;
+    match WorkflowStatus::from_i64(status) {
+        Some(status @ (WorkflowStatus::Compensating | WorkflowStatus::Compensated)) => Ok(status),
+        _ => Err(WorkflowError::WorkflowNotCompensating { status }),
+    }
+}
+
+///
+/// The pending compensation of the last step, whose path is the highest, so a step is only undone
+/// once every later step and every branch forked after it was.
+fn next_compensation(pending: Vec<Compensation>) -> Option<Compensation> {
+    pending
+        .into_iter()
+        .max_by_key(|compensation| position_path(&compensation.branch, compensation.position))
+}
+
+///
+/// Moves the workflow to compensated once no compensation is pending. Returns whether it is compensated.
+async fn finish_compensation(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+) -> Result<bool, WorkflowError> {
+    if !get_pending_compensations(client, namespace, workflow_id)
+        .await?
+        .is_empty()
+    {
+        return Ok(false);
+    }
+    let expire_after = resolve_retention(client, namespace, None).await?;
+    let now = Utc::now().timestamp_millis();
+    // concurrent calls only move the workflow once
+    let updated = update_workflow_status(
+        client,
+        namespace,
+        workflow_id,
+        WorkflowStatus::Compensating,
+        WorkflowStatus::Compensated,
+        Some(now + expire_after),
+        Some(now),
+    )
+    .await?;
+    if updated {
+        propagate_child_outcome_or_log(client, namespace, workflow_id).await;
+    }
+    Ok(true)
+}
+
+pub struct LeaseCompensationInput {
+    pub namespace: String,
+    pub workflow_id: String,
+    pub fencing_token: i64,
+    pub lease_timeout: i64,
+    pub worker_id: Option<String>,
+}
+
+pub enum LeaseCompensationOutput {
+    /// The next compensation to run, leased by the caller.
+    Granted(Compensation),
+    /// The next compensation is leased by another worker.
+    Held {
+        remaining_lease_timeout: i64,
+        lease_fencing_token: Option<i64>,
+        lease_worker_id: Option<String>,
+    },
+    /// Every compensation ran, the workflow is compensated.
+    Done,
+}
+
+///
+/// Leases the pending compensation of the last step. Compensations run one at a time, and like a
+/// position lease, one is handed to another worker once its lease expired without it being completed.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, fencing_token = data.fencing_token))]
+pub async fn lease_compensation(
+    client: &Client,
+    data: LeaseCompensationInput,
+) -> Result<LeaseCompensationOutput, WorkflowError> {
+    return_error_if_true(
+        data.lease_timeout <= 0,
+        WorkflowError::InvalidArgument("invalid_lease_timeout"),
+    )?;
+    let status = check_compensating_workflow(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        data.fencing_token,
+    )
+    .await?;
+    if status == WorkflowStatus::Compensated {
+        return Ok(LeaseCompensationOutput::Done);
+    }
+
+    // no compensation is registered while the workflow is compensating, so the pending ones only
+    // shrink and the next one stays the next one until it ran
+    let pending = get_pending_compensations(client, &data.namespace, &data.workflow_id).await?;
+    let Some(next) = next_compensation(pending) else {
+        finish_compensation(client, &data.namespace, &data.workflow_id).await?;
+        return Ok(LeaseCompensationOutput::Done);
+    };
+    let leased = lease_pending_compensation(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &next.branch,
+        next.position,
+        LeaseRequest {
+            lease_timeout: data.lease_timeout,
+            fencing_token: data.fencing_token,
+            worker_id: data.worker_id,
+        },
+    )
+    .await?;
+    if let Some(compensation) = leased {
+        return Ok(LeaseCompensationOutput::Granted(compensation));
+    }
+    // another worker leased or completed it since it was read
+    let now = Utc::now().timestamp_millis();
+    Ok(LeaseCompensationOutput::Held {
+        remaining_lease_timeout: next
+            .lease_expire_at
+            .unwrap_or(now)
+            .saturating_sub(now)
+            .max(0),
+        lease_fencing_token: next.lease_fencing_token,
+        lease_worker_id: next.lease_worker_id,
+    })
+}
+
+pub struct CompleteCompensationInput {
+    pub namespace: String,
+    pub workflow_id: String,
+    /// Path of the branch the position belongs to, empty for the top level of the workflow.
+    pub branch: Vec<i64>,
+    pub fencing_token: i64,
+    pub position: i64,
+    pub worker_id: Option<String>,
+}
+
+pub struct CompleteCompensationOutput {
+    /// Set once the last compensation completed and the workflow is compensated.
+    pub done: bool,
+}
+
+///
+/// Records that the compensation at the position ran, on behalf of the worker holding its lease.
+/// A worker whose lease expired can still complete it as long as no other worker leased it since.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, branch = ?data.branch, position = data.position, fencing_token = data.fencing_token))]
+pub async fn handle_complete_compensation(
+    client: &Client,
+    data: CompleteCompensationInput,
+) -> Result<CompleteCompensationOutput, WorkflowError> {
+    let branch = encode_branch(&data.branch)?;
+    check_compensating_workflow(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        data.fencing_token,
+    )
+    .await?;
+    let completed = complete_compensation(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &branch,
+        data.position,
+        data.fencing_token,
+        data.worker_id.as_deref(),
+    )
+    .await?;
+    if !completed {
+        let compensation = get_compensation(
+            client,
+            &data.namespace,
+            &data.workflow_id,
+            &branch,
+            data.position,
+        )
+        .await?;
+        return_error_if_true(compensation.is_none(), WorkflowError::LeaseNotFound)?;
+        let compensation = compensation.unwrap();
+        // a retried completion
+        if compensation.compensated_at.is_none() {
+            return_error_if_true(
+                compensation.lease_fencing_token.is_none(),
+                WorkflowError::LeaseNotFound,
+            )?;
+            return_error_if_true(
+                compensation.is_held_by_other(data.fencing_token, data.worker_id.as_deref()),
+                WorkflowError::LeaseHeld {
+                    lease_fencing_token: compensation.lease_fencing_token.unwrap_or_default(),
+                    lease_worker_id: compensation.lease_worker_id,
+                },
+            )?;
+        }
+    }
+    let done = finish_compensation(client, &data.namespace, &data.workflow_id).await?;
+    Ok(CompleteCompensationOutput { done })
+}
@@ -1,2 +1,9 @@
 pub mod checkpoint_service;
+pub mod child_workflow_service;
+pub mod compensation_service;
+pub mod namespace_service;
+pub mod position_service;
+pub mod quota_service;
+pub mod signal_service;
+pub mod timer_service;
 pub mod workflow_service;
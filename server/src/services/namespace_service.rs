@@ -0,0 +1,187 @@
+use hiqlite::Client;
+use tracing::instrument;
+
+use crate::helpers::common::return_error_if_true;
+use crate::helpers::errors::WorkflowError;
+use crate::repositories::namespaces::{
+    create_namespace as insert_namespace, delete_empty_namespace, get_namespace as find_namespace,
+    get_namespaces, update_namespace_settings,
+};
+use crate::schema::namespace::{DEFAULT_NAMESPACE, Namespace, NamespaceQuotas};
+
+const MAX_NAMESPACE_LENGTH: usize = 63;
+
+///
+/// Namespaces are part of lease and lock keys, so they are restricted to characters that never
+/// appear as a separator there.
+fn is_valid_namespace_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAMESPACE_LENGTH
+        && name.chars().all(|c| {
+            c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_' || c == '.'
+        })
+}
+
+fn is_valid_quotas(quotas: &NamespaceQuotas) -> bool {
+    [
+        quotas.max_running_workflows,
+        quotas.max_checkpoints_per_second,
+        quotas.max_checkpoint_bytes,
+        quotas.max_stored_bytes,
+    ]
+    .into_iter()
+    .flatten()
+    .all(|limit| limit >= 0)
+}
+
+pub struct CreateNamespaceInput {
+    pub name: String,
+    pub default_retention: i64,
+    pub quotas: NamespaceQuotas,
+}
+
+pub struct CreateNamespaceOutput {
+    pub namespace: Namespace,
+}
+
+#[instrument(skip_all, fields(namespace = %data.name))]
+pub async fn create_namespace(
+    client: &Client,
+    data: CreateNamespaceInput,
+) -> Result<CreateNamespaceOutput, WorkflowError> {
+    return_error_if_true(
+        !is_valid_namespace_name(&data.name),
+        WorkflowError::InvalidArgument("invalid_namespace"),
+    )?;
+    return_error_if_true(
+        data.default_retention < 0,
+        WorkflowError::InvalidArgument("invalid_default_retention"),
+    )?;
+    return_error_if_true(
+        !is_valid_quotas(&data.quotas),
+        WorkflowError::InvalidArgument("invalid_quota"),
+    )?;
+    let namespace =
+        insert_namespace(client, &data.name, data.default_retention, data.quotas).await?;
+    return_error_if_true(namespace.is_none(), WorkflowError::NamespaceAlreadyExists)?;
+    Ok(CreateNamespaceOutput {
+        namespace: namespace.unwrap(),
+    })
+}
+
+pub struct GetNamespaceInput {
+    pub name: String,
+}
+
+pub struct GetNamespaceOutput {
+    pub namespace: Namespace,
+}
+
+#[instrument(skip_all, fields(namespace = %data.name))]
+pub async fn get_namespace(
+    client: &Client,
+    data: GetNamespaceInput,
+) -> Result<GetNamespaceOutput, WorkflowError> {
+    let namespace = find_namespace(client, &data.name).await?;
+    return_error_if_true(namespace.is_none(), WorkflowError::NamespaceNotFound)?;
+    Ok(GetNamespaceOutput {
+        namespace: namespace.unwrap(),
+    })
+}
+
+pub struct ListNamespacesOutput {
+    pub namespaces: Vec<Namespace>,
+}
+
+#[instrument(skip_all)]
+pub async fn list_namespaces(client: &Client) -> Result<ListNamespacesOutput, WorkflowError> {
+    Ok(ListNamespacesOutput {
+        namespaces: get_namespaces(client).await?,
+    })
+}
+
+pub struct UpdateNamespaceInput {
+    pub name: String,
+    /// `None` keeps the current value.
+    pub default_retention: Option<i64>,
+    /// `None` keeps the current quotas, otherwise they are replaced as a whole.
+    pub quotas: Option<NamespaceQuotas>,
+}
+
+pub struct UpdateNamespaceOutput {
+    pub namespace: Namespace,
+}
+
+///
+/// Changes the default retention and the quotas. Workflows that already finished keep their expiry
+/// and quotas only apply to new requests.
+#[instrument(skip_all, fields(namespace = %data.name))]
+pub async fn update_namespace(
+    client: &Client,
+    data: UpdateNamespaceInput,
+) -> Result<UpdateNamespaceOutput, WorkflowError> {
+    return_error_if_true(
+        data.default_retention
+            .is_some_and(|retention| retention < 0),
+        WorkflowError::InvalidArgument("invalid_default_retention"),
+    )?;
+    return_error_if_true(
+        data.quotas
+            .as_ref()
+            .is_some_and(|quotas| !is_valid_quotas(quotas)),
+        WorkflowError::InvalidArgument("invalid_quota"),
+    )?;
+    let current = get_namespace(
+        client,
+        GetNamespaceInput {
+            name: data.name.clone(),
+        },
+    )
+    .await?
+    .namespace;
+    let default_retention = data.default_retention.unwrap_or(current.default_retention);
+    let quotas = data.quotas.unwrap_or_else(|| current.quotas());
+    let updated = update_namespace_settings(client, &data.name, default_retention, quotas).await?;
+    return_error_if_true(!updated, WorkflowError::NamespaceNotFound)?;
+    let output = get_namespace(client, GetNamespaceInput { name: data.name }).await?;
+    Ok(UpdateNamespaceOutput {
+        namespace: output.namespace,
+    })
+}
+
+pub struct DeleteNamespaceInput {
+    pub name: String,
+}
+
+pub struct DeleteNamespaceOutput {}
+
+#[instrument(skip_all, fields(namespace = %data.name))]
+pub async fn delete_namespace(
+    client: &Client,
+    data: DeleteNamespaceInput,
+) -> Result<DeleteNamespaceOutput, WorkflowError> {
+    return_error_if_true(
+        data.name == DEFAULT_NAMESPACE,
+        WorkflowError::InvalidArgument("default_namespace_not_deletable"),
+    )?;
+    let namespace = find_namespace(client, &data.name).await?;
+    return_error_if_true(namespace.is_none(), WorkflowError::NamespaceNotFound)?;
+    let deleted = delete_empty_namespace(client, &data.name).await?;
+    return_error_if_true(!deleted, WorkflowError::NamespaceNotEmpty)?;
+    Ok(DeleteNamespaceOutput {})
+}
+
+///
+/// The retention of a finished workflow: the requested one or else the default of its namespace.
+pub async fn resolve_retention(
+    client: &Client,
+    namespace: &str,
+    expire_after: Option<i64>,
+) -> Result<i64, WorkflowError> {
+    if let Some(expire_after) = expire_after {
+        return Ok(expire_after);
+    }
+    let namespace = find_namespace(client, namespace).await?;
+    return_error_if_true(namespace.is_none(), WorkflowError::NamespaceNotFound)?;
+    Ok(namespace.unwrap().default_retention)
+}
@@ -0,0 +1,101 @@
+use hiqlite::Client;
+
+use crate::helpers::errors::{CheckpointMismatch, WorkflowError};
+use crate::repositories::positions::get_position_records;
+
+/// The step a worker is about to record at a position.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum PositionStep<'a> {
+    Task {
+        idempotency_key: &'a str,
+        task_name: Option<&'a str>,
+    },
+    Timer {
+        name: &'a str,
+    },
+    Signal {
+        name: &'a str,
+    },
+    ChildWorkflow {
+        workflow_id: &'a str,
+    },
+}
+
+impl PositionStep<'_> {
+    /// The idempotency key and task name reported for the step when it does not match the position.
+    fn received(self) -> (String, Option<String>) {
+        match self {
+            Self::Task {
+                idempotency_key,
+                task_name,
+            } => (idempotency_key.to_string(), task_name.map(str::to_string)),
+            Self::Timer { name } | Self::Signal { name } => (String::new(), Some(name.to_string())),
+            Self::ChildWorkflow { workflow_id } => (workflow_id.to_string(), None),
+        }
+    }
+}
+
+///
+/// Fails if the position holds the records of another kind of step. Timers record no checkpoint,
+/// so the checkpoint of the position alone does not tell that a replay now runs a step where the
+/// first run slept. Steps and signals check their own checkpoint, so a timer fails on any and a
+/// child on one of another child, a timer also on a compensation, which belongs to the
+/// checkpointed step at its position.
+pub(crate) async fn check_position_step(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    branch: &str,
+    position: i64,
+    step: PositionStep<'_>,
+) -> Result<(), WorkflowError> {
+    let records = get_position_records(client, namespace, workflow_id, branch, position).await?;
+    let recorded = match step {
+        PositionStep::Timer { .. } => records
+            .checkpoint_idempotency_key
+            .map(|idempotency_key| (idempotency_key, records.checkpoint_task_name))
+            .or_else(|| {
+                records
+                    .signal_id
+                    .map(|signal_id| (signal_id, records.signal_name))
+            })
+            .or_else(|| {
+                records
+                    .compensation_name
+                    .map(|name| (String::new(), Some(name)))
+            }),
+        PositionStep::Signal { .. } => records.timer_name.map(|name| (String::new(), Some(name))),
+        PositionStep::ChildWorkflow { workflow_id } => records
+            .checkpoint_idempotency_key
+            .filter(|idempotency_key| idempotency_key != workflow_id)
+            .map(|idempotency_key| (idempotency_key, records.checkpoint_task_name))
+            .or_else(|| records.timer_name.map(|name| (String::new(), Some(name))))
+            .or_else(|| {
+                records
+                    .signal_id
+                    .map(|signal_id| (signal_id, records.signal_name))
+            }),
+        PositionStep::Task { .. } => records
+            .timer_name
+            .map(|name| (String::new(), Some(name)))
+            .or_else(|| {
+                records
+                    .signal_id
+                    .map(|signal_id| (signal_id, records.signal_name))
+            }),
+    };
+    let Some((recorded_idempotency_key, recorded_task_name)) = recorded else {
+        return Ok(());
+    };
+    let (received_idempotency_key, received_task_name) = step.received();
+    Err(WorkflowError::NonDeterministicCheckpoint(Box::new(
+        CheckpointMismatch {
+            branch: branch.to_string(),
+            position,
+            recorded_idempotency_key,
+            received_idempotency_key,
+            recorded_task_name,
+            received_task_name,
+        },
+    )))
+}
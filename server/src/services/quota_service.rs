@@ -0,0 +1,146 @@
+use hiqlite::Client;
+use tracing::instrument;
+
+use crate::helpers::common::return_error_if_true;
+use crate::helpers::errors::WorkflowError;
+use crate::metrics::workflow_metrics::metrics;
+use crate::quotas::quota_tracker::QuotaTracker;
+use crate::repositories::checkpoints::get_stored_bytes;
+use crate::repositories::namespaces::get_namespace;
+use crate::repositories::workflows::{count_running_workflows, get_workflow};
+use crate::schema::namespace::NamespaceQuotas;
+
+fn check_quota(
+    namespace: &str,
+    quota: &'static str,
+    limit: i64,
+    exceeded: bool,
+) -> Result<(), WorkflowError> {
+    if exceeded {
+        metrics()
+            .quota_rejections_total
+            .with_label_values(&[namespace, quota])
+            .inc();
+    }
+    return_error_if_true(exceeded, WorkflowError::QuotaExceeded { quota, limit })
+}
+
+///
+/// Rejects starting a new workflow when the namespace already has `max_running_workflows`.
+/// Restarting an existing workflow is always admitted. The count is read before the insert,
+/// so concurrent starts can overshoot the limit slightly.
+#[instrument(skip(client, quotas))]
+pub async fn check_workflow_admission(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    quotas: &NamespaceQuotas,
+) -> Result<(), WorkflowError> {
+    let Some(max_running_workflows) = quotas.max_running_workflows else {
+        return Ok(());
+    };
+    if get_workflow(client, namespace, workflow_id)
+        .await?
+        .is_some()
+    {
+        return Ok(());
+    }
+    let running_workflows = count_running_workflows(client, namespace).await?;
+    check_quota(
+        namespace,
+        "max_running_workflows",
+        max_running_workflows,
+        running_workflows >= max_running_workflows,
+    )
+}
+
+async fn stored_bytes(
+    client: &Client,
+    quota_tracker: &QuotaTracker,
+    namespace: &str,
+) -> Result<i64, WorkflowError> {
+    if let Some(stored_bytes) = quota_tracker.cached_stored_bytes(namespace) {
+        return Ok(stored_bytes);
+    }
+    let stored_bytes = get_stored_bytes(client, namespace).await?;
+    quota_tracker.set_stored_bytes(namespace, stored_bytes);
+    Ok(stored_bytes)
+}
+
+///
+/// Checks the size, the stored bytes and the rate quotas before a checkpoint value is written.
+/// The rate is limited by every node on its own, see [`QuotaTracker`], so the cluster as a whole
+/// admits up to the number of nodes times `max_checkpoints_per_second`.
+#[instrument(skip(client, quota_tracker))]
+pub async fn check_checkpoint_admission(
+    client: &Client,
+    quota_tracker: &QuotaTracker,
+    namespace: &str,
+    value_bytes: i64,
+) -> Result<(), WorkflowError> {
+    let Some(quotas) = get_namespace(client, namespace)
+        .await?
+        .map(|namespace| namespace.quotas())
+    else {
+        return Ok(());
+    };
+    if let Some(max_checkpoint_bytes) = quotas.max_checkpoint_bytes {
+        check_quota(
+            namespace,
+            "max_checkpoint_bytes",
+            max_checkpoint_bytes,
+            value_bytes > max_checkpoint_bytes,
+        )?;
+    }
+    if let Some(max_stored_bytes) = quotas.max_stored_bytes {
+        let stored_bytes = stored_bytes(client, quota_tracker, namespace).await?;
+        check_quota(
+            namespace,
+            "max_stored_bytes",
+            max_stored_bytes,
+            stored_bytes + value_bytes > max_stored_bytes,
+        )?;
+    }
+    // the rate is checked last, so rejected checkpoints don't use up the rate
+    if let Some(max_checkpoints_per_second) = quotas.max_checkpoints_per_second {
+        check_quota(
+            namespace,
+            "max_checkpoints_per_second",
+            max_checkpoints_per_second,
+            !quota_tracker.try_acquire_checkpoint(namespace, max_checkpoints_per_second),
+        )?;
+    }
+    Ok(())
+}
+
+pub struct NamespaceUsageInput {
+    pub name: String,
+}
+
+pub struct NamespaceUsageOutput {
+    pub quotas: NamespaceQuotas,
+    pub running_workflows: i64,
+    pub stored_bytes: i64,
+    /// Counted by the node answering the request only.
+    pub checkpoints_last_second: i64,
+}
+
+#[instrument(skip_all, fields(namespace = %data.name))]
+pub async fn get_namespace_usage(
+    client: &Client,
+    quota_tracker: &QuotaTracker,
+    data: NamespaceUsageInput,
+) -> Result<NamespaceUsageOutput, WorkflowError> {
+    let namespace = get_namespace(client, &data.name).await?;
+    return_error_if_true(namespace.is_none(), WorkflowError::NamespaceNotFound)?;
+    let (running_workflows, stored_bytes) = tokio::join!(
+        count_running_workflows(client, &data.name),
+        get_stored_bytes(client, &data.name),
+    );
+    Ok(NamespaceUsageOutput {
+        quotas: namespace.unwrap().quotas(),
+        running_workflows: running_workflows?,
+        stored_bytes: stored_bytes?,
+        checkpoints_last_second: quota_tracker.checkpoints_last_second(&data.name),
+    })
+}
@@ -0,0 +1,252 @@
+use hiqlite::Client;
+use tokio::time::{Duration, Instant};
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::events::signal_events::{publish_signal_event, wait_for_signal_event};
+use crate::events::workflow_events::WorkflowEvents;
+use crate::helpers::branch::encode_branch;
+use crate::helpers::common::return_error_if_true;
+use crate::helpers::errors::{CheckpointMismatch, WorkflowError};
+use crate::quotas::quota_tracker::QuotaTracker;
+use crate::repositories::checkpoints::{create_checkpoint, get_checkpoint};
+use crate::repositories::signals::{create_signal, deliver_signal, get_delivered_signal};
+use crate::repositories::workflows::get_workflow;
+use crate::schema::checkpoint::NewCheckpoint;
+use crate::schema::workflow::WorkflowStatus;
+use crate::services::position_service::{PositionStep, check_position_step};
+use crate::services::quota_service::check_checkpoint_admission;
+use crate::services::workflow_service::check_workflow_running;
+
+/// Longest time an `await_signal` waits for the signal, like the lease wait.
+const MAX_SIGNAL_WAIT_TIMEOUT: i64 = 60_000;
+
+pub struct SignalWorkflowInput {
+    pub namespace: String,
+    pub workflow_id: String,
+    pub name: String,
+    pub payload: Vec<u8>,
+    pub signal_id: Option<String>,
+}
+
+pub struct SignalWorkflowOutput {
+    pub signal_id: String,
+    /// Set if a signal with the same id was sent before and this one was dropped.
+    pub duplicate: bool,
+}
+
+///
+/// Stores a signal for the workflow, which the next `await_signal` of its name receives.
+/// Sending it again with the same `signal_id`, e.g. when a webhook is retried, stores it once.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, name = %data.name))]
+pub async fn signal_workflow(
+    client: &Client,
+    quota_tracker: &QuotaTracker,
+    data: SignalWorkflowInput,
+) -> Result<SignalWorkflowOutput, WorkflowError> {
+    return_error_if_true(
+        data.name.is_empty(),
+        WorkflowError::InvalidArgument("invalid_signal_name"),
+    )?;
+    return_error_if_true(
+        data.signal_id.as_deref().is_some_and(str::is_empty),
+        WorkflowError::InvalidArgument("invalid_signal_id"),
+    )?;
+    let workflow = get_workflow(client, &data.namespace, &data.workflow_id).await?;
+    return_error_if_true(workflow.is_none(), WorkflowError::WorkflowNotFound)?;
+    let status = workflow.unwrap().status;
+    return_error_if_true(
+        WorkflowStatus::from_i64(status).is_some_and(|status| status.is_terminal()),
+        WorkflowError::WorkflowTerminated { status },
+    )?;
+    // the payload becomes a checkpoint value once it is delivered
+    check_checkpoint_admission(
+        client,
+        quota_tracker,
+        &data.namespace,
+        data.payload.len() as i64,
+    )
+    .await?;
+
+    let signal_id = data.signal_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let created = create_signal(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &signal_id,
+        &data.name,
+        data.payload,
+    )
+    .await?;
+    if created {
+        publish_signal_event(client, &data.namespace, &data.workflow_id, &data.name).await;
+    }
+    Ok(SignalWorkflowOutput {
+        signal_id,
+        duplicate: !created,
+    })
+}
+
+pub struct AwaitSignalInput {
+    pub namespace: String,
+    pub workflow_id: String,
+    /// Path of the branch the position belongs to, empty for the top level of the workflow.
+    pub branch: Vec<i64>,
+    pub fencing_token: i64,
+    pub position: i64,
+    pub name: String,
+}
+
+pub struct AwaitSignalOutput {
+    /// The payload of the signal delivered to the position, `None` if no signal was sent yet.
+    pub payload: Option<Vec<u8>>,
+}
+
+///
+/// Delivers the oldest pending signal named `name` to the position and checkpoints its payload
+/// there, keyed by the signal id. A replay of the position returns the checkpointed payload,
+/// so every run of the workflow receives the same signal at the same position.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, branch = ?data.branch, position = data.position, fencing_token = data.fencing_token))]
+pub async fn await_signal(
+    client: &Client,
+    quota_tracker: &QuotaTracker,
+    data: &AwaitSignalInput,
+) -> Result<AwaitSignalOutput, WorkflowError> {
+    let branch = encode_branch(&data.branch)?;
+    let checkpoint = get_checkpoint(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &branch,
+        data.position,
+    )
+    .await?;
+    if let Some(checkpoint) = checkpoint {
+        return_error_if_true(
+            checkpoint.task_name.as_deref() != Some(data.name.as_str()),
+            WorkflowError::NonDeterministicCheckpoint(Box::new(CheckpointMismatch {
+                branch: branch.clone(),
+                position: data.position,
+                recorded_idempotency_key: checkpoint.idempotency_key.clone(),
+                received_idempotency_key: String::new(),
+                recorded_task_name: checkpoint.task_name.clone(),
+                received_task_name: Some(data.name.clone()),
+            })),
+        )?;
+        if let Some(payload) = checkpoint.value {
+            return Ok(AwaitSignalOutput {
+                payload: Some(payload),
+            });
+        }
+    }
+    check_workflow_running(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        data.fencing_token,
+    )
+    .await?;
+    check_position_step(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &branch,
+        data.position,
+        PositionStep::Signal { name: &data.name },
+    )
+    .await?;
+
+    let signal = match deliver_signal(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &data.name,
+        &branch,
+        data.position,
+    )
+    .await?
+    {
+        Some(signal) => Some(signal),
+        // another request delivered a signal to the position, or none is pending
+        None => {
+            get_delivered_signal(
+                client,
+                &data.namespace,
+                &data.workflow_id,
+                &branch,
+                data.position,
+            )
+            .await?
+        }
+    };
+    let Some(signal) = signal else {
+        return Ok(AwaitSignalOutput { payload: None });
+    };
+    return_error_if_true(
+        signal.name != data.name,
+        WorkflowError::NonDeterministicCheckpoint(Box::new(CheckpointMismatch {
+            branch: branch.clone(),
+            position: data.position,
+            recorded_idempotency_key: signal.signal_id.clone(),
+            received_idempotency_key: String::new(),
+            recorded_task_name: Some(signal.name.clone()),
+            received_task_name: Some(data.name.clone()),
+        })),
+    )?;
+    let written = create_checkpoint(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &branch,
+        data.position,
+        NewCheckpoint {
+            idempotency_key: signal.signal_id,
+            value: signal.payload.clone(),
+            task_name: Some(signal.name),
+        },
+    )
+    .await?;
+    // a replay receives the signal that is stored already
+    if written {
+        quota_tracker.add_stored_bytes(&data.namespace, signal.payload.len() as i64);
+    }
+    Ok(AwaitSignalOutput {
+        payload: Some(signal.payload),
+    })
+}
+
+///
+/// Long-poll variant of `await_signal`. While no signal is pending, the request is parked until
+/// one is sent or `wait_timeout` milliseconds have passed.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, branch = ?data.branch, position = data.position, wait_timeout))]
+pub async fn wait_for_signal(
+    client: &Client,
+    quota_tracker: &QuotaTracker,
+    events: &WorkflowEvents,
+    data: AwaitSignalInput,
+    wait_timeout: i64,
+) -> Result<AwaitSignalOutput, WorkflowError> {
+    return_error_if_true(
+        wait_timeout < 0,
+        WorkflowError::InvalidArgument("invalid_wait_timeout"),
+    )?;
+    let wait_timeout = wait_timeout.min(MAX_SIGNAL_WAIT_TIMEOUT);
+    let deadline = Instant::now() + Duration::from_millis(wait_timeout as u64);
+
+    loop {
+        // subscribe before reading the signals so no signal sent in between is missed
+        let mut receiver = events.subscribe();
+        let output = await_signal(client, quota_tracker, &data).await?;
+        if output.payload.is_some() || Instant::now() >= deadline {
+            return Ok(output);
+        }
+        wait_for_signal_event(
+            &mut receiver,
+            &data.namespace,
+            &data.workflow_id,
+            &data.name,
+            deadline,
+        )
+        .await;
+    }
+}
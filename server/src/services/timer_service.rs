@@ -0,0 +1,191 @@
+use chrono::Utc;
+use hiqlite::Client;
+use tokio::time::{Duration, Instant};
+use tracing::instrument;
+
+use crate::events::timer_events::{publish_timer_event, wait_for_timer_event};
+use crate::events::workflow_events::WorkflowEvents;
+use crate::helpers::branch::encode_branch;
+use crate::helpers::common::return_error_if_true;
+use crate::helpers::errors::{CheckpointMismatch, WorkflowError};
+use crate::repositories::timers::{
+    claim_due_timers, create_or_get_timer, get_next_timer_due_at, mark_timer_fired,
+};
+use crate::schema::timer::Timer;
+use crate::services::position_service::{PositionStep, check_position_step};
+use crate::services::workflow_service::check_workflow_running;
+
+const DEFAULT_POLL_LIMIT: i64 = 50;
+const MAX_POLL_LIMIT: i64 = 500;
+/// Longest time a poll waits for a timer to become due, like the lease wait.
+const MAX_TIMER_WAIT_TIMEOUT: i64 = 60_000;
+/// Timers are claimed for this long unless the poller sends a claim timeout.
+const DEFAULT_TIMER_CLAIM_TIMEOUT: i64 = 30_000;
+
+pub struct ScheduleTimerInput {
+    pub namespace: String,
+    pub workflow_id: String,
+    /// Path of the branch the position belongs to, empty for the top level of the workflow.
+    pub branch: Vec<i64>,
+    pub fencing_token: i64,
+    pub position: i64,
+    pub name: String,
+    pub delay: i64,
+}
+
+pub struct ScheduleTimerOutput {
+    pub fire_at: i64,
+    pub fired: bool,
+}
+
+///
+/// Records a timer firing `delay` milliseconds from now at the position, or returns the timer
+/// recorded there by an earlier run, so a replay waits for the fire time of the first run.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, branch = ?data.branch, position = data.position, fencing_token = data.fencing_token))]
+pub async fn schedule_timer(
+    client: &Client,
+    data: ScheduleTimerInput,
+) -> Result<ScheduleTimerOutput, WorkflowError> {
+    return_error_if_true(
+        data.delay < 0,
+        WorkflowError::InvalidArgument("invalid_timer_delay"),
+    )?;
+    let branch = encode_branch(&data.branch)?;
+    // another step recorded at the position means the workflow code changed the order of its steps
+    let (running, position_step) = tokio::join!(
+        check_workflow_running(
+            client,
+            &data.namespace,
+            &data.workflow_id,
+            data.fencing_token,
+        ),
+        check_position_step(
+            client,
+            &data.namespace,
+            &data.workflow_id,
+            &branch,
+            data.position,
+            PositionStep::Timer { name: &data.name },
+        ),
+    );
+    running?;
+    position_step?;
+
+    let now = Utc::now().timestamp_millis();
+    let fire_at = now.saturating_add(data.delay);
+    let timer = create_or_get_timer(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &branch,
+        data.position,
+        &data.name,
+        fire_at,
+    )
+    .await?;
+    return_error_if_true(
+        timer.name != data.name,
+        WorkflowError::NonDeterministicCheckpoint(Box::new(CheckpointMismatch {
+            branch: branch.clone(),
+            position: data.position,
+            recorded_idempotency_key: String::new(),
+            received_idempotency_key: String::new(),
+            recorded_task_name: Some(timer.name.clone()),
+            received_task_name: Some(data.name.clone()),
+        })),
+    )?;
+
+    let fired = timer.fired_at.is_some() || now >= timer.fire_at;
+    // pollers parked on a later timer have to pick up the new one
+    if !fired && timer.fire_at == fire_at {
+        publish_timer_event(client, &data.namespace).await;
+    }
+    if fired && timer.fired_at.is_none() {
+        mark_timer_fired(
+            client,
+            &data.namespace,
+            &data.workflow_id,
+            &branch,
+            data.position,
+            now,
+        )
+        .await?;
+    }
+    Ok(ScheduleTimerOutput {
+        fire_at: timer.fire_at,
+        fired,
+    })
+}
+
+pub struct PollDueTimersInput {
+    pub namespace: String,
+    pub limit: i64,
+    pub claim_timeout: Option<i64>,
+    pub wait_timeout: Option<i64>,
+}
+
+pub struct PollDueTimersOutput {
+    pub timers: Vec<Timer>,
+}
+
+///
+/// Claims the due timers of running workflows in the namespace, so the poller can resume their
+/// workflows. A claimed timer is returned to no other poller until `claim_timeout` milliseconds
+/// have passed, and it is due again then unless the resumed workflow passed it in the meantime.
+/// With `wait_timeout` the request is parked until a timer is due or the timeout has passed. A
+/// parked poll sleeps until the next due time and is woken by the timer events of the namespace,
+/// so it only reads while waiting and claims, which is a replicated write, once a timer is due.
+#[instrument(skip_all, fields(namespace = %data.namespace, limit = data.limit))]
+pub async fn poll_due_timers(
+    client: &Client,
+    events: &WorkflowEvents,
+    data: PollDueTimersInput,
+) -> Result<PollDueTimersOutput, WorkflowError> {
+    let limit = if data.limit <= 0 {
+        DEFAULT_POLL_LIMIT
+    } else {
+        data.limit.min(MAX_POLL_LIMIT)
+    };
+    let claim_timeout = data.claim_timeout.unwrap_or(DEFAULT_TIMER_CLAIM_TIMEOUT);
+    return_error_if_true(
+        claim_timeout <= 0,
+        WorkflowError::InvalidArgument("invalid_claim_timeout"),
+    )?;
+    let wait_timeout = data.wait_timeout.unwrap_or(0);
+    return_error_if_true(
+        wait_timeout < 0,
+        WorkflowError::InvalidArgument("invalid_wait_timeout"),
+    )?;
+    let deadline =
+        Instant::now() + Duration::from_millis(wait_timeout.min(MAX_TIMER_WAIT_TIMEOUT) as u64);
+
+    loop {
+        // subscribe before reading the timers so no timer scheduled in between is missed
+        let mut receiver = events.subscribe();
+        let next_due_at = get_next_timer_due_at(client, &data.namespace).await?;
+        let now = Utc::now().timestamp_millis();
+        if next_due_at.is_some_and(|due_at| due_at <= now) {
+            let timers = claim_due_timers(
+                client,
+                &data.namespace,
+                now,
+                now.saturating_add(claim_timeout),
+                limit,
+            )
+            .await?;
+            // otherwise other pollers claimed the due timers first
+            if !timers.is_empty() {
+                return Ok(PollDueTimersOutput { timers });
+            }
+            continue;
+        }
+        if Instant::now() >= deadline {
+            return Ok(PollDueTimersOutput { timers: Vec::new() });
+        }
+        let wake_at = next_due_at.map_or(deadline, |due_at| {
+            let wait = due_at.saturating_sub(Utc::now().timestamp_millis()).max(0);
+            deadline.min(Instant::now() + Duration::from_millis(wait as u64))
+        });
+        wait_for_timer_event(&mut receiver, &data.namespace, wake_at).await;
+    }
+}
@@ -1,104 +1,745 @@
 use chrono::Utc;
 use hiqlite::Client;
-use hiqlite_macros::params;
-use std::error::Error;
+use tracing::{error, instrument};
 
+use crate::events::timer_events::publish_timer_event;
+use crate::helpers::branch::encode_branch;
 use crate::helpers::common::return_error_if_true;
-use crate::repositories::checkpoints::delete_expired_checkpoints;
-use crate::repositories::workflows::{create_or_get_workflow, delete_expired_workflows};
+use crate::helpers::errors::{CheckpointMismatch, WorkflowError};
+use crate::helpers::pagination::{decode_cursor, encode_cursor};
+use crate::metrics::workflow_metrics::metrics;
+use crate::repositories::checkpoints::{delete_expired_checkpoints, reserve_idempotency_key};
+use crate::repositories::compensations::delete_expired_compensations;
+use crate::repositories::lease_checkpoint::delete_expired_leases;
+use crate::repositories::namespaces::get_namespace;
+use crate::repositories::signals::delete_expired_signals;
+use crate::repositories::timers::delete_expired_timers;
+use crate::repositories::workflow_payloads::{
+    delete_expired_workflow_payloads, get_workflow_payload, save_workflow_input,
+    save_workflow_result,
+};
+use crate::repositories::workflows::{
+    ListWorkflowsFilter, create_or_get_workflow, delete_expired_workflows, get_workflow,
+    get_workflows, record_workflow_heartbeat, time_out_overdue_workflows, update_workflow_status,
+    update_workflow_status_and_fencing_token,
+};
 use crate::repositories::workflows_fencing_tokens::{
     delete_expired_workflow_fencing_tokens, get_workflow_fencing_token,
     increment_workflow_fencing_token,
 };
-use crate::schema::workflow::WorkflowStatus;
+use crate::schema::workflow::{Workflow, WorkflowParent, WorkflowStatus};
+use crate::services::child_workflow_service::propagate_child_outcome;
+use crate::services::namespace_service::resolve_retention;
+use crate::services::position_service::{PositionStep, check_position_step};
+use crate::services::quota_service::check_workflow_admission;
 
 pub struct CreateWorkflowInput {
+    pub namespace: String,
     pub workflow_id: String,
     pub name: Option<String>,
+    pub input: Option<Vec<u8>>,
+    /// Milliseconds the workflow may run before it times out, counted from its first start.
+    pub execution_timeout: Option<i64>,
+    /// Set when the workflow is started as a child workflow.
+    pub parent: Option<ParentPositionInput>,
+}
+
+/// The position of the parent a child workflow is started at.
+pub struct ParentPositionInput {
+    pub workflow_id: String,
+    /// Path of the branch the position belongs to, empty for the top level of the parent.
+    pub branch: Vec<i64>,
+    pub position: i64,
+    /// Fencing token of the worker running the parent.
+    pub fencing_token: i64,
 }
 
 pub struct CreateWorkflowOutput {
+    /// 0 when the workflow is already completed.
     pub fencing_token: i64,
+    pub completed: bool,
+    /// The result the workflow was completed with.
+    pub result: Option<Vec<u8>>,
 }
 
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id))]
 pub async fn create_workflow(
     client: &Client,
     data: CreateWorkflowInput,
-) -> Result<CreateWorkflowOutput, Box<dyn Error + Send + Sync>> {
-    let (_, fencing_token) = tokio::join!(
-        create_or_get_workflow(
-            client,
-            &data.workflow_id,
-            WorkflowStatus::Running,
-            data.name
-        ),
-        increment_workflow_fencing_token(client, &data.workflow_id, 1),
-    );
+) -> Result<CreateWorkflowOutput, WorkflowError> {
+    return_error_if_true(
+        data.execution_timeout
+            .is_some_and(|execution_timeout| execution_timeout <= 0),
+        WorkflowError::InvalidArgument("invalid_execution_timeout"),
+    )?;
+    // workflows can only be started in namespaces that were created, so typos don't silently open a new one
+    let namespace = get_namespace(client, &data.namespace).await?;
+    return_error_if_true(namespace.is_none(), WorkflowError::NamespaceNotFound)?;
+    check_workflow_admission(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &namespace.unwrap().quotas(),
+    )
+    .await?;
+    let parent = match data.parent {
+        Some(parent) => {
+            let workflow_parent = WorkflowParent {
+                branch: encode_branch(&parent.branch)?,
+                workflow_id: parent.workflow_id,
+                position: parent.position,
+            };
+            check_parent_position(
+                client,
+                &data.namespace,
+                &data.workflow_id,
+                &workflow_parent,
+                parent.fencing_token,
+            )
+            .await?;
+            Some(workflow_parent)
+        }
+        None => None,
+    };
+    let workflow = create_or_get_workflow(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        WorkflowStatus::Running,
+        data.name,
+        data.execution_timeout
+            .map(|execution_timeout| Utc::now().timestamp_millis() + execution_timeout),
+        parent.clone(),
+    )
+    .await?;
+    // the fencing token belongs to the worker running the compensations now
+    return_error_if_true(
+        workflow.status == WorkflowStatus::Compensating as i64,
+        WorkflowError::WorkflowCompensating,
+    )?;
+    // failed, cancelled, timed out and compensated workflows stay finished
+    return_error_if_true(
+        workflow.status != WorkflowStatus::Running as i64
+            && workflow.status != WorkflowStatus::Paused as i64
+            && workflow.status != WorkflowStatus::Completed as i64,
+        WorkflowError::WorkflowTerminated {
+            status: workflow.status,
+        },
+    )?;
+    // only a start that goes through takes the position of the parent
+    if let Some(parent) = &parent {
+        reserve_parent_position(client, &data.namespace, &data.workflow_id, parent).await?;
+    }
+    // a completed workflow is not run again, its starter gets the result right away
+    if workflow.status == WorkflowStatus::Completed as i64 {
+        let payload = get_workflow_payload(client, &data.namespace, &data.workflow_id).await?;
+        return Ok(CreateWorkflowOutput {
+            fencing_token: 0,
+            completed: true,
+            result: payload.and_then(|payload| payload.result),
+        });
+    }
+
+    if let Some(input) = data.input {
+        save_workflow_input(client, &data.namespace, &data.workflow_id, input).await?;
+    }
+    let fencing_token =
+        increment_workflow_fencing_token(client, &data.namespace, &data.workflow_id, 1).await?;
 
     Ok(CreateWorkflowOutput {
-        fencing_token: fencing_token?,
+        fencing_token,
+        completed: false,
+        result: None,
+    })
+}
+
+///
+/// Checks that the worker holding `parent_fencing_token` still runs the parent and that the
+/// position of the parent is free for the child or already holds it, before anything is written.
+async fn check_parent_position(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    parent: &WorkflowParent,
+    parent_fencing_token: i64,
+) -> Result<(), WorkflowError> {
+    return_error_if_true(
+        parent.workflow_id == workflow_id || parent.position < 0,
+        WorkflowError::InvalidArgument("invalid_parent_workflow"),
+    )?;
+    check_workflow_running(client, namespace, &parent.workflow_id, parent_fencing_token).await?;
+    check_position_step(
+        client,
+        namespace,
+        &parent.workflow_id,
+        &parent.branch,
+        parent.position,
+        PositionStep::ChildWorkflow { workflow_id },
+    )
+    .await
+}
+
+///
+/// Reserves the position of the parent for the child, the same way a step reserves its position,
+/// so a replay of the parent that starts another child or runs a step there fails.
+async fn reserve_parent_position(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    parent: &WorkflowParent,
+) -> Result<(), WorkflowError> {
+    let recorded_child_workflow_id = reserve_idempotency_key(
+        client,
+        namespace,
+        &parent.workflow_id,
+        &parent.branch,
+        parent.position,
+        workflow_id.to_string(),
+        None,
+    )
+    .await?;
+    return_error_if_true(
+        recorded_child_workflow_id != workflow_id,
+        WorkflowError::NonDeterministicCheckpoint(Box::new(CheckpointMismatch {
+            branch: parent.branch.clone(),
+            position: parent.position,
+            recorded_idempotency_key: recorded_child_workflow_id.clone(),
+            received_idempotency_key: workflow_id.to_string(),
+            recorded_task_name: None,
+            received_task_name: None,
+        })),
+    )
+}
+
+///
+/// Logs instead of failing, since `await_child_workflow` records the outcome of a finished child
+/// as well if this write was lost.
+pub(crate) async fn propagate_child_outcome_or_log(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+) {
+    if let Err(e) = propagate_child_outcome(client, namespace, workflow_id).await {
+        error!(
+            "Error recording the outcome of workflow {} at its parent: {}",
+            workflow_id, e
+        );
+    }
+}
+
+///
+/// Checks that the worker holding `fencing_token` may still run the workflow, i.e. no other worker
+/// took it over and it is neither paused nor finished.
+pub(crate) async fn check_workflow_running(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    fencing_token: i64,
+) -> Result<(), WorkflowError> {
+    let (token, workflow) = tokio::join!(
+        get_workflow_fencing_token(client, namespace, workflow_id),
+        get_workflow(client, namespace, workflow_id),
+    );
+    let token = token?;
+    return_error_if_true(token.is_none(), WorkflowError::FencingTokenNotFound)?;
+    let stored_fencing_token = token.unwrap();
+    return_error_if_true(
+        stored_fencing_token > fencing_token,
+        WorkflowError::FencingTokenExpired {
+            current_fencing_token: stored_fencing_token,
+            sent_fencing_token: fencing_token,
+        },
+    )?;
+    let workflow = workflow?;
+    return_error_if_true(workflow.is_none(), WorkflowError::WorkflowNotFound)?;
+    let status = workflow.unwrap().status;
+    return_error_if_true(
+        status == WorkflowStatus::Paused as i64,
+        WorkflowError::WorkflowPaused,
+    )?;
+    return_error_if_true(
+        status == WorkflowStatus::Compensating as i64,
+        WorkflowError::WorkflowCompensating,
+    )?;
+    return_error_if_true(
+        status != WorkflowStatus::Running as i64,
+        WorkflowError::WorkflowTerminated { status },
+    )
+}
+
+pub struct HeartbeatWorkflowInput {
+    pub namespace: String,
+    pub workflow_id: String,
+    pub fencing_token: i64,
+}
+
+pub struct HeartbeatWorkflowOutput {
+    pub last_heartbeat_at: i64,
+}
+
+///
+/// Records that the worker holding the latest fencing token is still running the workflow.
+/// A superseded worker gets `fencing_token_expired` and a paused or finished workflow its status,
+/// so the worker knows to stop.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, fencing_token = data.fencing_token))]
+pub async fn heartbeat_workflow(
+    client: &Client,
+    data: HeartbeatWorkflowInput,
+) -> Result<HeartbeatWorkflowOutput, WorkflowError> {
+    let token = get_workflow_fencing_token(client, &data.namespace, &data.workflow_id).await?;
+    return_error_if_true(token.is_none(), WorkflowError::FencingTokenNotFound)?;
+    let stored_fencing_token = token.unwrap();
+    return_error_if_true(
+        stored_fencing_token > data.fencing_token,
+        WorkflowError::FencingTokenExpired {
+            current_fencing_token: stored_fencing_token,
+            sent_fencing_token: data.fencing_token,
+        },
+    )?;
+
+    let now = Utc::now().timestamp_millis();
+    let recorded =
+        record_workflow_heartbeat(client, &data.namespace, &data.workflow_id, now).await?;
+    if !recorded {
+        let workflow = get_workflow(client, &data.namespace, &data.workflow_id).await?;
+        return_error_if_true(workflow.is_none(), WorkflowError::WorkflowNotFound)?;
+        let status = workflow.unwrap().status;
+        return_error_if_true(
+            status == WorkflowStatus::Paused as i64,
+            WorkflowError::WorkflowPaused,
+        )?;
+        return_error_if_true(
+            status == WorkflowStatus::Compensating as i64,
+            WorkflowError::WorkflowCompensating,
+        )?;
+        return Err(WorkflowError::WorkflowTerminated { status });
+    }
+    Ok(HeartbeatWorkflowOutput {
+        last_heartbeat_at: now,
     })
 }
 
 pub struct FinishWorkflowInput {
+    pub namespace: String,
     pub workflow_id: String,
     pub fencing_token: i64,
-    pub expire_after: i64,
+    pub expire_after: Option<i64>,
+    pub result: Option<Vec<u8>>,
 }
 
 pub struct FinishWorkflowOutput {}
 
+///
+/// Completes a running workflow on behalf of the worker holding the latest fencing token.
+/// Paused, compensating and finished workflows are rejected with their status.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, fencing_token = data.fencing_token))]
 pub async fn finish_workflow(
     client: &Client,
     data: FinishWorkflowInput,
-) -> Result<FinishWorkflowOutput, Box<dyn Error + Send + Sync>> {
-    let token = get_workflow_fencing_token(client, &data.workflow_id).await?;
+) -> Result<FinishWorkflowOutput, WorkflowError> {
+    // a stale worker must not complete the workflow with its own result, and a finished
+    // workflow keeps its outcome and retention
+    check_workflow_running(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        data.fencing_token,
+    )
+    .await?;
+    let expire_after = resolve_retention(client, &data.namespace, data.expire_after).await?;
+
+    // written before the status, so a start seeing the workflow completed finds the result as well
+    if let Some(result) = data.result {
+        save_workflow_result(client, &data.namespace, &data.workflow_id, result).await?;
+    }
+
+    transition_workflow(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &[WorkflowStatus::Running],
+        WorkflowStatus::Completed,
+        Some(expire_after),
+        false,
+    )
+    .await?;
+    propagate_child_outcome_or_log(client, &data.namespace, &data.workflow_id).await;
+
+    Ok(FinishWorkflowOutput {})
+}
+
+///
+/// Moves the workflow to `to_status` if its current status is one of `from_statuses`.
+/// When `expire_after` is given, the workflow is considered finished and becomes subject to retention.
+/// With `bump_fencing_token`, the fencing token is bumped in the same write as the status, so the
+/// workers running the workflow get `abort` exactly when the status changed.
+async fn transition_workflow(
+    client: &Client,
+    namespace: &str,
+    workflow_id: &str,
+    from_statuses: &[WorkflowStatus],
+    to_status: WorkflowStatus,
+    expire_after: Option<i64>,
+    bump_fencing_token: bool,
+) -> Result<(), WorkflowError> {
+    let workflow = get_workflow(client, namespace, workflow_id).await?;
+    return_error_if_true(workflow.is_none(), WorkflowError::WorkflowNotFound)?;
+    let status = workflow.unwrap().status;
+    let current_status =
+        WorkflowStatus::from_i64(status).filter(|status| from_statuses.contains(status));
     return_error_if_true(
-        token.is_none(),
-        Box::new(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "fencing_token_not_found",
-        )),
+        current_status.is_none(),
+        WorkflowError::InvalidStatusTransition { status },
     )?;
+
+    let now = Utc::now().timestamp_millis();
+    let (expire_at, completed_at) = match expire_after {
+        Some(expire_after) => (Some(now + expire_after), Some(now)),
+        None => (None, None),
+    };
+    // the status is only updated if nobody changed it in the meantime
+    let from_status = current_status.unwrap();
+    let updated = if bump_fencing_token {
+        update_workflow_status_and_fencing_token(
+            client,
+            namespace,
+            workflow_id,
+            from_status,
+            to_status,
+            expire_at,
+            completed_at,
+        )
+        .await?
+    } else {
+        update_workflow_status(
+            client,
+            namespace,
+            workflow_id,
+            from_status,
+            to_status,
+            expire_at,
+            completed_at,
+        )
+        .await?
+    };
+    return_error_if_true(!updated, WorkflowError::InvalidStatusTransition { status })?;
+    Ok(())
+}
+
+pub struct CancelWorkflowInput {
+    pub namespace: String,
+    pub workflow_id: String,
+    pub expire_after: Option<i64>,
+}
+
+pub struct CancelWorkflowOutput {}
+
+///
+/// Cancels a running or paused workflow. The fencing token is bumped, so in-flight workers
+/// get `abort` on their next checkpoint.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id))]
+pub async fn cancel_workflow(
+    client: &Client,
+    data: CancelWorkflowInput,
+) -> Result<CancelWorkflowOutput, WorkflowError> {
+    let expire_after = resolve_retention(client, &data.namespace, data.expire_after).await?;
+    transition_workflow(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &[WorkflowStatus::Running, WorkflowStatus::Paused],
+        WorkflowStatus::Cancelled,
+        Some(expire_after),
+        true,
+    )
+    .await?;
+    propagate_child_outcome_or_log(client, &data.namespace, &data.workflow_id).await;
+    Ok(CancelWorkflowOutput {})
+}
+
+pub struct FailWorkflowInput {
+    pub namespace: String,
+    pub workflow_id: String,
+    pub fencing_token: i64,
+    pub expire_after: Option<i64>,
+}
+
+pub struct FailWorkflowOutput {}
+
+///
+/// Marks a workflow as failed on behalf of the worker holding the latest fencing token.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id, fencing_token = data.fencing_token))]
+pub async fn fail_workflow(
+    client: &Client,
+    data: FailWorkflowInput,
+) -> Result<FailWorkflowOutput, WorkflowError> {
+    let token = get_workflow_fencing_token(client, &data.namespace, &data.workflow_id).await?;
+    return_error_if_true(token.is_none(), WorkflowError::FencingTokenNotFound)?;
+    let stored_fencing_token = token.unwrap();
     return_error_if_true(
-        token.unwrap() < data.fencing_token,
-        Box::new(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            "fencing_token_expired",
-        )),
+        stored_fencing_token > data.fencing_token,
+        WorkflowError::FencingTokenExpired {
+            current_fencing_token: stored_fencing_token,
+            sent_fencing_token: data.fencing_token,
+        },
     )?;
-    let expire_at = Utc::now().timestamp_millis() + data.expire_after;
-
-    client
-        .execute(
-            "UPDATE Workflows SET expire_at = $1, status = $2, completed_at = $3  WHERE id = $4",
-            params![
-                expire_at,
-                WorkflowStatus::Completed as i64,
-                Utc::now().timestamp_millis(),
-                data.workflow_id
-            ],
-        )
-        .await?;
+    let expire_after = resolve_retention(client, &data.namespace, data.expire_after).await?;
+    transition_workflow(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &[WorkflowStatus::Running, WorkflowStatus::Paused],
+        WorkflowStatus::Failed,
+        Some(expire_after),
+        true,
+    )
+    .await?;
+    propagate_child_outcome_or_log(client, &data.namespace, &data.workflow_id).await;
+    Ok(FailWorkflowOutput {})
+}
 
-    Ok(FinishWorkflowOutput {})
+pub struct CompensateWorkflowInput {
+    pub namespace: String,
+    pub workflow_id: String,
+}
+
+pub struct CompensateWorkflowOutput {
+    /// The fencing token the compensations are leased with.
+    pub fencing_token: i64,
 }
 
-pub async fn handle_workflow_cleanup(client: &Client) -> Result<(), Box<dyn Error + Send + Sync>> {
+///
+/// Moves an unfinished, failed, cancelled or timed out workflow to compensating and bumps the
+/// fencing token, so workers still running its steps get `abort` and only the caller can lease
+/// the registered compensations. Compensating it again hands the compensations to a new worker.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id))]
+pub async fn compensate_workflow(
+    client: &Client,
+    data: CompensateWorkflowInput,
+) -> Result<CompensateWorkflowOutput, WorkflowError> {
+    transition_workflow(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &[
+            WorkflowStatus::Running,
+            WorkflowStatus::Paused,
+            WorkflowStatus::Failed,
+            WorkflowStatus::Cancelled,
+            WorkflowStatus::TimedOut,
+            WorkflowStatus::Compensating,
+        ],
+        WorkflowStatus::Compensating,
+        None,
+        false,
+    )
+    .await?;
+    let fencing_token =
+        increment_workflow_fencing_token(client, &data.namespace, &data.workflow_id, 1).await?;
+    Ok(CompensateWorkflowOutput { fencing_token })
+}
+
+pub struct PauseWorkflowInput {
+    pub namespace: String,
+    pub workflow_id: String,
+}
+
+pub struct PauseWorkflowOutput {}
+
+///
+/// Pauses a running workflow. In-flight workers get `abort` on their next checkpoint and
+/// no new leases are granted until the workflow is resumed.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id))]
+pub async fn pause_workflow(
+    client: &Client,
+    data: PauseWorkflowInput,
+) -> Result<PauseWorkflowOutput, WorkflowError> {
+    transition_workflow(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &[WorkflowStatus::Running],
+        WorkflowStatus::Paused,
+        None,
+        true,
+    )
+    .await?;
+    Ok(PauseWorkflowOutput {})
+}
+
+pub struct ResumeWorkflowInput {
+    pub namespace: String,
+    pub workflow_id: String,
+}
+
+pub struct ResumeWorkflowOutput {}
+
+///
+/// Resumes a paused workflow. Workers need to call `workflow_start` again to get a fresh fencing token.
+#[instrument(skip_all, fields(namespace = %data.namespace, workflow_id = %data.workflow_id))]
+pub async fn resume_workflow(
+    client: &Client,
+    data: ResumeWorkflowInput,
+) -> Result<ResumeWorkflowOutput, WorkflowError> {
+    transition_workflow(
+        client,
+        &data.namespace,
+        &data.workflow_id,
+        &[WorkflowStatus::Paused],
+        WorkflowStatus::Running,
+        None,
+        false,
+    )
+    .await?;
+    // the timers of a paused workflow are not polled, so some may be due already
+    publish_timer_event(client, &data.namespace).await;
+    Ok(ResumeWorkflowOutput {})
+}
+
+const DEFAULT_LIST_PAGE_SIZE: i64 = 50;
+const MAX_LIST_PAGE_SIZE: i64 = 500;
+
+pub struct ListWorkflowsInput {
+    pub namespace: String,
+    pub status: Option<i64>,
+    pub name: Option<String>,
+    pub created_after: Option<i64>,
+    pub created_before: Option<i64>,
+    pub completed_after: Option<i64>,
+    pub completed_before: Option<i64>,
+    pub last_heartbeat_before: Option<i64>,
+    pub page_size: i64,
+    pub page_cursor: Option<String>,
+}
+
+pub struct ListWorkflowsOutput {
+    pub workflows: Vec<Workflow>,
+    pub next_page_cursor: Option<String>,
+}
+
+///
+/// Lists workflows newest first. `next_page_cursor` is set when more workflows match the filter.
+#[instrument(skip_all, fields(namespace = %data.namespace))]
+pub async fn list_workflows(
+    client: &Client,
+    data: ListWorkflowsInput,
+) -> Result<ListWorkflowsOutput, WorkflowError> {
+    let after = match data.page_cursor {
+        Some(cursor) => {
+            let decoded = decode_cursor(&cursor);
+            return_error_if_true(
+                decoded.is_none(),
+                WorkflowError::InvalidArgument("invalid_page_cursor"),
+            )?;
+            decoded
+        }
+        None => None,
+    };
+    let page_size = if data.page_size <= 0 {
+        DEFAULT_LIST_PAGE_SIZE
+    } else {
+        data.page_size.min(MAX_LIST_PAGE_SIZE)
+    };
+
+    // one extra row tells whether there is a next page
+    let mut workflows = get_workflows(
+        client,
+        ListWorkflowsFilter {
+            namespace: data.namespace,
+            status: data.status,
+            name: data.name,
+            created_after: data.created_after,
+            created_before: data.created_before,
+            completed_after: data.completed_after,
+            completed_before: data.completed_before,
+            last_heartbeat_before: data.last_heartbeat_before,
+            after,
+        },
+        page_size + 1,
+    )
+    .await?;
+
+    let mut next_page_cursor = None;
+    if workflows.len() as i64 > page_size {
+        workflows.truncate(page_size as usize);
+        next_page_cursor = workflows
+            .last()
+            .map(|workflow| encode_cursor(workflow.created_at, &workflow.id));
+    }
+
+    Ok(ListWorkflowsOutput {
+        workflows,
+        next_page_cursor,
+    })
+}
+
+///
+/// Times out running workflows past their execution deadline. Their fencing token is bumped, so
+/// workers still running them get `abort` on their next checkpoint.
+#[instrument(skip_all)]
+pub async fn handle_workflow_timeouts(client: &Client) -> Result<(), WorkflowError> {
+    if !client.is_leader_db().await {
+        return Ok(());
+    }
+    let workflows = time_out_overdue_workflows(client, Utc::now().timestamp_millis()).await?;
+    for workflow in workflows {
+        increment_workflow_fencing_token(client, &workflow.namespace, &workflow.id, 1).await?;
+        metrics()
+            .workflow_timeouts_total
+            .with_label_values(&[&workflow.namespace])
+            .inc();
+        propagate_child_outcome_or_log(client, &workflow.namespace, &workflow.id).await;
+    }
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub async fn handle_workflow_cleanup(client: &Client) -> Result<(), WorkflowError> {
     if !client.is_leader_db().await {
         return Ok(());
     }
     println!("Deleting expired workflows");
 
     let current_timestamp = Utc::now().timestamp_millis();
-    let status = WorkflowStatus::Completed as i8;
-    let (fencing_tokens, checkpoints) = tokio::join!(
-        delete_expired_workflow_fencing_tokens(client, current_timestamp, status),
-        delete_expired_checkpoints(client, current_timestamp, status),
-    );
-    fencing_tokens?;
-    checkpoints?;
-    delete_expired_workflows(client, current_timestamp, status).await?;
+    for status in WorkflowStatus::TERMINAL {
+        let status = status as i8;
+        let (fencing_tokens, checkpoints, payloads, timers, signals, compensations) = tokio::join!(
+            delete_expired_workflow_fencing_tokens(client, current_timestamp, status),
+            delete_expired_checkpoints(client, current_timestamp, status),
+            delete_expired_workflow_payloads(client, current_timestamp, status),
+            delete_expired_timers(client, current_timestamp, status),
+            delete_expired_signals(client, current_timestamp, status),
+            delete_expired_compensations(client, current_timestamp, status),
+        );
+        let deleted_rows = &metrics().cleanup_deleted_rows_total;
+        deleted_rows
+            .with_label_values(&["workflow_fencing_tokens"])
+            .inc_by(fencing_tokens? as u64);
+        deleted_rows
+            .with_label_values(&["checkpoints"])
+            .inc_by(checkpoints? as u64);
+        deleted_rows
+            .with_label_values(&["workflow_payloads"])
+            .inc_by(payloads? as u64);
+        deleted_rows
+            .with_label_values(&["timers"])
+            .inc_by(timers? as u64);
+        deleted_rows
+            .with_label_values(&["signals"])
+            .inc_by(signals? as u64);
+        deleted_rows
+            .with_label_values(&["compensations"])
+            .inc_by(compensations? as u64);
+        let workflows = delete_expired_workflows(client, current_timestamp, status).await?;
+        deleted_rows
+            .with_label_values(&["workflows"])
+            .inc_by(workflows as u64);
+    }
+    let leases = delete_expired_leases(client, current_timestamp).await?;
+    metrics()
+        .cleanup_deleted_rows_total
+        .with_label_values(&["leases"])
+        .inc_by(leases as u64);
     println!("Deleted expired workflows");
     Ok(())
 }
@@ -0,0 +1,2 @@
+pub mod propagation;
+pub mod subscriber;
@@ -0,0 +1,57 @@
+use std::env::var;
+use std::error::Error;
+
+use opentelemetry::global;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing::Level;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::{Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+
+const SERVICE_NAME: &str = "idempotency-server";
+
+///
+/// Installs the global subscriber. The filter is read from `RUST_LOG` and defaults to `info`.
+/// When `OTEL_EXPORTER_OTLP_ENDPOINT` is set, spans are exported over OTLP/gRPC as well and
+/// the returned provider has to be shut down to flush them.
+pub fn init_tracing() -> Result<Option<SdkTracerProvider>, Box<dyn Error>> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let fmt = tracing_subscriber::fmt::layer()
+        .with_target(true)
+        .with_level(true);
+
+    if var("OTEL_EXPORTER_OTLP_ENDPOINT").is_err() {
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(fmt)
+            .try_init()?;
+        return Ok(None);
+    }
+
+    // the endpoint is read by the exporter itself, like the other OTEL_EXPORTER_OTLP_* variables
+    let exporter = SpanExporter::builder().with_tonic().build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+        .build();
+    global::set_tracer_provider(provider.clone());
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt)
+        .with(
+            tracing_opentelemetry::layer()
+                .with_tracer(provider.tracer(SERVICE_NAME))
+                // the background spans of hiqlite and openraft are not part of any request
+                .with_filter(Targets::new().with_target("idempotency_server", Level::TRACE)),
+        )
+        .try_init()?;
+    Ok(Some(provider))
+}
@@ -0,0 +1,74 @@
+use idempotency_client::{Credentials, WorkflowOptions};
+use idempotency_server::rpc_server::auth::{AuthConfig, Scope};
+
+use crate::common::TestEngine;
+
+#[test]
+fn api_keys_are_parsed_with_scopes_and_namespaces() {
+    let api_keys = AuthConfig::parse_api_keys(
+        "worker-key:write,ops-key:read+admin,orders-key:write@orders|billing",
+    )
+    .unwrap();
+    assert_eq!(api_keys.len(), 3);
+    assert_eq!(api_keys["worker-key"].scopes, vec![Scope::Write]);
+    assert_eq!(api_keys["worker-key"].namespaces, None);
+    assert_eq!(api_keys["ops-key"].scopes, vec![Scope::Read, Scope::Admin]);
+    assert_eq!(
+        api_keys["orders-key"].namespaces,
+        Some(vec!["orders".to_string(), "billing".to_string()])
+    );
+}
+
+#[test]
+fn malformed_api_keys_are_rejected_without_leaking_the_keys() {
+    for api_keys in [
+        "secret-key",
+        ":write",
+        "secret-key:owner",
+        "secret-key:write@",
+        "good-key:read,secret-key:",
+    ] {
+        let error = AuthConfig::parse_api_keys(api_keys).unwrap_err();
+        assert!(
+            !error.to_string().contains("secret-key"),
+            "{api_keys}: {error}"
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn calls_need_credentials_with_the_required_scope() {
+    let engine = TestEngine::start_with_auth(AuthConfig {
+        api_keys: AuthConfig::parse_api_keys("reader:read,worker:write").unwrap(),
+        jwt_secret: None,
+    })
+    .await;
+    let client = engine.connect().await;
+
+    let error = client
+        .start_workflow("guarded", WorkflowOptions::default())
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(error.reason(), Some("unauthenticated"));
+
+    let reader = client
+        .clone()
+        .with_credentials(Credentials::ApiKey("reader".to_string()));
+    let error = reader
+        .start_workflow("guarded", WorkflowOptions::default())
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(error.reason(), Some("permission_denied"));
+
+    let worker = client.with_credentials(Credentials::ApiKey("worker".to_string()));
+    worker
+        .start_workflow("guarded", WorkflowOptions::default())
+        .await
+        .unwrap();
+    // unwinding a workflow cancels it for its workers, like the other admin RPCs
+    let error = worker.compensate_workflow("guarded").await.err().unwrap();
+    assert_eq!(error.reason(), Some("permission_denied"));
+    engine.shutdown().await;
+}
@@ -0,0 +1,221 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use idempotency_client::proto::{GetWorkflowHistoryRequest, ListChildWorkflowsRequest};
+use idempotency_client::{ChildOutcome, ClientError, WorkflowOptions};
+use tonic_types::StatusExt;
+
+use crate::common::TestEngine;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn branches_checkpoint_at_their_own_paths_and_replay() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let runs = AtomicUsize::new(0);
+
+    for _ in 0..2 {
+        let mut workflow = client
+            .start_workflow("fan-out", WorkflowOptions::default())
+            .await
+            .unwrap();
+        let mut branches = workflow.branches(2);
+        assert_eq!(branches[1].path(), &[0, 1]);
+        let (first, second) = branches.split_at_mut(1);
+        let runs = &runs;
+        let (first, second) = tokio::join!(
+            first[0].step("work", || async {
+                runs.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, std::io::Error>(10i64)
+            }),
+            second[0].step("work", || async {
+                runs.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, std::io::Error>(11i64)
+            }),
+        );
+        assert_eq!((first.unwrap(), second.unwrap()), (10, 11));
+        assert_eq!(workflow.position(), 1);
+    }
+    // the replay returned the recorded values without running the steps again
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+    let history = client
+        .raw()
+        .get_workflow_history(GetWorkflowHistoryRequest {
+            workflow_id: "fan-out".to_string(),
+            include_values: false,
+            namespace: String::new(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    let paths: Vec<_> = history
+        .checkpoints
+        .iter()
+        .map(|entry| (entry.branch.clone(), entry.position))
+        .collect();
+    assert_eq!(paths, vec![(vec![0, 0], 0), (vec![0, 1], 0)]);
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn branches_receive_signals_and_start_children_at_their_own_positions() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let mut workflow = client
+        .start_workflow("approvals", WorkflowOptions::default())
+        .await
+        .unwrap();
+    client
+        .signal_workflow("approvals", "approved", &"first", None)
+        .await
+        .unwrap();
+    client
+        .signal_workflow("approvals", "approved", &"second", None)
+        .await
+        .unwrap();
+    let mut branches = workflow.branches(2);
+    let mut received = Vec::new();
+    for branch in &mut branches {
+        let signal: Option<String> = branch.await_signal("approved", None).await.unwrap();
+        received.push(signal.unwrap());
+    }
+    assert_eq!(received, ["first", "second"]);
+
+    // both branches start a child at their position 1
+    for (index, branch) in branches.iter_mut().enumerate() {
+        let child = branch
+            .start_child(format!("approval-{index}"), WorkflowOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(child.parent_position(), Some(1));
+        child.complete_with_result(&(index as i64)).await.unwrap();
+    }
+    for (index, branch) in branches.iter().enumerate() {
+        let outcome: Option<ChildOutcome<i64>> = branch
+            .await_child(
+                &format!("approval-{index}"),
+                1,
+                Some(Duration::from_secs(5)),
+            )
+            .await
+            .unwrap();
+        assert_eq!(outcome, Some(ChildOutcome::Completed(Some(index as i64))));
+    }
+    let children = client
+        .raw()
+        .list_child_workflows(ListChildWorkflowsRequest {
+            workflow_id: "approvals".to_string(),
+            namespace: String::new(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .children;
+    let parents: Vec<_> = children
+        .iter()
+        .map(|child| (child.parent_branch.clone(), child.parent_position))
+        .collect();
+    assert_eq!(parents, vec![(vec![0, 0], Some(1)), (vec![0, 1], Some(1))]);
+
+    // a replay receives the same signals at the same positions
+    let mut workflow = client
+        .start_workflow("approvals", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let mut branches = workflow.branches(2);
+    let signal: Option<String> = branches[1].await_signal("approved", None).await.unwrap();
+    assert_eq!(signal.as_deref(), Some("second"));
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn timer_of_a_branch_reports_its_path_when_replayed_differently() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let mut workflow = client
+        .start_workflow("sleepy", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let mut branches = workflow.branches(2);
+    assert!(
+        !branches[1]
+            .sleep("nap", Duration::from_secs(60))
+            .await
+            .unwrap()
+    );
+    // the other branch sleeps at the same position without a conflict
+    assert!(branches[0].sleep("rest", Duration::ZERO).await.unwrap());
+    let timers = client.poll_due_timers(10, None).await.unwrap();
+    assert_eq!(timers.len(), 0);
+
+    let error = branches[1]
+        .sleep("doze", Duration::from_secs(60))
+        .await
+        .unwrap_err();
+    assert_eq!(error.reason(), Some("non_deterministic_checkpoint_found"));
+    let ClientError::Rpc(status) = error else {
+        panic!("expected an rpc error");
+    };
+    let metadata = status.get_details_error_info().unwrap().metadata;
+    assert_eq!(metadata["branch"], "0.1");
+    assert_eq!(metadata["path"], "0.1.0");
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn compensations_of_branches_run_before_the_step_they_were_forked_at() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let mut workflow = client
+        .start_workflow("trip", WorkflowOptions::default())
+        .await
+        .unwrap();
+    workflow
+        .step("pay", || async { Ok::<_, std::io::Error>(()) })
+        .await
+        .unwrap();
+    workflow
+        .register_compensation("refund", &"pay")
+        .await
+        .unwrap();
+    let mut branches = workflow.branches(2);
+    for (index, branch) in branches.iter_mut().enumerate() {
+        branch
+            .step("book", || async { Ok::<_, std::io::Error>(()) })
+            .await
+            .unwrap();
+        branch
+            .register_compensation("cancel", &format!("book-{index}"))
+            .await
+            .unwrap();
+    }
+    workflow
+        .step("notify", || async { Ok::<_, std::io::Error>(()) })
+        .await
+        .unwrap();
+    workflow
+        .register_compensation("retract", &"notify")
+        .await
+        .unwrap();
+
+    let compensator = client.compensate_workflow("trip").await.unwrap();
+    let mut undone = Vec::new();
+    while let Some(compensation) = compensator.next(10_000).await.unwrap() {
+        let payload: String = compensator.payload(&compensation).unwrap();
+        undone.push((compensation.branch.clone(), payload));
+        compensator
+            .complete(&compensation.branch, compensation.position)
+            .await
+            .unwrap();
+    }
+    assert_eq!(
+        undone,
+        vec![
+            (vec![], "notify".to_string()),
+            (vec![1, 1], "book-1".to_string()),
+            (vec![1, 0], "book-0".to_string()),
+            (vec![], "pay".to_string()),
+        ]
+    );
+    engine.shutdown().await;
+}
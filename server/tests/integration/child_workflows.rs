@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use idempotency_client::proto::{ListChildWorkflowsRequest, WorkflowStartRequest};
+use idempotency_client::{ChildOutcome, WorkflowOptions};
+
+use tonic::Code;
+
+use crate::common::TestEngine;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn parent_receives_the_result_of_its_child() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let mut parent = client
+        .start_workflow("parent", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let child = parent
+        .start_child("child", WorkflowOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(child.parent_position(), Some(0));
+    assert_eq!(parent.position(), 1);
+
+    let pending: Option<ChildOutcome<i64>> = parent.await_child("child", 0, None).await.unwrap();
+    assert_eq!(pending, None);
+
+    child.complete_with_result(&42i64).await.unwrap();
+    let outcome: Option<ChildOutcome<i64>> = parent
+        .await_child("child", 0, Some(Duration::from_secs(5)))
+        .await
+        .unwrap();
+    assert_eq!(outcome, Some(ChildOutcome::Completed(Some(42))));
+
+    let children = client
+        .raw()
+        .list_child_workflows(ListChildWorkflowsRequest {
+            workflow_id: "parent".to_string(),
+            namespace: String::new(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .children;
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0].workflow_id, "child");
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn failed_child_is_reported_to_its_parent() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let mut parent = client
+        .start_workflow("parent", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let child = parent
+        .start_child("child", WorkflowOptions::default())
+        .await
+        .unwrap();
+    child.fail().await.unwrap();
+    let outcome: Option<ChildOutcome<i64>> = parent
+        .await_child("child", 0, Some(Duration::from_secs(5)))
+        .await
+        .unwrap();
+    assert_eq!(outcome, Some(ChildOutcome::Failed));
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn child_without_parent_position_is_rejected() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    client
+        .start_workflow("parent", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let requests = [(Some("parent".to_string()), None), (None, Some(0))];
+    for (parent_workflow_id, parent_position) in requests {
+        let status = client
+            .raw()
+            .workflow_start(WorkflowStartRequest {
+                workflow_id: "child".to_string(),
+                parent_workflow_id,
+                parent_position,
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    let error = client.workflow_status("child").await.unwrap_err();
+    assert_eq!(error.reason(), Some("workflow_not_found"));
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rejected_child_start_leaves_the_parent_position_free() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    client
+        .start_workflow("child", WorkflowOptions::default())
+        .await
+        .unwrap()
+        .fail()
+        .await
+        .unwrap();
+    let mut parent = client
+        .start_workflow("parent", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let error = parent
+        .start_child("child", WorkflowOptions::default())
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(error.reason(), Some("workflow_terminated"));
+
+    // the failed start took nothing, so the parent runs a step at the position instead
+    let value = parent
+        .step("fallback", || async { Ok::<_, std::io::Error>(7i64) })
+        .await
+        .unwrap();
+    assert_eq!(value, 7);
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_the_current_worker_of_a_running_parent_starts_children() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let mut superseded = client
+        .start_workflow("parent", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let current = client
+        .start_workflow("parent", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let error = superseded
+        .start_child("child", WorkflowOptions::default())
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(error.reason(), Some("fencing_token_expired"));
+
+    current.complete().await.unwrap();
+    let status = client
+        .raw()
+        .workflow_start(WorkflowStartRequest {
+            workflow_id: "child".to_string(),
+            parent_workflow_id: Some("parent".to_string()),
+            parent_position: Some(0),
+            parent_fencing_token: Some(i64::MAX),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+    assert_eq!(status.message(), "workflow_terminated");
+
+    let error = client.workflow_status("child").await.unwrap_err();
+    assert_eq!(error.reason(), Some("workflow_not_found"));
+    engine.shutdown().await;
+}
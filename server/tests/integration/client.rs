@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use idempotency_client::proto::LeaseCheckpointRequest;
+use idempotency_client::proto::lease_checkpoint_response::Response;
+use idempotency_client::{ClientError, StepOptions, WorkflowOptions};
+
+use crate::common::TestEngine;
+
+fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn renewed_lease_outlives_its_lease_timeout() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let mut workflow = client
+        .start_workflow("renew", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let renewer = workflow.lease_renewer();
+    let other_worker = client.clone();
+    let fencing_token = workflow.fencing_token();
+
+    let options = StepOptions {
+        lease_timeout: 200,
+        wait_timeout: None,
+    };
+    let value: i64 = workflow
+        .step_with_options("long", options, || async {
+            let expire_at = renewer.renew(10_000).await?;
+            assert!(expire_at >= now_millis() + 5_000);
+            tokio::time::sleep(Duration::from_millis(400)).await;
+            // the original lease timed out, but the renewed one still keeps other workers out
+            let response = other_worker
+                .raw()
+                .lease_checkpoint(LeaseCheckpointRequest {
+                    workflow_id: "renew".to_string(),
+                    fencing_token,
+                    lease_timeout: 200,
+                    position: 0,
+                    idempotency_key: "long".to_string(),
+                    task_name: Some("long".to_string()),
+                    ..Default::default()
+                })
+                .await?
+                .into_inner();
+            assert!(matches!(
+                response.response,
+                Some(Response::RemainingLeaseTimeout(_))
+            ));
+            Ok::<_, ClientError>(7)
+        })
+        .await
+        .unwrap();
+    assert_eq!(value, 7);
+
+    // the step was checkpointed, so there is no lease left to renew
+    let error = renewer.renew(10_000).await.unwrap_err();
+    assert_eq!(error.reason(), Some("leased_checkpoint_not_found"));
+
+    workflow.complete().await.unwrap();
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn branch_renews_the_lease_of_its_own_position() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let mut workflow = client
+        .start_workflow("renew-branch", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let mut branches = workflow.branches(2);
+    let mut branch = branches.pop().unwrap();
+    let renewer = branch.lease_renewer();
+    let renewed: i64 = branch
+        .step("renewing", || async { renewer.renew(5_000).await })
+        .await
+        .unwrap();
+    assert!(renewed > now_millis());
+    engine.shutdown().await;
+}
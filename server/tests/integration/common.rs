@@ -0,0 +1,79 @@
+use std::net::TcpListener;
+use std::path::PathBuf;
+
+use idempotency_client::WorkflowClient;
+use idempotency_server::database::server::Server;
+use idempotency_server::rpc_server::auth::AuthConfig;
+use idempotency_server::{Engine, EngineBuilder, EngineConfig};
+use tonic::transport::Server as GrpcServer;
+use uuid::Uuid;
+
+/// A single node engine with its own data directory and ports, so tests can run in parallel.
+pub struct TestEngine {
+    pub engine: Engine,
+    data_dir: PathBuf,
+}
+
+fn free_addr() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+impl TestEngine {
+    pub async fn start() -> Self {
+        Self::start_with_auth(AuthConfig::default()).await
+    }
+
+    pub async fn start_with_auth(auth: AuthConfig) -> Self {
+        let data_dir = std::env::temp_dir().join(format!("idempotency-test-{}", Uuid::new_v4()));
+        let node = Server {
+            id: 1,
+            addr_api: free_addr(),
+            addr_raft: free_addr(),
+        };
+        let engine = EngineBuilder::new()
+            .config(EngineConfig {
+                node: node.clone(),
+                nodes: vec![node],
+                data_dir: data_dir.to_string_lossy().to_string(),
+                hiqlite_config_path: "hiqlite.toml".to_string(),
+                auth,
+                tls: None,
+            })
+            .cleanup_scheduler(false)
+            .build()
+            .await
+            .unwrap();
+        Self { engine, data_dir }
+    }
+
+    pub fn client(&self) -> &hiqlite::Client {
+        self.engine.client()
+    }
+
+    ///
+    /// Serves the gRPC API on a free port and returns a client connected to it.
+    pub async fn connect(&self) -> WorkflowClient {
+        let addr = free_addr();
+        let service = self.engine.grpc_service();
+        let listen_addr = addr.parse().unwrap();
+        tokio::spawn(
+            GrpcServer::builder()
+                .add_service(service)
+                .serve(listen_addr),
+        );
+        // the listener binds in the spawned task
+        for _ in 0..50 {
+            if let Ok(client) = WorkflowClient::connect(format!("http://{addr}")).await {
+                return client;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        panic!("the gRPC server did not start on {addr}");
+    }
+
+    pub async fn shutdown(self) {
+        self.engine.shutdown().await.unwrap();
+        let _ = std::fs::remove_dir_all(&self.data_dir);
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use idempotency_client::WorkflowOptions;
+use idempotency_client::proto::RegisterCompensationRequest;
+use tonic::Code;
+
+use crate::common::TestEngine;
+
+const COMPENSATED: i64 = 7;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn compensations_run_last_step_first() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let mut workflow = client
+        .start_workflow("trip", WorkflowOptions::default())
+        .await
+        .unwrap();
+    for booking in ["flight", "hotel", "car"] {
+        let id: String = workflow
+            .step(booking, || async move {
+                Ok::<_, std::io::Error>(format!("{booking}-1"))
+            })
+            .await
+            .unwrap();
+        workflow
+            .register_compensation(&format!("cancel-{booking}"), &id)
+            .await
+            .unwrap();
+    }
+
+    let compensator = client.compensate_workflow("trip").await.unwrap();
+    let undone = Arc::new(Mutex::new(Vec::new()));
+    compensator
+        .run(|compensation| {
+            let undone = undone.clone();
+            let id: String = compensator.payload(&compensation).unwrap();
+            async move {
+                undone.lock().unwrap().push((compensation.name, id));
+                Ok::<_, std::io::Error>(())
+            }
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        *undone.lock().unwrap(),
+        vec![
+            ("cancel-car".to_string(), "car-1".to_string()),
+            ("cancel-hotel".to_string(), "hotel-1".to_string()),
+            ("cancel-flight".to_string(), "flight-1".to_string()),
+        ]
+    );
+    let status = client.workflow_status("trip").await.unwrap();
+    assert_eq!(status.status, COMPENSATED);
+
+    // the workflow was unwound, so it can't be started again
+    let error = client
+        .start_workflow("trip", WorkflowOptions::default())
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(error.reason(), Some("workflow_terminated"));
+
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn compensations_are_only_registered_for_recorded_steps() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let mut workflow = client
+        .start_workflow("trip", WorkflowOptions::default())
+        .await
+        .unwrap();
+    // a started child is undone before it finished
+    workflow
+        .start_child("booking", WorkflowOptions::default())
+        .await
+        .unwrap();
+    workflow
+        .register_compensation("cancel-booking", &"booking")
+        .await
+        .unwrap();
+
+    // a timer records no step that could be undone
+    assert!(
+        !workflow
+            .sleep("wait-for-checkin", Duration::from_secs(60))
+            .await
+            .unwrap()
+    );
+    let status = client
+        .raw()
+        .register_compensation(RegisterCompensationRequest {
+            workflow_id: "trip".to_string(),
+            fencing_token: workflow.fencing_token(),
+            position: workflow.position(),
+            name: "cancel-checkin".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+    assert_eq!(status.message(), "checkpoint_not_found");
+    engine.shutdown().await;
+}
@@ -0,0 +1,75 @@
+use idempotency_server::events::lease_events::LeaseEvent;
+use idempotency_server::events::signal_events::SignalEvent;
+use idempotency_server::events::timer_events::TimerEvent;
+use idempotency_server::events::workflow_events::{
+    EVENT_VERSION, WorkflowEvent, decode_workflow_event, encode_workflow_event,
+};
+use serde::Serialize;
+
+/// The lease event as nodes sent it before events were versioned.
+#[derive(Serialize)]
+struct LegacyLeaseEvent {
+    namespace: String,
+    workflow_id: String,
+    position: i64,
+}
+
+fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+    bincode::serde::encode_to_vec(value, bincode::config::legacy()).unwrap()
+}
+
+#[test]
+fn versioned_events_are_decoded() {
+    let bytes = encode_workflow_event(&WorkflowEvent::Lease(LeaseEvent {
+        namespace: "orders".to_string(),
+        workflow_id: "order-42".to_string(),
+        branch: "3.1".to_string(),
+        position: 2,
+    }));
+    let Some(WorkflowEvent::Lease(event)) = decode_workflow_event(&bytes).unwrap() else {
+        panic!("expected a lease event");
+    };
+    assert_eq!(
+        (event.namespace.as_str(), event.workflow_id.as_str()),
+        ("orders", "order-42")
+    );
+    assert_eq!((event.branch.as_str(), event.position), ("3.1", 2));
+
+    let bytes = encode_workflow_event(&WorkflowEvent::Signal(SignalEvent {
+        namespace: "orders".to_string(),
+        workflow_id: "order-42".to_string(),
+        name: "approved".to_string(),
+    }));
+    let Some(WorkflowEvent::Signal(event)) = decode_workflow_event(&bytes).unwrap() else {
+        panic!("expected a signal event");
+    };
+    assert_eq!(event.name, "approved");
+
+    let bytes = encode_workflow_event(&WorkflowEvent::Timer(TimerEvent {
+        namespace: "orders".to_string(),
+    }));
+    let Some(WorkflowEvent::Timer(event)) = decode_workflow_event(&bytes).unwrap() else {
+        panic!("expected a timer event");
+    };
+    assert_eq!(event.namespace, "orders");
+}
+
+#[test]
+fn lease_events_of_older_nodes_are_decoded_at_the_top_level() {
+    let bytes = encode(&LegacyLeaseEvent {
+        namespace: "default".to_string(),
+        workflow_id: "order-42".to_string(),
+        position: 5,
+    });
+    let Some(WorkflowEvent::Lease(event)) = decode_workflow_event(&bytes).unwrap() else {
+        panic!("expected a lease event");
+    };
+    assert_eq!(event.workflow_id, "order-42");
+    assert_eq!((event.branch.as_str(), event.position), ("", 5));
+}
+
+#[test]
+fn events_of_newer_versions_are_skipped() {
+    let bytes = encode(&(u64::MAX, EVENT_VERSION + 1, "a future event"));
+    assert!(decode_workflow_event(&bytes).unwrap().is_none());
+}
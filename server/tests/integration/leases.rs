@@ -0,0 +1,248 @@
+use idempotency_client::WorkflowOptions;
+use idempotency_client::proto::lease_checkpoint_response::Response;
+use idempotency_client::proto::{CheckPointRequest, LeaseCheckpointRequest};
+use idempotency_server::helpers::branch::TOP_LEVEL_BRANCH;
+use idempotency_server::helpers::errors::WorkflowError;
+use idempotency_server::repositories::checkpoints::{create_checkpoint, reserve_idempotency_key};
+use idempotency_server::repositories::lease_checkpoint::lease_checkpoint;
+use idempotency_server::repositories::timers::create_or_get_timer;
+use idempotency_server::schema::checkpoint::NewCheckpoint;
+use idempotency_server::schema::leased_checkpoint::{LeaseGrant, LeaseRequest};
+
+use crate::common::TestEngine;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn lease_is_not_granted_once_the_position_was_checkpointed() {
+    let engine = TestEngine::start().await;
+    let client = engine.client();
+    // a worker that read the position before another one checkpointed it still sends its grant
+    create_checkpoint(
+        client,
+        "default",
+        "raced",
+        TOP_LEVEL_BRANCH,
+        0,
+        NewCheckpoint {
+            idempotency_key: "step".to_string(),
+            value: vec![1],
+            task_name: Some("step".to_string()),
+        },
+    )
+    .await
+    .unwrap();
+    let grant = lease_checkpoint(
+        client,
+        "default",
+        "raced",
+        TOP_LEVEL_BRANCH,
+        0,
+        LeaseRequest {
+            lease_timeout: 1_000,
+            fencing_token: 1,
+            worker_id: Some("late-worker".to_string()),
+        },
+    )
+    .await
+    .unwrap();
+    assert!(matches!(grant, LeaseGrant::Recorded));
+
+    // a position holding only its idempotency key still has to run its task
+    reserve_idempotency_key(
+        client,
+        "default",
+        "raced",
+        TOP_LEVEL_BRANCH,
+        1,
+        "key".to_string(),
+        None,
+    )
+    .await
+    .unwrap();
+    let grant = lease_checkpoint(
+        client,
+        "default",
+        "raced",
+        TOP_LEVEL_BRANCH,
+        1,
+        LeaseRequest {
+            lease_timeout: 1_000,
+            fencing_token: 1,
+            worker_id: Some("worker".to_string()),
+        },
+    )
+    .await
+    .unwrap();
+    assert!(matches!(grant, LeaseGrant::Granted(_)));
+
+    // a timer records no checkpoint, but its position is never leased to a step either
+    create_or_get_timer(
+        client,
+        "default",
+        "raced",
+        TOP_LEVEL_BRANCH,
+        2,
+        "nap",
+        i64::MAX,
+    )
+    .await
+    .unwrap();
+    let grant = lease_checkpoint(
+        client,
+        "default",
+        "raced",
+        TOP_LEVEL_BRANCH,
+        2,
+        LeaseRequest {
+            lease_timeout: 1_000,
+            fencing_token: 1,
+            worker_id: Some("worker".to_string()),
+        },
+    )
+    .await
+    .unwrap();
+    assert!(matches!(grant, LeaseGrant::Recorded));
+    engine.shutdown().await;
+}
+
+fn lease_request(fencing_token: i64, position: i64, worker_id: &str) -> LeaseCheckpointRequest {
+    LeaseCheckpointRequest {
+        workflow_id: "contended".to_string(),
+        fencing_token,
+        lease_timeout: 10_000,
+        position,
+        idempotency_key: "step".to_string(),
+        task_name: Some("step".to_string()),
+        worker_id: Some(worker_id.to_string()),
+        ..Default::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn checkpointed_position_is_never_leased_again() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let workflow = client
+        .start_workflow("contended", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let fencing_token = workflow.fencing_token();
+
+    for position in 0..20 {
+        let granted = client
+            .raw()
+            .lease_checkpoint(lease_request(fencing_token, position, "owner"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(granted.response, None);
+
+        // the other worker keeps asking for the position while the owner checkpoints it
+        let contender = client.clone();
+        let contending = tokio::spawn(async move {
+            loop {
+                let response = contender
+                    .raw()
+                    .lease_checkpoint(lease_request(fencing_token, position, "contender"))
+                    .await
+                    .unwrap()
+                    .into_inner();
+                match response.response {
+                    Some(Response::RemainingLeaseTimeout(_)) => continue,
+                    Some(Response::Value(value)) => return Some(value),
+                    None => return None,
+                }
+            }
+        });
+        client
+            .raw()
+            .checkpoint(CheckPointRequest {
+                workflow_id: "contended".to_string(),
+                fencing_token,
+                position,
+                value: vec![position as u8],
+                idempotency_key: "step".to_string(),
+                task_name: Some("step".to_string()),
+                worker_id: Some("owner".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let value = contending.await.unwrap();
+        assert_eq!(value, Some(vec![position as u8]), "position {position}");
+    }
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn checkpoint_of_another_step_is_rejected() {
+    let engine = TestEngine::start().await;
+    let client = engine.client();
+    let step = |idempotency_key: &str, task_name: &str| NewCheckpoint {
+        idempotency_key: idempotency_key.to_string(),
+        value: vec![1],
+        task_name: Some(task_name.to_string()),
+    };
+    reserve_idempotency_key(
+        client,
+        "default",
+        "replayed",
+        TOP_LEVEL_BRANCH,
+        0,
+        "reserved".to_string(),
+        Some("charge".to_string()),
+    )
+    .await
+    .unwrap();
+    for (idempotency_key, task_name) in [("other", "charge"), ("reserved", "refund")] {
+        let error = create_checkpoint(
+            client,
+            "default",
+            "replayed",
+            TOP_LEVEL_BRANCH,
+            0,
+            step(idempotency_key, task_name),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            WorkflowError::NonDeterministicCheckpoint(_)
+        ));
+    }
+
+    let checkpoint = || step("reserved", "charge");
+    let written = create_checkpoint(
+        client,
+        "default",
+        "replayed",
+        TOP_LEVEL_BRANCH,
+        0,
+        checkpoint(),
+    );
+    assert!(written.await.unwrap());
+    // a retry of the step is no write, but no mismatch either
+    let written = create_checkpoint(
+        client,
+        "default",
+        "replayed",
+        TOP_LEVEL_BRANCH,
+        0,
+        checkpoint(),
+    );
+    assert!(!written.await.unwrap());
+    let error = create_checkpoint(
+        client,
+        "default",
+        "replayed",
+        TOP_LEVEL_BRANCH,
+        0,
+        step("other", "charge"),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(
+        error,
+        WorkflowError::NonDeterministicCheckpoint(_)
+    ));
+    engine.shutdown().await;
+}
@@ -0,0 +1,13 @@
+mod auth;
+mod branches;
+mod child_workflows;
+mod client;
+mod common;
+mod compensations;
+mod events;
+mod leases;
+mod quotas;
+mod signals;
+mod timers;
+mod tls;
+mod workflows;
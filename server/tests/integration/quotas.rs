@@ -0,0 +1,219 @@
+use std::time::Duration;
+
+use idempotency_client::WorkflowOptions;
+use idempotency_client::proto::{
+    CheckPointRequest, CreateNamespaceRequest, GetNamespaceUsageRequest, GetWorkflowHistoryRequest,
+    LeaseCheckpointRequest, NamespaceQuotas,
+};
+
+use crate::common::TestEngine;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn running_workflows_are_limited_but_restarts_are_not() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    client
+        .raw()
+        .create_namespace(CreateNamespaceRequest {
+            name: "limited".to_string(),
+            default_retention: 60_000,
+            quotas: Some(NamespaceQuotas {
+                max_running_workflows: Some(1),
+                ..Default::default()
+            }),
+        })
+        .await
+        .unwrap();
+    let client = client.with_namespace("limited");
+
+    client
+        .start_workflow("first", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let error = client
+        .start_workflow("second", WorkflowOptions::default())
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(error.reason(), Some("quota_exceeded"));
+    client
+        .start_workflow("first", WorkflowOptions::default())
+        .await
+        .unwrap();
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn usage_counts_stored_checkpoint_bytes() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    client
+        .raw()
+        .create_namespace(CreateNamespaceRequest {
+            name: "usage".to_string(),
+            default_retention: 60_000,
+            quotas: None,
+        })
+        .await
+        .unwrap();
+    let client = client.with_namespace("usage");
+    let mut workflow = client
+        .start_workflow("stored", WorkflowOptions::default())
+        .await
+        .unwrap();
+    workflow
+        .step("payload", || async {
+            Ok::<_, std::io::Error>(vec![7u8; 100])
+        })
+        .await
+        .unwrap();
+
+    let usage = client
+        .raw()
+        .get_namespace_usage(GetNamespaceUsageRequest {
+            name: "usage".to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(usage.running_workflows, 1);
+    assert!(usage.stored_bytes >= 100);
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn retried_checkpoint_is_not_counted_twice() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    client
+        .raw()
+        .create_namespace(CreateNamespaceRequest {
+            name: "stored".to_string(),
+            default_retention: 60_000,
+            quotas: Some(NamespaceQuotas {
+                max_stored_bytes: Some(250),
+                ..Default::default()
+            }),
+        })
+        .await
+        .unwrap();
+    let client = client.with_namespace("stored");
+    let mut workflow = client
+        .start_workflow("retried", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let value = vec![1u8; 100];
+    let encoded = workflow
+        .step("first", || async { Ok::<_, std::io::Error>(value.clone()) })
+        .await
+        .unwrap();
+    assert_eq!(encoded.len(), 100);
+    let history = client
+        .raw()
+        .get_workflow_history(GetWorkflowHistoryRequest {
+            workflow_id: "retried".to_string(),
+            include_values: true,
+            namespace: "stored".to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    let stored_value = history.checkpoints[0].value.clone().unwrap();
+
+    // a retry whose response got lost writes the same position again
+    client
+        .raw()
+        .checkpoint(CheckPointRequest {
+            workflow_id: "retried".to_string(),
+            namespace: "stored".to_string(),
+            fencing_token: workflow.fencing_token(),
+            position: 0,
+            value: stored_value,
+            idempotency_key: "first".to_string(),
+            task_name: Some("first".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    workflow
+        .step("second", || async {
+            Ok::<_, std::io::Error>(value.clone())
+        })
+        .await
+        .unwrap();
+    let usage = client
+        .raw()
+        .get_namespace_usage(GetNamespaceUsageRequest {
+            name: "stored".to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(usage.stored_bytes < 250);
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rejected_stale_checkpoint_does_not_use_up_the_rate() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    client
+        .raw()
+        .create_namespace(CreateNamespaceRequest {
+            name: "throttled".to_string(),
+            default_retention: 60_000,
+            quotas: Some(NamespaceQuotas {
+                max_checkpoints_per_second: Some(1),
+                ..Default::default()
+            }),
+        })
+        .await
+        .unwrap();
+    let client = client.with_namespace("throttled");
+    let stale = client
+        .start_workflow("zombie", WorkflowOptions::default())
+        .await
+        .unwrap();
+    client
+        .raw()
+        .lease_checkpoint(LeaseCheckpointRequest {
+            workflow_id: "zombie".to_string(),
+            namespace: "throttled".to_string(),
+            fencing_token: stale.fencing_token(),
+            lease_timeout: 1,
+            position: 0,
+            idempotency_key: "step".to_string(),
+            task_name: Some("step".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let mut current = client
+        .start_workflow("zombie", WorkflowOptions::default())
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    // the superseded worker's lease expired, so its checkpoint is rejected before the rate is checked
+    let status = client
+        .raw()
+        .checkpoint(CheckPointRequest {
+            workflow_id: "zombie".to_string(),
+            namespace: "throttled".to_string(),
+            fencing_token: stale.fencing_token(),
+            position: 0,
+            value: vec![1],
+            idempotency_key: "step".to_string(),
+            task_name: Some("step".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.message(), "fencing_token_expired");
+    current
+        .step("step", || async { Ok::<_, std::io::Error>(2i64) })
+        .await
+        .unwrap();
+    engine.shutdown().await;
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use idempotency_client::WorkflowOptions;
+
+use crate::common::TestEngine;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn signal_is_delivered_once_and_replayed() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let mut workflow = client
+        .start_workflow("approval", WorkflowOptions::default())
+        .await
+        .unwrap();
+
+    let first_id = client
+        .signal_workflow(
+            "approval",
+            "approved",
+            &"alice",
+            Some("webhook-1".to_string()),
+        )
+        .await
+        .unwrap();
+    let retried_id = client
+        .signal_workflow(
+            "approval",
+            "approved",
+            &"alice",
+            Some("webhook-1".to_string()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(first_id, retried_id);
+
+    let approver: Option<String> = workflow.await_signal("approved", None).await.unwrap();
+    assert_eq!(approver.as_deref(), Some("alice"));
+    // the retried signal was not stored a second time
+    let next: Option<String> = workflow
+        .await_signal("approved", Some(Duration::from_millis(100)))
+        .await
+        .unwrap();
+    assert_eq!(next, None);
+
+    let mut replay = client
+        .start_workflow("approval", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let approver: Option<String> = replay.await_signal("approved", None).await.unwrap();
+    assert_eq!(approver.as_deref(), Some("alice"));
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn awaiting_signal_wakes_up_when_it_is_sent() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let mut workflow = client
+        .start_workflow("waiting", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let sender = client.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        sender
+            .signal_workflow("waiting", "go", &1, None)
+            .await
+            .unwrap();
+    });
+    let value: Option<i64> = workflow
+        .await_signal("go", Some(Duration::from_secs(10)))
+        .await
+        .unwrap();
+    assert_eq!(value, Some(1));
+    engine.shutdown().await;
+}
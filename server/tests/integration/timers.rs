@@ -0,0 +1,101 @@
+use std::time::{Duration, Instant};
+
+use idempotency_client::WorkflowOptions;
+
+use crate::common::TestEngine;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn timer_fires_once_due_and_is_passed_on_restart() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let mut workflow = client
+        .start_workflow("sleeper", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let fired = workflow
+        .sleep("nap", Duration::from_millis(200))
+        .await
+        .unwrap();
+    assert!(!fired);
+    assert_eq!(workflow.position(), 0);
+
+    let timers = client.poll_due_timers(10, Some(5_000)).await.unwrap();
+    assert_eq!(timers.len(), 1);
+    assert_eq!(timers[0].workflow_id, "sleeper");
+    assert_eq!(timers[0].position, 0);
+    assert_eq!(timers[0].name, "nap");
+
+    let mut workflow = client
+        .start_workflow("sleeper", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let fired = workflow
+        .sleep("nap", Duration::from_millis(200))
+        .await
+        .unwrap();
+    assert!(fired);
+    assert_eq!(workflow.position(), 1);
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn step_or_signal_replayed_at_a_timer_position_is_non_deterministic() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let mut workflow = client
+        .start_workflow("sleeper", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let fired = workflow
+        .sleep("nap", Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert!(!fired);
+
+    // the timer records no checkpoint, yet a replay running something else there must fail
+    let mut workflow = client
+        .start_workflow("sleeper", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let error = workflow
+        .step("charge", || async { Ok::<_, std::io::Error>(1i64) })
+        .await
+        .unwrap_err();
+    assert_eq!(error.reason(), Some("non_deterministic_checkpoint_found"));
+
+    client
+        .signal_workflow("sleeper", "approval", &true, None)
+        .await
+        .unwrap();
+    let error = workflow
+        .await_signal::<bool>("approval", None)
+        .await
+        .unwrap_err();
+    assert_eq!(error.reason(), Some("non_deterministic_checkpoint_found"));
+    assert_eq!(workflow.position(), 0);
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn waiting_poll_is_woken_by_a_timer_scheduled_meanwhile() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let poller = client.clone();
+    let started = Instant::now();
+    let polling = tokio::spawn(async move { poller.poll_due_timers(10, Some(20_000)).await });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let mut workflow = client
+        .start_workflow("sleeper", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let fired = workflow
+        .sleep("nap", Duration::from_millis(300))
+        .await
+        .unwrap();
+    assert!(!fired);
+    let timers = polling.await.unwrap().unwrap();
+    assert_eq!(timers.len(), 1);
+    assert!(started.elapsed() < Duration::from_secs(10));
+    engine.shutdown().await;
+}
@@ -0,0 +1,26 @@
+use idempotency_server::rpc_server::tls::TlsConfig;
+
+fn path(path: &str) -> Option<String> {
+    Some(path.to_string())
+}
+
+#[test]
+fn tls_is_configured_by_cert_and_key_together() {
+    assert!(TlsConfig::from_paths(None, None, None).unwrap().is_none());
+    let tls = TlsConfig::from_paths(path("cert.pem"), path("key.pem"), path("ca.pem"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(tls.client_ca_path.as_deref(), Some("ca.pem"));
+}
+
+#[test]
+fn partial_tls_configuration_is_rejected() {
+    for (cert, key, client_ca) in [
+        (path("cert.pem"), None, None),
+        (None, path("key.pem"), None),
+        (None, None, path("ca.pem")),
+        (path("cert.pem"), None, path("ca.pem")),
+    ] {
+        assert!(TlsConfig::from_paths(cert, key, client_ca).is_err());
+    }
+}
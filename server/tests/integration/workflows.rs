@@ -0,0 +1,182 @@
+use idempotency_client::WorkflowOptions;
+use idempotency_client::proto::{CancelWorkflowRequest, CompleteWorkflowRequest};
+use idempotency_server::metrics::workflow_metrics::metrics;
+use idempotency_server::repositories::workflows_fencing_tokens::get_workflow_fencing_token;
+
+use crate::common::TestEngine;
+
+const COMPLETED: i64 = 1;
+const CANCELLED: i64 = 3;
+const COMPENSATED: i64 = 7;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn finished_workflows_are_not_started_again() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+
+    let workflow = client
+        .start_workflow("failed", WorkflowOptions::default())
+        .await
+        .unwrap();
+    workflow.fail().await.unwrap();
+    let error = client
+        .start_workflow("failed", WorkflowOptions::default())
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(error.reason(), Some("workflow_terminated"));
+
+    client
+        .start_workflow("cancelled", WorkflowOptions::default())
+        .await
+        .unwrap();
+    client
+        .raw()
+        .cancel_workflow(CancelWorkflowRequest {
+            workflow_id: "cancelled".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let error = client
+        .start_workflow("cancelled", WorkflowOptions::default())
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(error.reason(), Some("workflow_terminated"));
+    let status = client.workflow_status("cancelled").await.unwrap();
+    assert_eq!(status.status, CANCELLED);
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn compensated_workflow_is_not_completed_with_the_compensator_token() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let mut workflow = client
+        .start_workflow("unwound", WorkflowOptions::default())
+        .await
+        .unwrap();
+    workflow
+        .step("charge", || async { Ok::<_, std::io::Error>(1) })
+        .await
+        .unwrap();
+    workflow.register_compensation("refund", &1).await.unwrap();
+    let compensator = client.compensate_workflow("unwound").await.unwrap();
+    compensator
+        .run(|_| async { Ok::<_, std::io::Error>(()) })
+        .await
+        .unwrap();
+    let compensated = client.workflow_status("unwound").await.unwrap();
+    assert_eq!(compensated.status, COMPENSATED);
+
+    let error = client
+        .raw()
+        .complete_workflow(CompleteWorkflowRequest {
+            workflow_id: "unwound".to_string(),
+            fencing_token: compensator.fencing_token(),
+            expire_after: Some(1_000_000),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(error.message(), "workflow_terminated");
+    let status = client.workflow_status("unwound").await.unwrap();
+    assert_eq!(status.status, COMPENSATED);
+    assert_eq!(status.expire_at, compensated.expire_at);
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn completed_workflow_keeps_its_result_and_retention() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let workflow = client
+        .start_workflow("done", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let fencing_token = workflow.fencing_token();
+    workflow.complete_with_result(&1).await.unwrap();
+    let completed = client.workflow_status("done").await.unwrap();
+    assert_eq!(completed.status, COMPLETED);
+
+    let error = client
+        .raw()
+        .complete_workflow(CompleteWorkflowRequest {
+            workflow_id: "done".to_string(),
+            fencing_token,
+            expire_after: Some(1_000_000),
+            result: Some(vec![2]),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(error.message(), "workflow_terminated");
+    let status = client.workflow_status("done").await.unwrap();
+    assert_eq!(status.expire_at, completed.expire_at);
+
+    let restarted = client
+        .start_workflow("done", WorkflowOptions::default())
+        .await
+        .unwrap();
+    assert!(restarted.is_completed());
+    assert_eq!(restarted.result::<i64>().unwrap(), Some(1));
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn superseded_worker_is_counted_as_fencing_rejection() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let rejections = metrics()
+        .fencing_token_rejections_total
+        .with_label_values(&["heartbeat_workflow"]);
+    let superseded = client
+        .start_workflow("restarted", WorkflowOptions::default())
+        .await
+        .unwrap();
+    client
+        .start_workflow("restarted", WorkflowOptions::default())
+        .await
+        .unwrap();
+
+    let before = rejections.get();
+    let error = superseded.heartbeat().await.unwrap_err();
+    assert_eq!(error.reason(), Some("fencing_token_expired"));
+    assert!(rejections.get() > before);
+    engine.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cancelled_workflow_fences_its_worker_once() {
+    let engine = TestEngine::start().await;
+    let client = engine.connect().await;
+    let workflow = client
+        .start_workflow("fenced", WorkflowOptions::default())
+        .await
+        .unwrap();
+    let cancel = || async {
+        client
+            .raw()
+            .cancel_workflow(CancelWorkflowRequest {
+                workflow_id: "fenced".to_string(),
+                ..Default::default()
+            })
+            .await
+    };
+    let fencing_token = || get_workflow_fencing_token(engine.client(), "default", "fenced");
+
+    cancel().await.unwrap();
+    assert_eq!(
+        fencing_token().await.unwrap(),
+        Some(workflow.fencing_token() + 1)
+    );
+    // a rejected transition leaves the token alone
+    let status = cancel().await.unwrap_err();
+    assert_eq!(status.message(), "invalid_workflow_status_transition");
+    assert_eq!(
+        fencing_token().await.unwrap(),
+        Some(workflow.fencing_token() + 1)
+    );
+    engine.shutdown().await;
+}